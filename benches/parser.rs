@@ -0,0 +1,66 @@
+//! Бенчмарки токенізації з парсингом і обчислення на виразах різного розміру
+//!
+//! Токенізатор приватний, тож токенізація вимірюється лише разом із
+//! парсингом через `parse_expression`.
+//!
+//! Запуск: `cargo bench`
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use std::hint::black_box;
+use tree_parser::{evaluate, parse_and_eval, parse_expression};
+
+/// Вираз із `terms` доданків: `1 * (2 - 0.5) + 2 * (3 - 0.5) + ...`
+fn expression(terms: usize) -> String {
+    (1..=terms)
+        .map(|i| format!("{} * ({} - 0.5)", i, i + 1))
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+/// Малий, середній і великий вирази
+fn inputs() -> Vec<(&'static str, String)> {
+    vec![
+        ("small", expression(2)),
+        ("medium", expression(100)),
+        ("large", expression(2_000)),
+    ]
+}
+
+fn bench_tokenize_and_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tokenize_and_parse");
+    for (size, input) in inputs() {
+        group.bench_with_input(BenchmarkId::from_parameter(size), &input, |b, input| {
+            b.iter(|| parse_expression(black_box(input)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_evaluate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("evaluate");
+    for (size, input) in inputs() {
+        let expr = parse_expression(&input).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(size), &expr, |b, expr| {
+            b.iter(|| evaluate::<f64>(black_box(expr)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_parse_and_eval(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_and_eval");
+    for (size, input) in inputs() {
+        group.bench_with_input(BenchmarkId::from_parameter(size), &input, |b, input| {
+            b.iter(|| parse_and_eval(black_box(input)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_tokenize_and_parse,
+    bench_evaluate,
+    bench_parse_and_eval
+);
+criterion_main!(benches);
@@ -0,0 +1,17 @@
+//! Довільні рядки ніколи не мають спричиняти паніку парсера:
+//! результат — лише `Ok` або `Err`.
+//!
+//! Запуск: `cargo fuzz run parse_expression`
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|input: &str| {
+    if let Ok(expr) = tree_parser::parse_expression(input) {
+        let _ = tree_parser::try_evaluate(&expr);
+        let _ = expr.to_infix();
+    }
+    let _ = tree_parser::parse_and_eval(input);
+    let _ = tree_parser::parse_expression_bytes(input.as_bytes());
+});
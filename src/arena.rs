@@ -0,0 +1,178 @@
+//! Компактне представлення AST у вигляді арени вузлів
+
+use crate::{
+    EvalError, Expr, ParseError, Scope, apply_binary_op, apply_function, lookup, parse_expression,
+};
+
+/// Вузол арени: дочірні вузли задаються індексами у [`ExprArena`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprNode {
+    /// Числовий вузол
+    Number(f64),
+
+    /// Змінна; арена обчислюється без значень змінних
+    Variable(String),
+
+    /// Уявна одиниця `i`, що не має дійсного значення
+    #[cfg(feature = "complex")]
+    Imaginary,
+
+    /// Змінна `let`: значення вузла з заданим індексом
+    Local(u32),
+
+    /// Зв'язування `let`; значенням є значення тіла
+    Let {
+        /// Індекс значення змінної
+        value: u32,
+        /// Індекс тіла
+        body: u32,
+    },
+
+    /// Унарний мінус над вузлом із заданим індексом
+    Neg(u32),
+
+    /// Бінарна операція
+    BinaryOp {
+        /// Оператор: '+', '-', '*', '/', '^', '⊕', '<', '>', '&'
+        op: char,
+        /// Індекс лівого піддерева
+        left: u32,
+        /// Індекс правого піддерева
+        right: u32,
+    },
+
+    /// Виклик функції
+    Func {
+        /// Назва функції
+        name: String,
+        /// Індекси аргументів
+        args: Vec<u32>,
+    },
+}
+
+/// AST, збережене в одному векторі замість дерева з `Box`
+///
+/// Вузли лежать у постфіксному порядку: діти завжди стоять перед батьком,
+/// а корінь — останнім. Завдяки цьому обчислення — один прохід по вектору
+/// без переходів за вказівниками.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExprArena {
+    nodes: Vec<ExprNode>,
+}
+
+impl ExprArena {
+    /// Парсить вираз і зберігає його в арені
+    ///
+    /// # Приклад
+    /// ```
+    /// let arena = tree_parser::ExprArena::parse("2 + 3 * 4").unwrap();
+    /// assert_eq!(arena.evaluate(), 14.0);
+    /// ```
+    pub fn parse(input: &str) -> Result<ExprArena, ParseError> {
+        Ok(ExprArena::from_expr(&parse_expression(input)?))
+    }
+
+    /// Перетворює звичайне AST на арену
+    ///
+    /// Одиниці вимірювання (feature `units`) не зберігаються: величина стає числом.
+    pub fn from_expr(expr: &Expr) -> ExprArena {
+        let mut arena = ExprArena { nodes: Vec::new() };
+        arena.push(expr, &mut Vec::new());
+        arena
+    }
+
+    fn push(&mut self, expr: &Expr, scope: &mut Scope<u32>) -> u32 {
+        let node = match expr {
+            Expr::Number(n) => ExprNode::Number(*n),
+            #[cfg(feature = "units")]
+            Expr::Quantity { value, .. } => ExprNode::Number(*value),
+            Expr::Variable(name) => match lookup(scope, name) {
+                Some(&index) => ExprNode::Local(index),
+                None => ExprNode::Variable(name.clone()),
+            },
+            Expr::Let { name, value, body } => {
+                let value = self.push(value, scope);
+                scope.push((name.clone(), value));
+                let body = self.push(body, scope);
+                scope.pop();
+                ExprNode::Let { value, body }
+            }
+            #[cfg(feature = "complex")]
+            Expr::Imaginary => ExprNode::Imaginary,
+            Expr::Neg(operand) => ExprNode::Neg(self.push(operand, scope)),
+            Expr::BinaryOp { op, left, right } => ExprNode::BinaryOp {
+                op: *op,
+                left: self.push(left, scope),
+                right: self.push(right, scope),
+            },
+            Expr::Func { name, args } => ExprNode::Func {
+                name: name.clone(),
+                args: args.iter().map(|arg| self.push(arg, scope)).collect(),
+            },
+        };
+        self.nodes.push(node);
+        (self.nodes.len() - 1) as u32
+    }
+
+    /// Усі вузли арени у постфіксному порядку
+    pub fn nodes(&self) -> &[ExprNode] {
+        &self.nodes
+    }
+
+    /// Індекс кореня
+    pub fn root(&self) -> u32 {
+        (self.nodes.len() - 1) as u32
+    }
+
+    /// Обчислює значення виразу
+    ///
+    /// # Паніка
+    /// Панікує, якщо обчислення неможливе (див. [`ExprArena::try_evaluate`]).
+    pub fn evaluate(&self) -> f64 {
+        match self.try_evaluate() {
+            Ok(value) => value,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Обчислює значення виразу, повертаючи помилку замість паніки
+    ///
+    /// Помилки ті самі, що й у [`crate::try_evaluate`]: невідомий оператор
+    /// дає [`EvalError::UnknownOperator`].
+    ///
+    /// # Приклад
+    /// ```
+    /// use tree_parser::{EvalError, Expr, ExprArena};
+    ///
+    /// let expr = Expr::BinaryOp {
+    ///     op: '$',
+    ///     left: Box::new(Expr::Number(1.0)),
+    ///     right: Box::new(Expr::Number(2.0)),
+    /// };
+    /// let arena = ExprArena::from_expr(&expr);
+    /// assert_eq!(arena.try_evaluate(), Err(EvalError::UnknownOperator('$')));
+    /// ```
+    pub fn try_evaluate(&self) -> Result<f64, EvalError> {
+        let mut values: Vec<f64> = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let value = match node {
+                ExprNode::Number(n) => *n,
+                ExprNode::Variable(name) => return Err(EvalError::UndefinedVariable(name.clone())),
+                #[cfg(feature = "complex")]
+                ExprNode::Imaginary => return Err(EvalError::NotReal),
+                ExprNode::Local(index) => values[*index as usize],
+                ExprNode::Let { body, .. } => values[*body as usize],
+                ExprNode::Neg(operand) => -values[*operand as usize],
+                ExprNode::BinaryOp { op, left, right } => {
+                    apply_binary_op(*op, values[*left as usize], values[*right as usize])?
+                }
+                ExprNode::Func { name, args } => {
+                    let args: Vec<f64> = args.iter().map(|&i| values[i as usize]).collect();
+                    apply_function(name, &args)?
+                }
+            };
+            values.push(value);
+        }
+        Ok(values[self.root() as usize])
+    }
+}
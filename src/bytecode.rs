@@ -0,0 +1,193 @@
+//! Компіляція AST у байткод для стекової машини
+
+use crate::{EvalError, Expr, apply_binary_op, apply_function};
+
+/// Інструкція стекової машини [`run_bytecode`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    /// Покласти число на стек
+    Push(f64),
+    /// Покласти на стек значення змінної з заданим індексом
+    Load(usize),
+    /// Перенести вершину стеку в нову локальну змінну `let`
+    Bind,
+    /// Забрати останню локальну змінну
+    Unbind,
+    /// Покласти на стек значення локальної змінної з заданим індексом
+    LoadLocal(usize),
+    /// Замінити вершину стеку протилежним значенням
+    Neg,
+    /// Додати дві верхні величини
+    Add,
+    /// Відняти верхню величину від попередньої
+    Sub,
+    /// Перемножити дві верхні величини
+    Mul,
+    /// Поділити попередню величину на верхню
+    Div,
+    /// Інший бінарний оператор: '^', '⊕', '<', '>', '&'
+    Op(char),
+    /// Викликати функцію з `argc` верхніх величин
+    Call {
+        /// Назва функції
+        name: String,
+        /// Кількість аргументів
+        argc: usize,
+    },
+}
+
+impl Expr {
+    /// Компілює вираз у байткод для [`run_bytecode`]
+    ///
+    /// Змінна з назвою `vars[i]` читається з `i`-ї позиції значень, переданих
+    /// у [`run_bytecode`]. Змінна поза `vars` дає [`EvalError::UndefinedVariable`].
+    ///
+    /// # Приклад
+    /// ```
+    /// use tree_parser::{parse_expression, run_bytecode};
+    ///
+    /// let code = parse_expression("x * x + y").unwrap().to_bytecode(&["x", "y"]).unwrap();
+    /// assert_eq!(run_bytecode(&code, &[3.0, 1.0]), 10.0);
+    /// assert_eq!(run_bytecode(&code, &[4.0, 0.0]), 16.0);
+    /// ```
+    pub fn to_bytecode(&self, vars: &[&str]) -> Result<Vec<Instruction>, EvalError> {
+        let mut code = Vec::new();
+        self.compile(vars, &mut Vec::new(), &mut code)?;
+        Ok(code)
+    }
+
+    fn compile(
+        &self,
+        vars: &[&str],
+        locals: &mut Vec<String>,
+        code: &mut Vec<Instruction>,
+    ) -> Result<(), EvalError> {
+        match self {
+            Expr::Number(n) => code.push(Instruction::Push(*n)),
+            #[cfg(feature = "units")]
+            Expr::Quantity { .. } => return Err(EvalError::UnexpectedUnit(self.to_string())),
+            Expr::Variable(name) => {
+                if let Some(index) = locals.iter().rposition(|local| local == name) {
+                    code.push(Instruction::LoadLocal(index));
+                    return Ok(());
+                }
+                let index = vars
+                    .iter()
+                    .position(|var| var == name)
+                    .ok_or_else(|| EvalError::UndefinedVariable(name.clone()))?;
+                code.push(Instruction::Load(index));
+            }
+            #[cfg(feature = "complex")]
+            Expr::Imaginary => return Err(EvalError::NotReal),
+            Expr::Let { name, value, body } => {
+                value.compile(vars, locals, code)?;
+                code.push(Instruction::Bind);
+                locals.push(name.clone());
+                body.compile(vars, locals, code)?;
+                locals.pop();
+                code.push(Instruction::Unbind);
+            }
+            Expr::Neg(operand) => {
+                operand.compile(vars, locals, code)?;
+                code.push(Instruction::Neg);
+            }
+            Expr::BinaryOp { op, left, right } => {
+                left.compile(vars, locals, code)?;
+                right.compile(vars, locals, code)?;
+                code.push(match op {
+                    '+' => Instruction::Add,
+                    '-' => Instruction::Sub,
+                    '*' => Instruction::Mul,
+                    '/' => Instruction::Div,
+                    _ => Instruction::Op(*op),
+                });
+            }
+            Expr::Func { name, args } => {
+                for arg in args {
+                    arg.compile(vars, locals, code)?;
+                }
+                code.push(Instruction::Call {
+                    name: name.clone(),
+                    argc: args.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Виконує байткод зі значеннями змінних `vars` і повертає вершину стеку
+///
+/// # Паніка
+/// Панікує на некоректному байткоді та якщо обчислення неможливе
+/// (див. [`try_run_bytecode`]).
+pub fn run_bytecode(code: &[Instruction], vars: &[f64]) -> f64 {
+    match try_run_bytecode(code, vars) {
+        Ok(value) => value,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+/// Як [`run_bytecode`], але повертає помилку замість паніки
+///
+/// Індекс змінної поза `vars` чи поза локальними змінними та нестача
+/// операндів на стеку дають [`EvalError::InvalidBytecode`] з номером
+/// інструкції; помилки обчислення — ті самі, що й у [`crate::try_evaluate`].
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{EvalError, Instruction, try_run_bytecode};
+///
+/// let code = [Instruction::Load(0), Instruction::Load(1), Instruction::Add];
+/// assert_eq!(try_run_bytecode(&code, &[1.0, 2.0]), Ok(3.0));
+/// assert_eq!(try_run_bytecode(&code, &[1.0]), Err(EvalError::InvalidBytecode(1)));
+/// ```
+pub fn try_run_bytecode(code: &[Instruction], vars: &[f64]) -> Result<f64, EvalError> {
+    let mut stack: Vec<f64> = Vec::with_capacity(code.len());
+    let mut locals: Vec<f64> = Vec::new();
+    for (at, instruction) in code.iter().enumerate() {
+        let invalid = || EvalError::InvalidBytecode(at);
+        let value = match instruction {
+            Instruction::Bind => {
+                locals.push(pop(&mut stack, at)?);
+                continue;
+            }
+            Instruction::Unbind => {
+                locals.pop().ok_or_else(invalid)?;
+                continue;
+            }
+            Instruction::LoadLocal(index) => *locals.get(*index).ok_or_else(invalid)?,
+            Instruction::Push(n) => *n,
+            Instruction::Load(index) => *vars.get(*index).ok_or_else(invalid)?,
+            Instruction::Neg => -pop(&mut stack, at)?,
+            Instruction::Call { name, argc } => {
+                let start = stack.len().checked_sub(*argc).ok_or_else(invalid)?;
+                let args = stack.split_off(start);
+                apply_function(name, &args)?
+            }
+            Instruction::Add => pop_pair(&mut stack, at, |l, r| Ok(l + r))?,
+            Instruction::Sub => pop_pair(&mut stack, at, |l, r| Ok(l - r))?,
+            Instruction::Mul => pop_pair(&mut stack, at, |l, r| Ok(l * r))?,
+            Instruction::Div => pop_pair(&mut stack, at, |l, r| Ok(l / r))?,
+            Instruction::Op(op) => pop_pair(&mut stack, at, |l, r| apply_binary_op(*op, l, r))?,
+        };
+        stack.push(value);
+    }
+    pop(&mut stack, code.len())
+}
+
+/// Знімає вершину стеку; порожній стек — некоректна інструкція `at`
+fn pop(stack: &mut Vec<f64>, at: usize) -> Result<f64, EvalError> {
+    stack.pop().ok_or(EvalError::InvalidBytecode(at))
+}
+
+/// Знімає два операнди бінарної операції та застосовує до них `f`
+fn pop_pair(
+    stack: &mut Vec<f64>,
+    at: usize,
+    f: impl FnOnce(f64, f64) -> Result<f64, EvalError>,
+) -> Result<f64, EvalError> {
+    let r = pop(stack, at)?;
+    let l = pop(stack, at)?;
+    f(l, r)
+}
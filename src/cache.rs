@@ -0,0 +1,80 @@
+//! Кешування результатів парсингу та обчислення
+
+use crate::{ParseEvalError, parse_and_eval};
+use std::collections::{HashMap, VecDeque};
+
+/// Кеш результатів `parse_and_eval`, ключем якого є рядок виразу
+///
+/// Кеш обмежений за кількістю записів: коли він заповнений, новий запис
+/// витісняє той, до якого найдовше не зверталися (LRU). Помилки парсингу й
+/// обчислення не кешуються.
+#[derive(Debug, Clone)]
+pub struct EvalCache {
+    capacity: usize,
+    values: HashMap<String, f64>,
+    /// Ключі від найдавніше до найнещодавніше використаних
+    order: VecDeque<String>,
+}
+
+impl EvalCache {
+    /// Створює порожній кеш на `capacity` записів
+    pub fn new(capacity: usize) -> EvalCache {
+        EvalCache {
+            capacity,
+            values: HashMap::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Повертає значення виразу з кешу або обчислює й запам'ятовує його
+    ///
+    /// # Приклад
+    /// ```
+    /// let mut cache = tree_parser::EvalCache::new(16);
+    /// assert_eq!(cache.get_or_eval("2 + 3").unwrap(), 5.0);
+    /// assert!(cache.contains("2 + 3"));
+    /// ```
+    pub fn get_or_eval(&mut self, input: &str) -> Result<f64, ParseEvalError> {
+        if let Some(&value) = self.values.get(input) {
+            self.touch(input);
+            return Ok(value);
+        }
+
+        let value = parse_and_eval(input)?;
+        if self.capacity == 0 {
+            return Ok(value);
+        }
+        if self.values.len() == self.capacity
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.values.remove(&oldest);
+        }
+        self.values.insert(input.to_string(), value);
+        self.order.push_back(input.to_string());
+        Ok(value)
+    }
+
+    /// Чи є вираз у кеші
+    pub fn contains(&self, input: &str) -> bool {
+        self.values.contains_key(input)
+    }
+
+    /// Кількість записів у кеші
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Чи порожній кеш
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Позначає ключ як щойно використаний
+    fn touch(&mut self, input: &str) {
+        if let Some(index) = self.order.iter().position(|key| key == input)
+            && let Some(key) = self.order.remove(index)
+        {
+            self.order.push_back(key);
+        }
+    }
+}
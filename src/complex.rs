@@ -0,0 +1,182 @@
+//! Обчислення над комплексними числами (feature `complex`)
+
+use crate::evaluator::{Evaluator, eval_tree, leaf_error};
+use crate::{EvalError, Expr, apply_binary_op, apply_function};
+
+/// Комплексне число `re + im·i`
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    const ONE: Complex = Complex { re: 1.0, im: 0.0 };
+
+    fn real(re: f64) -> Complex {
+        Complex { re, im: 0.0 }
+    }
+
+    fn is_real(self) -> bool {
+        self.im == 0.0
+    }
+
+    fn add(self, other: Complex) -> Complex {
+        Complex {
+            re: self.re + other.re,
+            im: self.im + other.im,
+        }
+    }
+
+    fn sub(self, other: Complex) -> Complex {
+        Complex {
+            re: self.re - other.re,
+            im: self.im - other.im,
+        }
+    }
+
+    fn mul(self, other: Complex) -> Complex {
+        Complex {
+            re: self.re * other.re - self.im * other.im,
+            im: self.re * other.im + self.im * other.re,
+        }
+    }
+
+    fn div(self, other: Complex) -> Complex {
+        let norm = other.re * other.re + other.im * other.im;
+        Complex {
+            re: (self.re * other.re + self.im * other.im) / norm,
+            im: (self.im * other.re - self.re * other.im) / norm,
+        }
+    }
+
+    fn exp(self) -> Complex {
+        let scale = self.re.exp();
+        Complex {
+            re: scale * self.im.cos(),
+            im: scale * self.im.sin(),
+        }
+    }
+
+    fn ln(self) -> Complex {
+        Complex {
+            re: self.re.hypot(self.im).ln(),
+            im: self.im.atan2(self.re),
+        }
+    }
+
+    /// Головне значення кореня: дійсна частина невід'ємна
+    fn sqrt(self) -> Complex {
+        let norm = self.re.hypot(self.im);
+        Complex {
+            re: ((norm + self.re) / 2.0).sqrt(),
+            im: ((norm - self.re) / 2.0).sqrt().copysign(self.im),
+        }
+    }
+
+    /// Степінь: цілі показники — множенням, решта — через `exp(w · ln z)`
+    fn pow(self, exponent: Complex) -> Complex {
+        if self.is_real() && exponent.is_real() && (self.re >= 0.0 || exponent.re.fract() == 0.0) {
+            return Complex::real(self.re.powf(exponent.re));
+        }
+        if exponent.is_real() && exponent.re.fract() == 0.0 && exponent.re.abs() <= 64.0 {
+            let mut result = Complex::ONE;
+            for _ in 0..exponent.re.abs() as u32 {
+                result = result.mul(self);
+            }
+            return if exponent.re < 0.0 {
+                Complex::ONE.div(result)
+            } else {
+                result
+            };
+        }
+        if self == Complex::real(0.0) {
+            return Complex::real(0.0);
+        }
+        exponent.mul(self.ln()).exp()
+    }
+}
+
+/// Обчислює вираз над комплексними числами та повертає `(re, im)`
+///
+/// Уявна одиниця `i` дорівнює `(0, 1)`, а від'ємна основа з дробовим
+/// показником дає комплексний результат: `(-1) ^ (1 / 2)` = `i`. Крім
+/// вбудованих функцій, тут є `sqrt` — головне значення кореня, тож
+/// `sqrt(-1)` = `i`. Решта операторів і функції вимагають дійсних операндів.
+///
+/// # Паніка
+/// Панікує, якщо операція неможлива (див. [`try_evaluate_complex`]).
+///
+/// # Приклад
+/// ```
+/// let expr = tree_parser::parse_expression("i * i").unwrap();
+/// assert_eq!(tree_parser::evaluate_complex(&expr), (-1.0, 0.0));
+/// ```
+pub fn evaluate_complex(expr: &Expr) -> (f64, f64) {
+    match try_evaluate_complex(expr) {
+        Ok(value) => value,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+/// Обчислює вираз над комплексними числами, повертаючи помилку замість паніки
+///
+/// Помилки ті самі, що й у [`crate::try_evaluate`], а порівняння чи функція
+/// з комплексним операндом дає [`EvalError::NotReal`].
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{EvalError, parse_expression, try_evaluate_complex};
+///
+/// let expr = parse_expression("sqrt(-4) + 1").unwrap();
+/// assert_eq!(try_evaluate_complex(&expr), Ok((1.0, 2.0)));
+/// let expr = parse_expression("i < 1").unwrap();
+/// assert_eq!(try_evaluate_complex(&expr), Err(EvalError::NotReal));
+/// ```
+pub fn try_evaluate_complex(expr: &Expr) -> Result<(f64, f64), EvalError> {
+    eval_tree(expr, &mut ComplexEval, &mut Vec::new()).map(|z| (z.re, z.im))
+}
+
+struct ComplexEval;
+
+impl Evaluator for ComplexEval {
+    type Value = Complex;
+
+    fn number(&mut self, value: f64) -> Result<Complex, EvalError> {
+        Ok(Complex::real(value))
+    }
+
+    fn leaf(&mut self, leaf: &Expr) -> Result<Complex, EvalError> {
+        match leaf {
+            Expr::Imaginary => Ok(Complex { re: 0.0, im: 1.0 }),
+            other => Err(leaf_error(other)),
+        }
+    }
+
+    fn neg(&mut self, operand: Complex) -> Result<Complex, EvalError> {
+        Ok(Complex::real(0.0).sub(operand))
+    }
+
+    fn binary(&mut self, op: char, l: Complex, r: Complex) -> Result<Complex, EvalError> {
+        match op {
+            '+' => Ok(l.add(r)),
+            '-' => Ok(l.sub(r)),
+            '*' => Ok(l.mul(r)),
+            '/' => Ok(l.div(r)),
+            '^' => Ok(l.pow(r)),
+            _ if l.is_real() && r.is_real() => apply_binary_op(op, l.re, r.re).map(Complex::real),
+            _ => Err(EvalError::NotReal),
+        }
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Complex>) -> Result<Complex, EvalError> {
+        if let ("sqrt", [z]) = (name, args.as_slice()) {
+            return Ok(z.sqrt());
+        }
+        if !args.iter().all(|z| z.is_real()) {
+            return Err(EvalError::NotReal);
+        }
+        let reals: Vec<f64> = args.iter().map(|z| z.re).collect();
+        apply_function(name, &reals).map(Complex::real)
+    }
+}
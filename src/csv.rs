@@ -0,0 +1,76 @@
+//! Пакетне обчислення виразів зі стовпця CSV
+
+use crate::{format_number, parse_and_eval};
+use thiserror::Error;
+
+/// Можливі помилки обробки CSV
+#[derive(Error, Debug, PartialEq)]
+pub enum CsvError {
+    /// Вхід не містить рядка заголовка
+    #[error("CSV input has no header row")]
+    MissingHeader,
+
+    /// У заголовку немає потрібного стовпця
+    #[error("Column not found: {0}")]
+    MissingColumn(String),
+}
+
+/// Обчислює вираз зі стовпця `column` у кожному рядку CSV
+///
+/// Повертає той самий CSV з доданим стовпцем `result`. Рядок, вираз у якому
+/// не вдалося розібрати чи обчислити (або якого бракує), отримує значення `ERR`.
+/// Підтримується мінімальний CSV: поля через кому, поле в подвійних лапках
+/// може містити коми, а `""` всередині лапок означає одну лапку.
+///
+/// # Приклад
+/// ```
+/// let csv = "id,expr\n1,2 + 3\n2,2 +\n";
+/// let out = tree_parser::eval_csv(csv, "expr").unwrap();
+/// assert_eq!(out, "id,expr,result\n1,2 + 3,5\n2,2 +,ERR\n");
+/// ```
+pub fn eval_csv(input: &str, column: &str) -> Result<String, CsvError> {
+    let mut lines = input.lines();
+    let header = lines.next().ok_or(CsvError::MissingHeader)?;
+    let index = split_csv_line(header)
+        .iter()
+        .position(|name| name.trim() == column)
+        .ok_or_else(|| CsvError::MissingColumn(column.to_string()))?;
+
+    let mut output = format!("{},result\n", header);
+    for line in lines {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let cell = match split_csv_line(line).get(index) {
+            Some(expr) => match parse_and_eval(expr) {
+                Ok(value) => format_number(value),
+                Err(_) => "ERR".to_string(),
+            },
+            None => "ERR".to_string(),
+        };
+        output.push_str(&format!("{},{}\n", line, cell));
+    }
+    Ok(output)
+}
+
+/// Розбиває рядок CSV на поля
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(ch),
+        }
+    }
+    fields.push(field);
+    fields
+}
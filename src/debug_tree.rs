@@ -0,0 +1,216 @@
+//! Вкладений рядковий формат дерева для кешів на диску
+
+use crate::{Expr, ParseError};
+
+impl Expr {
+    /// Записує AST як вкладені списки рядків: `("+" ("*" "3" "4") "2")`
+    ///
+    /// Вузол — список із підпису й дітей, листок — рядок у лапках. Числа
+    /// починаються з цифри чи знака (`"-inf"`, `"+inf"` і `"+NaN"` для
+    /// нескінченностей і NaN), унарний мінус — `("-" x)` з одною дитиною,
+    /// `let` — `("let" "x" value body)`, виклик функції — `("max" a b)`.
+    /// Формат читається назад через [`Expr::from_debug_tree`].
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("3 * 4 + 2").unwrap();
+    /// assert_eq!(expr.to_debug_tree(), r#"("+" ("*" "3" "4") "2")"#);
+    /// ```
+    pub fn to_debug_tree(&self) -> String {
+        let mut out = String::new();
+        self.write_debug_tree(&mut out);
+        out
+    }
+
+    fn write_debug_tree(&self, out: &mut String) {
+        let (head, children): (String, Vec<&Expr>) = match self {
+            Expr::Number(n) if n.is_nan() => return push_quoted("+NaN", out),
+            Expr::Number(n) if *n == f64::INFINITY => return push_quoted("+inf", out),
+            Expr::Number(n) => return push_quoted(&n.to_string(), out),
+            #[cfg(feature = "units")]
+            Expr::Quantity { value, unit } => {
+                return push_quoted(&format!("{}{}", value, unit), out);
+            }
+            Expr::Variable(name) => return push_quoted(name, out),
+            #[cfg(feature = "complex")]
+            Expr::Imaginary => return push_quoted("i", out),
+            Expr::Let { name, value, body } => {
+                out.push_str("(\"let\" ");
+                push_quoted(name, out);
+                for child in [value, body] {
+                    out.push(' ');
+                    child.write_debug_tree(out);
+                }
+                out.push(')');
+                return;
+            }
+            Expr::Neg(operand) => ("-".to_string(), vec![operand]),
+            Expr::BinaryOp { op, left, right } => (op.to_string(), vec![left, right]),
+            Expr::Func { name, args } => (name.clone(), args.iter().collect()),
+        };
+        out.push('(');
+        push_quoted(&head, out);
+        for child in children {
+            out.push(' ');
+            child.write_debug_tree(out);
+        }
+        out.push(')');
+    }
+
+    /// Читає AST у форматі [`Expr::to_debug_tree`]
+    ///
+    /// Порушення формату дає [`ParseError::UnexpectedTokenAt`] з позицією
+    /// (у байтах) або [`ParseError::UnexpectedEnd`].
+    ///
+    /// # Приклад
+    /// ```
+    /// use tree_parser::{Expr, parse_expression};
+    ///
+    /// let expr = Expr::from_debug_tree(r#"("max" ("-" "x") "2.5")"#).unwrap();
+    /// assert_eq!(expr, parse_expression("max(-x, 2.5)").unwrap());
+    /// ```
+    pub fn from_debug_tree(input: &str) -> Result<Expr, ParseError> {
+        let mut reader = Reader { input, pos: 0 };
+        let expr = reader.node()?;
+        reader.skip_whitespace();
+        match reader.input[reader.pos..].chars().next() {
+            Some(c) => Err(reader.unexpected(c.to_string())),
+            None => Ok(expr),
+        }
+    }
+}
+
+/// Дописує рядок у лапках, екрануючи `"` і `\`
+fn push_quoted(text: &str, out: &mut String) {
+    out.push('"');
+    for c in text.chars() {
+        if c == '"' || c == '\\' {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out.push('"');
+}
+
+/// Читач формату [`Expr::to_debug_tree`]; `pos` — поточна позиція у байтах
+struct Reader<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.input[self.pos..].chars().next()
+    }
+
+    fn unexpected(&self, token: String) -> ParseError {
+        ParseError::UnexpectedTokenAt {
+            token,
+            position: self.pos,
+        }
+    }
+
+    /// Рядок у лапках разом із його позицією
+    fn quoted(&mut self) -> Result<(String, usize), ParseError> {
+        let start = self.pos;
+        match self.peek() {
+            Some('"') => self.pos += 1,
+            Some(c) => return Err(self.unexpected(c.to_string())),
+            None => return Err(ParseError::UnexpectedEnd),
+        }
+        let mut text = String::new();
+        let mut chars = self.input[self.pos..].char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok((text, start));
+                }
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => text.push(escaped),
+                    None => break,
+                },
+                _ => text.push(c),
+            }
+        }
+        Err(ParseError::UnexpectedEnd)
+    }
+
+    fn node(&mut self) -> Result<Expr, ParseError> {
+        if self.peek() != Some('(') {
+            let (text, position) = self.quoted()?;
+            return leaf(text, position);
+        }
+        self.pos += 1;
+        let (head, position) = self.quoted()?;
+        let mut children = Vec::new();
+        let name = if head == "let" {
+            Some(self.quoted()?.0)
+        } else {
+            None
+        };
+        while self.peek().is_some_and(|c| c != ')') {
+            children.push(self.node()?);
+        }
+        if self.peek().is_none() {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        self.pos += 1;
+
+        let mut chars = head.chars();
+        let op = chars
+            .next()
+            .filter(|c| !c.is_alphabetic() && chars.next().is_none());
+        match (name, op, <[Expr; 2]>::try_from(children)) {
+            (Some(name), _, Ok([value, body])) => Ok(Expr::Let {
+                name,
+                value: Box::new(value),
+                body: Box::new(body),
+            }),
+            (None, Some(op), Ok([left, right])) => Ok(Expr::BinaryOp {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            }),
+            (None, Some('-'), Err(children)) if children.len() == 1 => {
+                Ok(Expr::Neg(Box::new(children.into_iter().next().unwrap())))
+            }
+            (None, None, args) => Ok(Expr::Func {
+                name: head,
+                args: args.map_or_else(|args| args, Vec::from),
+            }),
+            _ => Err(ParseError::UnexpectedTokenAt {
+                token: head,
+                position,
+            }),
+        }
+    }
+}
+
+/// Листок: число, якщо починається з цифри чи знака, інакше змінна
+fn leaf(text: String, position: usize) -> Result<Expr, ParseError> {
+    #[cfg(feature = "complex")]
+    if text == "i" {
+        return Ok(Expr::Imaginary);
+    }
+    if !text.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '+') {
+        return Ok(Expr::Variable(text));
+    }
+    if let Ok(n) = text.parse() {
+        return Ok(Expr::Number(n));
+    }
+    #[cfg(feature = "units")]
+    if let Some((value, unit)) = crate::parse_quantity(&text) {
+        return Ok(Expr::Quantity { value, unit });
+    }
+    Err(ParseError::UnexpectedTokenAt {
+        token: text,
+        position,
+    })
+}
@@ -0,0 +1,122 @@
+//! Точне десяткове обчислення (feature `decimal`)
+
+use crate::evaluator::{Evaluator, eval_tree};
+use crate::{EvalError, Expr, apply_binary_op, apply_function, builtin_arity};
+use rust_decimal::prelude::*;
+
+/// Обчислює вираз у десятковій арифметиці без похибок двійкових `f64`
+///
+/// Літерали перетворюються на найкоротше десяткове число з тим самим
+/// значенням `f64`, тож `0.1` стає рівно `0.1`. `+ - * /`, порівняння та
+/// функції, крім тригонометричних (ті обчислюються у `f64`), точні; степінь
+/// допускає лише цілий показник. Переповнення та ділення на нуль дають
+/// [`EvalError::DecimalOverflow`].
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{Decimal, evaluate_decimal, parse_expression};
+///
+/// let expr = parse_expression("0.1 + 0.2").unwrap();
+/// assert_eq!(evaluate_decimal(&expr), Ok(Decimal::new(3, 1)));
+/// ```
+pub fn evaluate_decimal(expr: &Expr) -> Result<Decimal, EvalError> {
+    eval_tree(expr, &mut DecimalEval, &mut Vec::new())
+}
+
+struct DecimalEval;
+
+impl Evaluator for DecimalEval {
+    type Value = Decimal;
+
+    fn number(&mut self, value: f64) -> Result<Decimal, EvalError> {
+        to_decimal(value)
+    }
+
+    fn neg(&mut self, operand: Decimal) -> Result<Decimal, EvalError> {
+        Ok(-operand)
+    }
+
+    fn binary(&mut self, op: char, l: Decimal, r: Decimal) -> Result<Decimal, EvalError> {
+        apply_decimal_op(op, l, r)
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Decimal>) -> Result<Decimal, EvalError> {
+        let expected =
+            builtin_arity(name).ok_or_else(|| EvalError::UnknownFunction(name.to_string()))?;
+        match (name, args.as_slice()) {
+            ("abs", [a]) => Ok(a.abs()),
+            ("floor", [a]) => Ok(a.floor()),
+            ("ceil", [a]) => Ok(a.ceil()),
+            ("round", [a]) => {
+                Ok(a.round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero))
+            }
+            ("sin" | "cos" | "tan", [a]) => {
+                let value = a.to_f64().ok_or(EvalError::DecimalOverflow)?;
+                to_decimal(apply_function(name, &[value])?)
+            }
+            ("min", [a, b]) => Ok(*a.min(b)),
+            ("max", [a, b]) => Ok(*a.max(b)),
+            _ => Err(EvalError::WrongArity {
+                name: name.to_string(),
+                expected,
+                found: args.len(),
+            }),
+        }
+    }
+}
+
+fn to_decimal(value: f64) -> Result<Decimal, EvalError> {
+    Decimal::from_f64(value).ok_or(EvalError::DecimalOverflow)
+}
+
+fn apply_decimal_op(op: char, l: Decimal, r: Decimal) -> Result<Decimal, EvalError> {
+    let result = match op {
+        '+' => l.checked_add(r),
+        '-' => l.checked_sub(r),
+        '*' => l.checked_mul(r),
+        '/' => l.checked_div(r),
+        '^' => {
+            let exponent = r.to_i64().filter(|_| r.is_integer()).ok_or_else(|| {
+                EvalError::NonIntegerOperand {
+                    op,
+                    value: r.to_f64().unwrap_or(f64::NAN),
+                }
+            })?;
+            checked_powi(l, exponent)
+        }
+        '<' => Some(Decimal::from(u8::from(l < r))),
+        '>' => Some(Decimal::from(u8::from(l > r))),
+        '&' => Some(Decimal::from(u8::from(!l.is_zero() && !r.is_zero()))),
+        _ => {
+            // Решта операторів цілочисельні, тож f64 їх не спотворює
+            let value = apply_binary_op(
+                op,
+                l.to_f64().unwrap_or(f64::NAN),
+                r.to_f64().unwrap_or(f64::NAN),
+            )?;
+            return to_decimal(value);
+        }
+    };
+    result.ok_or(EvalError::DecimalOverflow)
+}
+
+/// Цілий степінь множенням із перевіркою переповнення
+fn checked_powi(base: Decimal, exponent: i64) -> Option<Decimal> {
+    let mut result = Decimal::ONE;
+    let mut square = base;
+    let mut n = exponent.unsigned_abs();
+    while n > 0 {
+        if n & 1 == 1 {
+            result = result.checked_mul(square)?;
+        }
+        n >>= 1;
+        if n > 0 {
+            square = square.checked_mul(square)?;
+        }
+    }
+    if exponent < 0 {
+        Decimal::ONE.checked_div(result)
+    } else {
+        Some(result)
+    }
+}
@@ -0,0 +1,119 @@
+//! Спільний обхід AST для обчислювачів
+
+use crate::{EvalError, Expr, Scope, lookup};
+
+/// Спосіб обчислення вузлів AST, див. [`eval_tree`]
+///
+/// Обхід дерева, зв'язування `let` і порядок обчислення реалізовані один
+/// раз, а реалізації цього трейту вирішують, що означають числа, оператори
+/// й виклики функцій. Решта методів — точки розширення з типовою поведінкою.
+pub(crate) trait Evaluator {
+    type Value: Clone;
+
+    fn number(&mut self, value: f64) -> Result<Self::Value, EvalError>;
+    fn neg(&mut self, operand: Self::Value) -> Result<Self::Value, EvalError>;
+    fn binary(
+        &mut self,
+        op: char,
+        left: Self::Value,
+        right: Self::Value,
+    ) -> Result<Self::Value, EvalError>;
+    fn call(&mut self, name: &str, args: Vec<Self::Value>) -> Result<Self::Value, EvalError>;
+
+    /// Листок, відмінний від числа: змінна, не зв'язана `let`, уявна
+    /// одиниця чи величина з одиницею; типово — помилка [`leaf_error`]
+    fn leaf(&mut self, leaf: &Expr) -> Result<Self::Value, EvalError> {
+        Err(leaf_error(leaf))
+    }
+
+    /// Значення `let` зі значенням змінної `value`; типово — значення тіла
+    fn let_in(&mut self, _value: Self::Value, body: Self::Value) -> Result<Self::Value, EvalError> {
+        Ok(body)
+    }
+
+    /// Викликається перед обчисленням кожного вузла
+    fn enter(&mut self, _expr: &Expr) -> Result<(), EvalError> {
+        Ok(())
+    }
+
+    /// Викликається з результатом кожного обчисленого вузла
+    fn leave(&mut self, _expr: &Expr, value: Self::Value) -> Result<Self::Value, EvalError> {
+        Ok(value)
+    }
+
+    /// Результат бінарної операції, відомий без обчислення операндів
+    fn shortcut(&mut self, _op: char, _right: &Expr) -> Option<Self::Value> {
+        None
+    }
+
+    /// Результат бінарної операції, відомий без обчислення правого операнда
+    fn shortcut_left(&mut self, _op: char, _left: &Self::Value) -> Option<Self::Value> {
+        None
+    }
+}
+
+/// Помилка для листка без числового значення
+pub(crate) fn leaf_error(leaf: &Expr) -> EvalError {
+    match leaf {
+        #[cfg(feature = "complex")]
+        Expr::Imaginary => EvalError::NotReal,
+        #[cfg(feature = "units")]
+        Expr::Quantity { .. } => EvalError::UnexpectedUnit(leaf.to_string()),
+        other => EvalError::UndefinedVariable(other.to_string()),
+    }
+}
+
+/// Обчислює `expr` за допомогою `e`; змінні `let` зберігаються в `scope`
+///
+/// Діти обчислюються в порядку [`Expr::at_path`]: значення `let` перед
+/// тілом, лівий операнд перед правим, аргументи зліва направо.
+pub(crate) fn eval_tree<E: Evaluator>(
+    expr: &Expr,
+    e: &mut E,
+    scope: &mut Scope<E::Value>,
+) -> Result<E::Value, EvalError> {
+    e.enter(expr)?;
+    let value = match expr {
+        Expr::Number(n) => e.number(*n)?,
+        #[cfg(feature = "units")]
+        Expr::Quantity { .. } => e.leaf(expr)?,
+        Expr::Variable(name) => match lookup(scope, name) {
+            Some(value) => value.clone(),
+            None => e.leaf(expr)?,
+        },
+        #[cfg(feature = "complex")]
+        Expr::Imaginary => e.leaf(expr)?,
+        Expr::Let { name, value, body } => {
+            let value = eval_tree(value, e, scope)?;
+            scope.push((name.clone(), value.clone()));
+            let body = eval_tree(body, e, scope);
+            scope.pop();
+            e.let_in(value, body?)?
+        }
+        Expr::Neg(operand) => {
+            let operand = eval_tree(operand, e, scope)?;
+            e.neg(operand)?
+        }
+        Expr::BinaryOp { op, left, right } => match e.shortcut(*op, right) {
+            Some(value) => value,
+            None => {
+                let l = eval_tree(left, e, scope)?;
+                match e.shortcut_left(*op, &l) {
+                    Some(value) => value,
+                    None => {
+                        let r = eval_tree(right, e, scope)?;
+                        e.binary(*op, l, r)?
+                    }
+                }
+            }
+        },
+        Expr::Func { name, args } => {
+            let values = args
+                .iter()
+                .map(|arg| eval_tree(arg, e, scope))
+                .collect::<Result<Vec<_>, _>>()?;
+            e.call(name, values)?
+        }
+    };
+    e.leave(expr, value)
+}
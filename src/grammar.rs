@@ -0,0 +1,102 @@
+//! Граматика парсера в EBNF
+
+use crate::{ADDITIVE_OPS, BUILTINS, COMPARISON_OPS, CONSTANTS, MULTIPLICATIVE_OPS, POWER_TOKENS};
+
+/// Повертає граматику виразів у записі EBNF
+///
+/// Оператори, вбудовані функції та константи беруться з тих самих таблиць,
+/// що й у парсера, а правила для уявної одиниці (`complex`) та одиниць
+/// вимірювання (`units`) з'являються лише з відповідними feature.
+/// Вбудовані функції згруповані за кількістю аргументів; інші назви
+/// функцій граматика теж дозволяє.
+///
+/// # Приклад
+/// ```
+/// let grammar = tree_parser::grammar();
+/// assert!(grammar.contains(r#"Expr       = Term { ("+" | "-") Term } ;"#));
+/// assert!(grammar.contains(r#""min" | "max""#));
+/// ```
+pub fn grammar() -> String {
+    let mut factor = vec!["Number"];
+    if cfg!(feature = "units") {
+        factor.push("Quantity");
+    }
+    factor.extend(["Let", "Call", "Constant", "Ident"]);
+    if cfg!(feature = "complex") {
+        factor.push(r#""i""#);
+    }
+    factor.push(r#""(" Comparison ")""#);
+
+    let mut arities: Vec<usize> = BUILTINS.iter().map(|&(_, arity)| arity).collect();
+    arities.sort();
+    arities.dedup();
+    let mut calls: Vec<String> = arities
+        .iter()
+        .map(|arity| {
+            let args = vec!["Comparison"; *arity].join(r#" "," "#);
+            format!(r#"Function{} "(" {} ")""#, arity, args)
+        })
+        .collect();
+    calls.push(r#"Ident "(" [ Comparison { "," Comparison } ] ")""#.to_string());
+
+    let mut rules = vec![
+        rule(
+            "Comparison",
+            format!("Expr {{ {} Expr }}", choice(COMPARISON_OPS)),
+        ),
+        rule("Expr", format!("Term {{ {} Term }}", choice(ADDITIVE_OPS))),
+        rule(
+            "Term",
+            format!("Unary {{ {} Unary }}", choice(MULTIPLICATIVE_OPS)),
+        ),
+        rule("Unary", r#""-" Unary | Power"#),
+        rule(
+            "Power",
+            format!("Factor [ {} Unary ]", choice(POWER_TOKENS)),
+        ),
+        rule("Factor", factor.join(" | ")),
+        rule("Let", r#""let" Ident "=" Comparison "in" Comparison"#),
+        rule("Call", calls.join("\n           | ")),
+    ];
+    for arity in arities {
+        let names = BUILTINS
+            .iter()
+            .filter(|&&(_, n)| n == arity)
+            .map(|&(name, _)| name);
+        rules.push(rule(&format!("Function{}", arity), choice(names)));
+    }
+    rules.push(rule(
+        "Constant",
+        choice(CONSTANTS.iter().map(|&(name, _)| name)),
+    ));
+    rules.push(rule("Ident", r#"letter { letter | digit | "_" }"#));
+    rules.push(rule(
+        "Number",
+        r#"digit { digit } [ "." digit { digit } ] [ Exponent ]"#,
+    ));
+    rules.push(rule(
+        "Exponent",
+        r#"("e" | "E") [ "+" | "-" ] digit { digit }"#,
+    ));
+    if cfg!(feature = "units") {
+        rules.push(rule("Quantity", "Number letter { letter }"));
+    }
+    rules.concat()
+}
+
+/// Рядок правила з вирівняною назвою
+fn rule(name: &str, body: impl AsRef<str>) -> String {
+    format!("{:<10} = {} ;\n", name, body.as_ref())
+}
+
+/// Вибір між термінальними символами: `("+" | "-")`, а для одного — `"e"`
+fn choice<T: ToString>(items: impl IntoIterator<Item = T>) -> String {
+    let items: Vec<String> = items
+        .into_iter()
+        .map(|item| format!("\"{}\"", item.to_string()))
+        .collect();
+    match items.as_slice() {
+        [single] => single.clone(),
+        _ => format!("({})", items.join(" | ")),
+    }
+}
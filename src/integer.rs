@@ -0,0 +1,68 @@
+//! Цілочисельне обчислення в `i64` зі змінною політикою переповнення
+
+use crate::evaluator::{Evaluator, eval_tree};
+use crate::{EvalError, Expr, apply_binary_op, apply_function, builtin_arity};
+use std::marker::PhantomData;
+
+/// Що робити з результатом, який не вміщується в `i64`
+pub(crate) trait Overflow {
+    fn neg(value: i64) -> i64;
+    fn abs(value: i64) -> i64;
+    /// Цілочисельна операція; `None` — обчислити у `f64` й округлити
+    fn binary(op: char, l: i64, r: i64) -> Option<i64>;
+}
+
+/// Обчислює вираз у цілих `i64` з політикою переповнення `P`
+///
+/// # Паніка
+/// Панікує, якщо обчислення неможливе (див. [`crate::try_evaluate`]).
+pub(crate) fn evaluate_i64<P: Overflow>(expr: &Expr) -> i64 {
+    match eval_tree(expr, &mut IntEval::<P>(PhantomData), &mut Vec::new()) {
+        Ok(value) => value,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+/// Округлює до цілого; `as` насичує нескінченності, а NaN дає нуль
+fn to_i64(value: f64) -> i64 {
+    value.round() as i64
+}
+
+struct IntEval<P>(PhantomData<P>);
+
+impl<P: Overflow> Evaluator for IntEval<P> {
+    type Value = i64;
+
+    fn number(&mut self, value: f64) -> Result<i64, EvalError> {
+        Ok(to_i64(value))
+    }
+
+    fn neg(&mut self, operand: i64) -> Result<i64, EvalError> {
+        Ok(P::neg(operand))
+    }
+
+    fn binary(&mut self, op: char, l: i64, r: i64) -> Result<i64, EvalError> {
+        match P::binary(op, l, r) {
+            Some(value) => Ok(value),
+            None => apply_binary_op(op, l as f64, r as f64).map(to_i64),
+        }
+    }
+
+    fn call(&mut self, name: &str, args: Vec<i64>) -> Result<i64, EvalError> {
+        let expected =
+            builtin_arity(name).ok_or_else(|| EvalError::UnknownFunction(name.to_string()))?;
+        match (name, args.as_slice()) {
+            ("abs", [a]) => Ok(P::abs(*a)),
+            // Цілі значення округлення не змінює
+            ("floor" | "ceil" | "round", [a]) => Ok(*a),
+            ("sin" | "cos" | "tan", [a]) => apply_function(name, &[*a as f64]).map(to_i64),
+            ("min", [a, b]) => Ok(*a.min(b)),
+            ("max", [a, b]) => Ok(*a.max(b)),
+            _ => Err(EvalError::WrongArity {
+                name: name.to_string(),
+                expected,
+                found: args.len(),
+            }),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Обхід вузлів AST
+
+use crate::Expr;
+
+/// Ітератор по всіх вузлах виразу в прямому порядку (корінь, потім діти
+/// зліва направо)
+///
+/// Створюється через [`Expr::iter`] або `for node in &expr`.
+#[derive(Debug, Clone)]
+pub struct ExprIter<'a> {
+    /// Вузли, які ще треба відвідати; наступний — останній
+    stack: Vec<&'a Expr>,
+}
+
+impl<'a> Iterator for ExprIter<'a> {
+    type Item = &'a Expr;
+
+    fn next(&mut self) -> Option<&'a Expr> {
+        let node = self.stack.pop()?;
+        self.stack.extend(node.children().into_iter().rev());
+        Some(node)
+    }
+}
+
+impl Expr {
+    /// Повертає ітератор по всіх вузлах виразу в прямому порядку
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// let labels: Vec<String> = expr.iter().map(|node| node.to_string()).collect();
+    /// assert_eq!(labels, ["(+)", "2", "(*)", "3", "4"]);
+    /// ```
+    pub fn iter(&self) -> ExprIter<'_> {
+        ExprIter { stack: vec![self] }
+    }
+
+    /// Повертає вузли у зворотному порядку: діти зліва направо перед
+    /// батьком, корінь — останній
+    ///
+    /// Це порядок обчислення: кожен вузол іде після всіх своїх операндів.
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// let labels: Vec<String> = expr.postorder().iter().map(|node| node.to_string()).collect();
+    /// assert_eq!(labels, ["2", "3", "4", "(*)", "(+)"]);
+    /// ```
+    pub fn postorder(&self) -> Vec<&Expr> {
+        // Обхід «корінь, діти справа наліво», перевернутий, дає «діти, корінь»
+        let mut stack = vec![self];
+        let mut order = Vec::new();
+        while let Some(node) = stack.pop() {
+            stack.extend(node.children());
+            order.push(node);
+        }
+        order.reverse();
+        order
+    }
+
+    /// Повертає підвираз за шляхом від кореня: кожен індекс — номер дитини
+    ///
+    /// У бінарного вузла 0 — лівий операнд, 1 — правий; у виклику функції —
+    /// номер аргументу, у `let` 0 — значення, 1 — тіло, а в унарного мінуса
+    /// єдина дитина 0. Порожній шлях дає сам вираз, а індекс поза межами —
+    /// `None`.
+    ///
+    /// # Приклад
+    /// ```
+    /// use tree_parser::{Expr, parse_expression};
+    ///
+    /// let expr = parse_expression("2 + 3 * 4").unwrap();
+    /// assert_eq!(expr.at_path(&[1, 0]), Some(&Expr::Number(3.0)));
+    /// assert_eq!(expr.at_path(&[0, 0]), None);
+    /// ```
+    pub fn at_path(&self, path: &[usize]) -> Option<&Expr> {
+        path.iter()
+            .try_fold(self, |node, &index| node.children().get(index).copied())
+    }
+
+    /// Повертає копію виразу, у якій вузол за шляхом `path` (див.
+    /// [`Expr::at_path`]) замінено на `new`, або `None` для хибного шляху
+    ///
+    /// Решта дерева копіюється без змін, а сам вираз лишається як був.
+    ///
+    /// # Приклад
+    /// ```
+    /// use tree_parser::{Expr, parse_expression};
+    ///
+    /// let expr = parse_expression("2 + 3 * 4").unwrap();
+    /// let edited = expr.replace_at_path(&[1, 0], Expr::Number(5.0)).unwrap();
+    /// assert_eq!(edited, parse_expression("2 + 5 * 4").unwrap());
+    /// assert_eq!(expr.replace_at_path(&[0, 1], Expr::Number(5.0)), None);
+    /// ```
+    pub fn replace_at_path(&self, path: &[usize], new: Expr) -> Option<Expr> {
+        self.at_path(path)?;
+        Some(self.replace_in(path, &mut Some(new)))
+    }
+
+    /// Заміна за шляхом, який уже перевірено; `new` забирається один раз
+    fn replace_in(&self, path: &[usize], new: &mut Option<Expr>) -> Expr {
+        let Some((&target, rest)) = path.split_first() else {
+            return new.take().unwrap_or_else(|| self.clone());
+        };
+        let mut index = 0;
+        self.map_children(|child| {
+            let replaced = if index == target {
+                child.replace_in(rest, new)
+            } else {
+                child.clone()
+            };
+            index += 1;
+            replaced
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a Expr {
+    type Item = &'a Expr;
+    type IntoIter = ExprIter<'a>;
+
+    fn into_iter(self) -> ExprIter<'a> {
+        self.iter()
+    }
+}
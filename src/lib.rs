@@ -1,25 +1,132 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
 use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::marker::PhantomData;
 use thiserror::Error;
 
+mod arena;
+mod bytecode;
+mod cache;
+#[cfg(feature = "complex")]
+mod complex;
+mod csv;
+mod debug_tree;
+#[cfg(feature = "decimal")]
+mod decimal;
+mod evaluator;
+mod grammar;
+mod integer;
+mod iter;
+mod modular;
+mod num;
+mod ops;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod preserve;
+mod recover;
+mod reparse;
+mod saturating;
+mod typed;
+#[cfg(feature = "units")]
+mod units;
+mod wrapping;
+
+pub use arena::{ExprArena, ExprNode};
+pub use bytecode::{Instruction, run_bytecode, try_run_bytecode};
+pub use cache::EvalCache;
+#[cfg(feature = "complex")]
+pub use complex::{evaluate_complex, try_evaluate_complex};
+pub use csv::{CsvError, eval_csv};
+#[cfg(feature = "decimal")]
+pub use decimal::evaluate_decimal;
+pub use grammar::grammar;
+pub use iter::ExprIter;
+pub use modular::evaluate_mod;
+pub use num::Num;
+#[cfg(feature = "parallel")]
+pub use parallel::evaluate_parallel;
+pub use preserve::{SourceToken, tokenize_preserving};
+pub use recover::parse_recover;
+pub use reparse::ReparseContext;
+#[cfg(feature = "decimal")]
+pub use rust_decimal::Decimal;
+pub use saturating::eval_saturating_i64;
+pub use typed::{Value, evaluate_typed};
+#[cfg(feature = "units")]
+pub use units::{Unit, UnitValue, evaluate_units};
+pub use wrapping::evaluate_wrapping_i64;
+
+use evaluator::{Evaluator, eval_tree, leaf_error};
+
 /// Абстрактне синтаксичне дерево (AST) для арифметичних виразів.
 ///
 /// # Вузли AST
 /// - `Number(f64)` — число
-/// - `BinaryOp { op, left, right }` — бінарна операція (`+`, `-`, `*`, `/`)
-#[derive(Debug, PartialEq)]
+/// - `Quantity { value, unit }` — число з одиницею вимірювання (feature `units`)
+/// - `Variable(name)` — змінна, значення якої задається під час обчислення
+/// - `Imaginary` — уявна одиниця `i` (feature `complex`)
+/// - `Let { name, value, body }` — локальна змінна: `let x = 2 in x * x`
+/// - `Neg(expr)` — унарний мінус
+/// - `BinaryOp { op, left, right }` — бінарна операція (`+`, `-`, `*`, `/`,
+///   степінь `^`, XOR `⊕`, порівняння `<`, `>` та логічне «і» `&`)
+/// - `Func { name, args }` — виклик функції: `abs(x)`, `floor(x)`, `ceil(x)`,
+///   `round(x)` (половини від нуля), `min(a, b)`, `max(a, b)`
+///
+/// Набір вузлів залежить від feature, тож зовнішній `match` має мати гілку `_`.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum Expr {
     /// Числовий вузол
     Number(f64),
 
+    /// Число з одиницею вимірювання, наприклад `3m`
+    #[cfg(feature = "units")]
+    Quantity {
+        /// Числове значення
+        value: f64,
+        /// Назва базової одиниці
+        unit: String,
+    },
+
+    /// Змінна
+    Variable(String),
+
+    /// Уявна одиниця `i`
+    #[cfg(feature = "complex")]
+    Imaginary,
+
+    /// Локальне зв'язування `let name = value in body`
+    Let {
+        /// Назва змінної
+        name: String,
+        /// Значення змінної
+        value: Box<Expr>,
+        /// Вираз, у якому змінна видима
+        body: Box<Expr>,
+    },
+
+    /// Унарний мінус
+    Neg(Box<Expr>),
+
     /// Бінарна операція
     BinaryOp {
-        /// Оператор: '+', '-', '*', '/'
+        /// Оператор: '+', '-', '*', '/', '^', '⊕', '<', '>', '&'
         op: char,
         /// Ліве піддерево
         left: Box<Expr>,
         /// Праве піддерево
         right: Box<Expr>,
     },
+
+    /// Виклик функції
+    Func {
+        /// Назва функції
+        name: String,
+        /// Аргументи
+        args: Vec<Expr>,
+    },
 }
 
 /// Можливі помилки парсингу
@@ -33,192 +140,4206 @@ pub enum ParseError {
     #[error("Unexpected token: {0}")]
     UnexpectedToken(String),
 
+    /// Неочікуваний токен разом із його позицією
+    /// ([`ParserConfig::token_positions`])
+    #[error("Unexpected token: {token}")]
+    UnexpectedTokenAt {
+        /// Текст токена
+        token: String,
+        /// Позиція токена у вводі (у байтах)
+        position: usize,
+    },
+
     /// Відсутня закриваюча дужка
     #[error("Missing closing parenthesis")]
     MissingClosingParenthesis,
+
+    /// Літерал надто великий для `f64` і перетворився б на нескінченність
+    #[error("Number out of range: {0}")]
+    NumberOutOfRange(String),
+
+    /// Між двома операндами бракує оператора (суворий режим): `2 3`, `2 (3)`
+    #[error("Missing operator before '{token}' at position {position}")]
+    MissingOperator {
+        /// Число чи `(` після пропущеного оператора
+        token: String,
+        /// Позиція токена у вводі (у символах)
+        position: usize,
+    },
+
+    /// Неправильне групування розрядів комами ([`ParserConfig::comma_grouping`]): `1,00`
+    #[error("Invalid digit grouping '{token}' at position {position}")]
+    InvalidDigitGrouping {
+        /// Число разом із комами
+        token: String,
+        /// Позиція числа у вводі (у байтах)
+        position: usize,
+    },
+
+    /// Після `e` чи `E` впритул до числа бракує цифр показника: `1e`, `2e+`
+    #[error("Malformed exponent '{token}' at position {position}")]
+    MalformedExponent {
+        /// Число разом з `e` і знаком показника
+        token: String,
+        /// Позиція числа у вводі (у байтах)
+        position: usize,
+    },
+
+    /// Виклик функції з кількома аргументами, коли коми групують розряди
+    /// ([`ParserConfig::comma_grouping`]): `max(1,000)`
+    #[error(
+        "Function '{name}' at position {position} takes several arguments, which comma_grouping does not allow"
+    )]
+    MultiArgCall {
+        /// Назва функції
+        name: String,
+        /// Позиція назви у вводі (у байтах)
+        position: usize,
+    },
+
+    /// Після оператора в кінці вводу бракує операнда: `2 +`
+    #[error("Missing operand after '{op}' at position {position}")]
+    MissingOperand {
+        /// Оператор без правого операнда
+        op: String,
+        /// Позиція одразу після оператора (у байтах)
+        position: usize,
+    },
+
+    /// Функцію викликано з неправильною кількістю аргументів
+    #[error("Function '{name}' expects {expected} argument(s), got {found}")]
+    WrongArity {
+        /// Назва функції
+        name: String,
+        /// Очікувана кількість аргументів
+        expected: usize,
+        /// Передана кількість аргументів
+        found: usize,
+    },
+
+    /// Вхід перевищує задані обмеження на довжину або кількість токенів
+    #[error("Input exceeds size limits")]
+    InputTooLarge,
+
+    /// Байт поза діапазоном ASCII у байтовому вводі
+    #[error("Non-ASCII byte 0x{byte:02x} at position {position}")]
+    NonAsciiByte {
+        /// Значення байта
+        byte: u8,
+        /// Позиція байта у вводі
+        position: usize,
+    },
+
+    /// Оператор не входить до [`ParserConfig::allowed_operators`]
+    #[error("Operator not allowed: {0}")]
+    OperatorNotAllowed(char),
 }
 
-impl Expr {
-    /// Виводить дерево AST у консоль
+/// Оператор, який, імовірно, мав на увазі токен `token`: `x` чи `times` — `*`
+fn spelled_operator(token: &str) -> Option<char> {
+    match token.to_lowercase().as_str() {
+        "plus" | "add" => Some('+'),
+        "minus" | "sub" | "−" => Some('-'),
+        "x" | "×" | "·" | "times" | "mul" => Some('*'),
+        "÷" | ":" | "div" | "over" => Some('/'),
+        "pow" => Some('^'),
+        _ => None,
+    }
+}
+
+/// Кількість символів з кожного боку помилки в [`ParseError::with_context`]
+const SNIPPET_CHARS: usize = 10;
+
+impl ParseError {
+    /// Повертає повідомлення про помилку мовою `lang`
+    ///
+    /// Підтримуються `"uk"` (українська) та `"en"` (англійська); для інших
+    /// мов повертається англійське повідомлення, як у `Display`.
     ///
     /// # Приклад
     /// ```
-    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
-    /// expr.print_tree();
+    /// let err = tree_parser::parse_expression("").unwrap_err();
+    /// assert_eq!(err.localized("uk"), "Неочікуваний кінець вводу");
+    /// assert_eq!(err.localized("en"), "Unexpected end of input");
     /// ```
-    pub fn print_tree(&self) {
-        println!("\nExpression: {}\n", self.to_infix());
-        Self::print_node(self, "".to_string(), true);
-        println!();
-    }
-
-    fn print_node(expr: &Expr, prefix: String, is_last: bool) {
-        let connector = if is_last { "└── " } else { "├── " };
-        print!("{}", prefix);
-        print!("{}", connector);
-
-        match expr {
-            Expr::Number(n) => println!("{}", n),
-            Expr::BinaryOp { op, left, right } => {
-                println!("{}", op);
-                let new_prefix = prefix + if is_last { "    " } else { "│   " };
-                let children = vec![left.as_ref(), right.as_ref()];
-
-                for (i, child) in children.iter().enumerate() {
-                    let last = i == children.len() - 1;
-                    Self::print_node(child, new_prefix.clone(), last);
-                }
+    pub fn localized(&self, lang: &str) -> String {
+        if lang != "uk" {
+            return self.to_string();
+        }
+        match self {
+            ParseError::UnexpectedEnd => "Неочікуваний кінець вводу".to_string(),
+            ParseError::UnexpectedToken(token) | ParseError::UnexpectedTokenAt { token, .. } => {
+                format!("Неочікуваний токен: {}", token)
+            }
+            ParseError::MissingClosingParenthesis => "Відсутня закриваюча дужка".to_string(),
+            ParseError::NumberOutOfRange(number) => {
+                format!("Число поза допустимим діапазоном: {}", number)
+            }
+            ParseError::MissingOperator { token, position } => {
+                format!("Бракує оператора перед '{}' на позиції {}", token, position)
+            }
+            ParseError::InvalidDigitGrouping { token, position } => {
+                format!(
+                    "Неправильне групування розрядів '{}' на позиції {}",
+                    token, position
+                )
             }
+            ParseError::MalformedExponent { token, position } => {
+                format!(
+                    "Неправильний показник степеня '{}' на позиції {}",
+                    token, position
+                )
+            }
+            ParseError::MultiArgCall { name, position } => format!(
+                "Функція '{}' на позиції {} має кілька аргументів, а comma_grouping їх не дозволяє",
+                name, position
+            ),
+            ParseError::MissingOperand { op, position } => {
+                format!("Бракує операнда після '{}' на позиції {}", op, position)
+            }
+            ParseError::WrongArity {
+                name,
+                expected,
+                found,
+            } => format!(
+                "Функція '{}' очікує аргументів: {}, отримано: {}",
+                name, expected, found
+            ),
+            ParseError::InputTooLarge => "Вхід перевищує обмеження розміру".to_string(),
+            ParseError::NonAsciiByte { byte, position } => {
+                format!("Байт поза ASCII 0x{:02x} на позиції {}", byte, position)
+            }
+            ParseError::OperatorNotAllowed(op) => format!("Оператор заборонено: {}", op),
         }
     }
 
-    /// Повертає рядкове представлення виразу у звичайному інфіксному вигляді
+    /// Підказка для неочікуваного токена, схожого на записаний словами чи
+    /// іншим символом оператор: `x` чи `times` замість `*`, `plus` замість `+`
+    ///
+    /// Такий токен одразу після виразу — помилка в будь-якому режимі, тож
+    /// підказку має, наприклад, `parse_expression("2 x 3")`.
     ///
     /// # Приклад
     /// ```
-    /// let expr = tree_parser::parse_expression("2 + 3").unwrap();
-    /// assert_eq!(expr.to_infix(), "(2 + 3)");
+    /// let err = tree_parser::parse_expression("2 plus 3").unwrap_err();
+    /// assert_eq!(err.suggestion().as_deref(), Some("did you mean '+'?"));
     /// ```
-    pub fn to_infix(&self) -> String {
-        match self {
-            Expr::Number(n) => format!("{}", n),
-            Expr::BinaryOp { op, left, right } => {
-                format!("({} {} {})", left.to_infix(), op, right.to_infix())
+    pub fn suggestion(&self) -> Option<String> {
+        let (ParseError::UnexpectedToken(token) | ParseError::UnexpectedTokenAt { token, .. }) =
+            self
+        else {
+            return None;
+        };
+        spelled_operator(token).map(|op| format!("did you mean '{}'?", op))
+    }
+
+    /// Повідомлення про помилку з уривком `source` навколо місця помилки
+    ///
+    /// Неочікуваний токен із позицією ([`ParseError::UnexpectedTokenAt`]),
+    /// число без оператора й число з неправильним групуванням розрядів
+    /// позначаються як `>x<`,
+    /// неочікуваний кінець — як `><` після останнього символу, а бракуючий
+    /// операнд — як `><` одразу після оператора. З кожного боку
+    /// показується до 10 символів, обрізаний текст замінюється на `...`.
+    /// Для решти помилок повертається звичайне повідомлення. `source` має
+    /// бути тим самим рядком, що розбирався.
+    ///
+    /// # Приклад
+    /// ```
+    /// use tree_parser::{ParserConfig, parse_expression_with};
+    ///
+    /// let config = ParserConfig {
+    ///     token_positions: true,
+    ///     ..ParserConfig::default()
+    /// };
+    /// let source = "1 + 2 * ) - 3";
+    /// let err = parse_expression_with(source, &config).unwrap_err();
+    /// assert_eq!(err.with_context(source), "Unexpected token: )\n  1 + 2 * >)< - 3");
+    /// ```
+    pub fn with_context(&self, source: &str) -> String {
+        let Some((start, end)) = self.source_range(source) else {
+            return self.to_string();
+        };
+        let (Some(before), Some(token), Some(after)) = (
+            source.get(..start),
+            source.get(start..end),
+            source.get(end..),
+        ) else {
+            return self.to_string();
+        };
+
+        // Переноси рядків у виводі в один рядок замінюються пробілами
+        let flatten = |c: char| if c.is_whitespace() { ' ' } else { c };
+        let skip = before.chars().count().saturating_sub(SNIPPET_CHARS);
+        let left: String = before.chars().skip(skip).map(flatten).collect();
+        let right: String = after.chars().take(SNIPPET_CHARS).map(flatten).collect();
+        format!(
+            "{}\n  {}{}>{}<{}{}",
+            self,
+            if skip > 0 { "... " } else { "" },
+            left,
+            token,
+            right.trim_end(),
+            if after.chars().count() > SNIPPET_CHARS {
+                " ..."
+            } else {
+                ""
             }
-        }
+        )
     }
-}
 
-impl fmt::Display for Expr {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    /// Байтовий діапазон `source`, на який указує помилка; порожній для
+    /// місця, де чогось бракує
+    fn source_range(&self, source: &str) -> Option<(usize, usize)> {
         match self {
-            Expr::Number(n) => write!(f, "{}", n),
-            Expr::BinaryOp { op, .. } => write!(f, "({})", op),
+            ParseError::MissingOperator { token, position } => {
+                let start = source
+                    .char_indices()
+                    .nth(*position)
+                    .map_or(source.len(), |(i, _)| i);
+                Some((start, start + token.len()))
+            }
+            ParseError::UnexpectedTokenAt { token, position }
+            | ParseError::InvalidDigitGrouping { token, position }
+            | ParseError::MalformedExponent { token, position }
+            | ParseError::MultiArgCall {
+                name: token,
+                position,
+            } => Some((*position, position + token.len())),
+            ParseError::UnexpectedEnd => {
+                let end = source.trim_end().len();
+                Some((end, end))
+            }
+            ParseError::MissingOperand { position, .. } => Some((*position, *position)),
+            _ => None,
         }
     }
 }
 
-/// Парсить арифметичний вираз у рядку та повертає AST
+/// Повідомлення про помилку в стилі rustc: рядок `source` з помилкою й
+/// позначка `^` під хибним токеном
 ///
-/// # Граматика
-///
-/// Expr   = Term { ("+" | "-") Term } ;
-/// Term   = Factor { ("*" | "/") Factor } ;
-/// Factor = Number | "(" Expr ")" ;
-/// Number = digit { digit } ;
+/// Для місця, де чогось бракує (кінець вводу, операнд після оператора),
+/// ставиться один `^` одразу після останнього символу. Рядок і стовпець
+/// рахуються з одиниці в символах. Помилки без позиції повертають лише
+/// перший рядок. `source` має бути тим самим рядком, що розбирався.
 ///
 /// # Приклад
 /// ```
-/// let expr = tree_parser::parse_expression("3 + 5 * (2 - 8) / 4").unwrap();
+/// use tree_parser::{ParserConfig, parse_expression_with, render_diagnostic};
+///
+/// let config = ParserConfig {
+///     token_positions: true,
+///     ..ParserConfig::default()
+/// };
+/// let err = parse_expression_with("2 + ) 3", &config).unwrap_err();
+/// assert_eq!(
+///     render_diagnostic("2 + ) 3", &err),
+///     "error: Unexpected token: )\n --> 1:5\n  |\n1 | 2 + ) 3\n  |     ^\n"
+/// );
 /// ```
-pub fn parse_expression(input: &str) -> Result<Expr, ParseError> {
-    let mut tokens = tokenize(input)?;
-    parse_expr(&mut tokens)
+pub fn render_diagnostic(source: &str, error: &ParseError) -> String {
+    let header = format!("error: {}\n", error);
+    let Some((start, end)) = error.source_range(source) else {
+        return header;
+    };
+    let (Some(before), Some(token)) = (source.get(..start), source.get(start..end)) else {
+        return header;
+    };
+
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[start..]
+        .find(['\r', '\n'])
+        .map_or(source.len(), |i| start + i);
+    let line_number = before.matches('\n').count() + 1;
+    let column = before[line_start..].chars().count();
+    // Токен, що переходить на наступний рядок, підкреслюється до кінця рядка
+    let width = token
+        .chars()
+        .take_while(|c| *c != '\n' && *c != '\r')
+        .count()
+        .max(1);
+
+    let gutter = " ".repeat(line_number.to_string().len());
+    let line: String = source[line_start..line_end]
+        .chars()
+        .map(|c| if c == '\t' { ' ' } else { c })
+        .collect();
+    format!(
+        "{header}{gutter}--> {line_number}:{}\n{gutter} |\n{line_number} | {line}\n{gutter} | {}{}\n",
+        column + 1,
+        " ".repeat(column),
+        "^".repeat(width),
+    )
 }
 
-/// Токенізація рядка у вектор токенів
-fn tokenize(input: &str) -> Result<Vec<String>, ParseError> {
-    let mut tokens = Vec::new();
-    let mut number = String::new();
+/// Можливі помилки обчислення
+#[derive(Error, Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum EvalError {
+    /// Невідомий оператор
+    #[error("Unknown operator: {0}")]
+    UnknownOperator(char),
 
-    for ch in input.chars() {
-        if ch.is_whitespace() {
-            continue;
-        } else if ch.is_ascii_digit() {
-            number.push(ch);
-        } else {
-            if !number.is_empty() {
-                tokens.push(number.clone());
-                number.clear();
-            }
-            tokens.push(ch.to_string());
-        }
-    }
+    /// Невідома функція
+    #[error("Unknown function: {0}")]
+    UnknownFunction(String),
 
-    if !number.is_empty() {
-        tokens.push(number);
-    }
+    /// Змінну не задано
+    #[error("Undefined variable: {0}")]
+    UndefinedVariable(String),
 
-    Ok(tokens)
+    /// Функцію викликано з неправильною кількістю аргументів
+    #[error("Function '{name}' expects {expected} argument(s), got {found}")]
+    WrongArity {
+        /// Назва функції
+        name: String,
+        /// Очікувана кількість аргументів
+        expected: usize,
+        /// Передана кількість аргументів
+        found: usize,
+    },
+
+    /// Нецілий операнд у цілочисельній операції
+    #[error("Operator {op} requires integer operands, got {value}")]
+    NonIntegerOperand {
+        /// Оператор
+        op: char,
+        /// Нецілий операнд
+        value: f64,
+    },
+
+    /// Значення перевищило допустиму величину
+    #[error("Value {value} exceeds the limit of {max_abs}")]
+    ValueTooLarge {
+        /// Завелике значення
+        value: f64,
+        /// Найбільше допустиме значення за модулем
+        max_abs: f64,
+    },
+
+    /// Від'ємний проміжний результат у [`evaluate_non_negative`]
+    #[error("Negative intermediate result: {0}")]
+    NegativeResult(f64),
+
+    /// Нецілий результат у модульній арифметиці
+    #[error("Modular arithmetic requires integers, got {0}")]
+    NonIntegerValue(f64),
+
+    /// Модуль не додатний
+    #[error("Modulus must be positive, got {0}")]
+    InvalidModulus(i64),
+
+    /// Дільник не має оберненого за модулем
+    #[error("{value} has no inverse modulo {modulus}")]
+    NoModularInverse {
+        /// Дільник за модулем
+        value: i64,
+        /// Модуль
+        modulus: i64,
+    },
+
+    /// Операнд не того типу в [`evaluate_typed`]: `(1 < 2) + 3`
+    #[error("Type mismatch: '{op}' expects {expected}, got {found}")]
+    TypeMismatch {
+        /// Оператор чи назва функції
+        op: String,
+        /// Очікуваний тип: `number` чи `boolean`
+        expected: String,
+        /// Тип переданого значення
+        found: String,
+    },
+
+    /// Обчислення потребує більше операцій, ніж дозволяє
+    /// [`evaluate_with_budget`]
+    #[error("Evaluation exceeds the budget of {0} operations")]
+    BudgetExceeded(usize),
+
+    /// Некоректна інструкція байткоду: індекс змінної поза межами чи
+    /// порожній стек, див. [`try_run_bytecode`]
+    #[error("Invalid bytecode at instruction {0}")]
+    InvalidBytecode(usize),
+
+    /// Комплексне значення там, де потрібне дійсне число
+    #[cfg(feature = "complex")]
+    #[error("Complex value where a real number is required")]
+    NotReal,
+
+    /// Переповнення або ділення на нуль у десятковій арифметиці
+    #[cfg(feature = "decimal")]
+    #[error("Decimal overflow or division by zero")]
+    DecimalOverflow,
+
+    /// Несумісні одиниці вимірювання
+    #[cfg(feature = "units")]
+    #[error("Unit mismatch: '{left}' vs '{right}'")]
+    UnitMismatch {
+        /// Одиниця лівого операнда
+        left: String,
+        /// Одиниця правого операнда
+        right: String,
+    },
+
+    /// Число з одиницею в обчисленні без одиниць; такі вирази обчислює
+    /// [`evaluate_units`]
+    #[cfg(feature = "units")]
+    #[error("Quantity {0} has a unit; use evaluate_units")]
+    UnexpectedUnit(String),
 }
 
-/// Реалізація правила граматики Expr = Term { ("+" | "-") Term }
-fn parse_expr(tokens: &mut Vec<String>) -> Result<Expr, ParseError> {
-    parse_binary_op(tokens, parse_term, &['+', '-'])
+/// Помилка [`parse_and_eval`]: вираз не розібрано або не обчислено
+#[derive(Error, Debug)]
+pub enum ParseEvalError {
+    /// Помилка парсингу
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+
+    /// Помилка обчислення
+    #[error("Evaluation error: {0}")]
+    Eval(#[from] EvalError),
 }
 
-/// Реалізація правила граматики Term = Factor { ("*" | "/") Factor }
-fn parse_term(tokens: &mut Vec<String>) -> Result<Expr, ParseError> {
-    parse_binary_op(tokens, parse_factor, &['*', '/'])
+/// Одиниця кутів для тригонометричних функцій, див. [`evaluate_with_angle_mode`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AngleMode {
+    /// Радіани, як у звичайному обчисленні
+    #[default]
+    Radians,
+    /// Градуси: `sin(90)` = 1
+    Degrees,
 }
 
-/// Парсинг бінарної операції
-fn parse_binary_op<F>(
-    tokens: &mut Vec<String>,
-    subparser: F,
-    ops: &[char],
-) -> Result<Expr, ParseError>
-where
-    F: Fn(&mut Vec<String>) -> Result<Expr, ParseError>,
-{
-    let mut left = subparser(tokens)?;
-    while let Some(op) = tokens.first().and_then(|s| s.chars().next()) {
-        if ops.contains(&op) {
-            tokens.remove(0);
-            let right = subparser(tokens)?;
-            left = Expr::BinaryOp {
-                op,
-                left: Box::new(left),
-                right: Box::new(right),
-            };
-        } else {
-            break;
+/// Спосіб відображення знаку множення
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MulSign {
+    /// Зірочка: `2 * 3`
+    Asterisk,
+    /// Крапка посередині: `2 · 3`
+    Dot,
+    /// Неявне множення: `2(3)`
+    Implicit,
+}
+
+/// Налаштування інфіксного виводу виразу для [`Expr::to_infix_with`]
+///
+/// Значення за замовчуванням відповідають [`Expr::to_infix`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayOptions {
+    /// Як відображати множення
+    pub mul_sign: MulSign,
+    /// Чи ставити пробіли навколо операторів
+    pub spaced: bool,
+    /// Чи ставити лише ті дужки, без яких дерево зміниться
+    pub minimal_parens: bool,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        DisplayOptions {
+            mul_sign: MulSign::Asterisk,
+            spaced: true,
+            minimal_parens: false,
         }
     }
-    Ok(left)
 }
 
-/// Реалізація правила граматики Factor = Number | "(" Expr ")"
-fn parse_factor(tokens: &mut Vec<String>) -> Result<Expr, ParseError> {
-    if tokens.is_empty() {
-        return Err(ParseError::UnexpectedEnd);
+/// Формат числа для [`eval_to_string`]
+///
+/// Значення за замовчуванням: `1,234,567.5`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumberFormat {
+    /// Роздільник груп по три цифри цілої частини; `None` — без груп
+    pub group_separator: Option<char>,
+    /// Десятковий роздільник
+    pub decimal_separator: char,
+    /// Кількість знаків після роздільника; `None` — скільки потрібно, як у
+    /// [`format_number`]
+    pub decimals: Option<usize>,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            group_separator: Some(','),
+            decimal_separator: '.',
+            decimals: None,
+        }
     }
+}
 
-    let token = tokens.remove(0);
+impl Expr {
+    /// Повертає значення, якщо вузол є числом
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("42").unwrap();
+    /// assert_eq!(expr.as_number(), Some(42.0));
+    /// ```
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Expr::Number(n) => Some(*n),
+            _ => None,
+        }
+    }
 
-    if token == "(" {
-        let expr = parse_expr(tokens)?;
-        if tokens.is_empty() || tokens.remove(0) != ")" {
-            return Err(ParseError::MissingClosingParenthesis);
+    /// Повертає оператор і піддерева, якщо вузол є бінарною операцією
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3").unwrap();
+    /// let (op, left, _) = expr.as_binary_op().unwrap();
+    /// assert_eq!(op, '+');
+    /// assert_eq!(left.as_number(), Some(2.0));
+    /// ```
+    pub fn as_binary_op(&self) -> Option<(char, &Expr, &Expr)> {
+        match self {
+            Expr::BinaryOp { op, left, right } => Some((*op, left, right)),
+            _ => None,
         }
-        Ok(expr)
-    } else if let Ok(num) = token.parse::<f64>() {
-        Ok(Expr::Number(num))
-    } else {
-        Err(ParseError::UnexpectedToken(token))
     }
-}
 
-/// Обчислює значення AST
-///
-/// # Приклад
-/// ```
-/// let expr = tree_parser::parse_expression("3 + 5").unwrap();
-/// assert_eq!(tree_parser::evaluate(&expr), 8.0);
-/// ```
-pub fn evaluate(expr: &Expr) -> f64 {
-    match expr {
-        Expr::Number(n) => *n,
-        Expr::BinaryOp { op, left, right } => {
-            let l = evaluate(left);
-            let r = evaluate(right);
-            match op {
-                '+' => l + r,
-                '-' => l - r,
-                '*' => l * r,
-                '/' => l / r,
-                _ => unreachable!(),
+    /// Повертає оператор кореня, якщо корінь — бінарна операція
+    ///
+    /// Для числа, змінної, унарного мінуса та виклику функції повертає `None`.
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// assert_eq!(expr.root_operator(), Some('+'));
+    /// ```
+    pub fn root_operator(&self) -> Option<char> {
+        self.as_binary_op().map(|(op, _, _)| op)
+    }
+
+    /// Чи є корінь додаванням
+    pub fn is_addition(&self) -> bool {
+        self.root_operator() == Some('+')
+    }
+
+    /// Чи є корінь відніманням (не унарним мінусом)
+    pub fn is_subtraction(&self) -> bool {
+        self.root_operator() == Some('-')
+    }
+
+    /// Чи є корінь множенням
+    pub fn is_multiplication(&self) -> bool {
+        self.root_operator() == Some('*')
+    }
+
+    /// Чи є корінь діленням
+    pub fn is_division(&self) -> bool {
+        self.root_operator() == Some('/')
+    }
+
+    /// Повертає всі числові літерали виразу зліва направо
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// assert_eq!(expr.numbers(), vec![2.0, 3.0, 4.0]);
+    /// ```
+    pub fn numbers(&self) -> Vec<f64> {
+        let mut out = Vec::new();
+        self.collect_numbers(&mut out);
+        out
+    }
+
+    /// Повертає найбільший числовий літерал виразу
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 30 * 4").unwrap();
+    /// assert_eq!(expr.max_number(), Some(30.0));
+    /// ```
+    pub fn max_number(&self) -> Option<f64> {
+        self.numbers().into_iter().reduce(f64::max)
+    }
+
+    /// Повертає найменший числовий літерал виразу
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 30 * 4").unwrap();
+    /// assert_eq!(expr.min_number(), Some(2.0));
+    /// ```
+    pub fn min_number(&self) -> Option<f64> {
+        self.numbers().into_iter().reduce(f64::min)
+    }
+
+    /// Повертає перший зліва числовий літерал виразу
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// assert_eq!(expr.leftmost_number(), Some(2.0));
+    /// ```
+    pub fn leftmost_number(&self) -> Option<f64> {
+        self.numbers().first().copied()
+    }
+
+    /// Повертає останній зліва направо числовий літерал виразу
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// assert_eq!(expr.rightmost_number(), Some(4.0));
+    /// ```
+    pub fn rightmost_number(&self) -> Option<f64> {
+        self.numbers().last().copied()
+    }
+
+    /// Повертає назви вільних змінних виразу за абеткою, без повторів
+    ///
+    /// Змінні, зв'язані `let`, усередині його тіла не враховуються.
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("let a = y in a * x + y").unwrap();
+    /// assert_eq!(expr.free_variables(), ["x", "y"]);
+    /// ```
+    pub fn free_variables(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        self.collect_free_variables(&mut Vec::new(), &mut out);
+        out.sort();
+        out.dedup();
+        out
+    }
+
+    /// Замінює кожен числовий літерал новою змінною `a0`, `a1`, ...
+    ///
+    /// Повертає шаблон і вилучені константи в порядку зліва направо, тож
+    /// вирази, що різняться лише константами, мають однаковий шаблон. Назви,
+    /// які вже є у виразі, пропускаються. Числа з одиницями не змінюються.
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// let (template, constants) = expr.abstract_numbers();
+    /// assert_eq!(template.to_infix_min(), "a0 + a1 * a2");
+    /// assert_eq!(constants, [2.0, 3.0, 4.0]);
+    /// ```
+    pub fn abstract_numbers(&self) -> (Expr, Vec<f64>) {
+        let mut used = Vec::new();
+        self.collect_names(&mut used);
+        let mut constants = Vec::new();
+        let template = self.abstract_in(&used, &mut 0, &mut constants);
+        (template, constants)
+    }
+
+    fn abstract_in(&self, used: &[String], next: &mut usize, constants: &mut Vec<f64>) -> Expr {
+        match self {
+            Expr::Number(n) => {
+                let name = loop {
+                    let name = format!("a{}", next);
+                    *next += 1;
+                    if !used.contains(&name) {
+                        break name;
+                    }
+                };
+                constants.push(*n);
+                Expr::Variable(name)
+            }
+            other => other.map_children(|child| child.abstract_in(used, next, constants)),
+        }
+    }
+
+    /// Збирає назви всіх змінних виразу, зокрема зв'язаних `let`
+    fn collect_names(&self, out: &mut Vec<String>) {
+        match self {
+            Expr::Variable(name) | Expr::Let { name, .. } => out.push(name.clone()),
+            _ => {}
+        }
+        for child in self.children() {
+            child.collect_names(out);
+        }
+    }
+
+    fn collect_free_variables<'a>(&'a self, bound: &mut Vec<&'a str>, out: &mut Vec<String>) {
+        match self {
+            Expr::Variable(name) if !bound.contains(&name.as_str()) => out.push(name.clone()),
+            Expr::Let { name, value, body } => {
+                value.collect_free_variables(bound, out);
+                bound.push(name);
+                body.collect_free_variables(bound, out);
+                bound.pop();
+            }
+            other => {
+                for child in other.children() {
+                    child.collect_free_variables(bound, out);
+                }
+            }
+        }
+    }
+
+    fn collect_numbers(&self, out: &mut Vec<f64>) {
+        match self {
+            Expr::Number(n) => out.push(*n),
+            #[cfg(feature = "units")]
+            Expr::Quantity { value, .. } => out.push(*value),
+            Expr::Variable(_) => {}
+            #[cfg(feature = "complex")]
+            Expr::Imaginary => {}
+            Expr::Let { value, body, .. } => {
+                value.collect_numbers(out);
+                body.collect_numbers(out);
+            }
+            Expr::Neg(operand) => operand.collect_numbers(out),
+            Expr::BinaryOp { left, right, .. } => {
+                left.collect_numbers(out);
+                right.collect_numbers(out);
             }
+            Expr::Func { args, .. } => args.iter().for_each(|arg| arg.collect_numbers(out)),
         }
     }
+
+    /// Повертає загальну кількість вузлів дерева
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// assert_eq!(expr.node_count(), 5);
+    /// ```
+    pub fn node_count(&self) -> usize {
+        self.count_leaves() + self.count_internal()
+    }
+
+    /// Повертає кількість листків (числових вузлів)
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// assert_eq!(expr.count_leaves(), 3);
+    /// ```
+    pub fn count_leaves(&self) -> usize {
+        match self {
+            Expr::Number(_) => 1,
+            #[cfg(feature = "units")]
+            Expr::Quantity { .. } => 1,
+            Expr::Variable(_) => 1,
+            #[cfg(feature = "complex")]
+            Expr::Imaginary => 1,
+            Expr::Let { value, body, .. } => value.count_leaves() + body.count_leaves(),
+            Expr::Neg(operand) => operand.count_leaves(),
+            Expr::BinaryOp { left, right, .. } => left.count_leaves() + right.count_leaves(),
+            Expr::Func { args, .. } => args.iter().map(Expr::count_leaves).sum(),
+        }
+    }
+
+    /// Повертає кількість внутрішніх вузлів (операцій та викликів функцій)
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// assert_eq!(expr.count_internal(), 2);
+    /// ```
+    pub fn count_internal(&self) -> usize {
+        match self {
+            Expr::Number(_) => 0,
+            #[cfg(feature = "units")]
+            Expr::Quantity { .. } => 0,
+            Expr::Variable(_) => 0,
+            #[cfg(feature = "complex")]
+            Expr::Imaginary => 0,
+            Expr::Let { value, body, .. } => 1 + value.count_internal() + body.count_internal(),
+            Expr::Neg(operand) => 1 + operand.count_internal(),
+            Expr::BinaryOp { left, right, .. } => {
+                1 + left.count_internal() + right.count_internal()
+            }
+            Expr::Func { args, .. } => 1 + args.iter().map(Expr::count_internal).sum::<usize>(),
+        }
+    }
+
+    /// Повертає кількість вузлів, для яких `pred` повертає `true`
+    ///
+    /// # Приклад
+    /// ```
+    /// use tree_parser::Expr;
+    ///
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// assert_eq!(expr.count_matching(&|node| matches!(node, Expr::BinaryOp { .. })), 2);
+    /// ```
+    pub fn count_matching<F: Fn(&Expr) -> bool>(&self, pred: &F) -> usize {
+        self.iter().filter(|node| pred(node)).count()
+    }
+
+    /// Згортає дерево знизу вгору: `on_number` для чисел, `on_op` для
+    /// бінарних операторів зі згорнутими операндами, `on_call` для викликів
+    /// функцій зі згорнутими аргументами, `on_leaf` для решти листків
+    ///
+    /// Унарний мінус згортається як `-1 * x`, що зберігає знак нуля. Змінна,
+    /// зв'язана `let`, згортається у згорнуте значення зв'язування; вільні
+    /// змінні, уявна одиниця й числа з одиницями йдуть в `on_leaf`.
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * -x").unwrap();
+    /// let leaves = expr.fold(&|_| 1, &|_, l, r| l + r, &|_, args| args.len(), &|_| 1);
+    /// assert_eq!(leaves, 4);
+    /// let infix = expr.fold(
+    ///     &|n| n.to_string(),
+    ///     &|op, l, r| format!("({} {} {})", l, op, r),
+    ///     &|name, args| format!("{}({})", name, args.join(", ")),
+    ///     &|leaf| leaf.to_infix(),
+    /// );
+    /// assert_eq!(infix, "(2 + (3 * (-1 * x)))");
+    /// ```
+    pub fn fold<T: Clone>(
+        &self,
+        on_number: &dyn Fn(f64) -> T,
+        on_op: &dyn Fn(char, T, T) -> T,
+        on_call: &dyn Fn(&str, Vec<T>) -> T,
+        on_leaf: &dyn Fn(&Expr) -> T,
+    ) -> T {
+        let mut fold = FoldEval {
+            on_number,
+            on_op,
+            on_call,
+            on_leaf,
+        };
+        match eval_tree(self, &mut fold, &mut Vec::new()) {
+            Ok(value) => value,
+            Err(_) => unreachable!("fold callbacks cannot fail"),
+        }
+    }
+
+    /// Чи обчислюється вираз у значення, близьке до `expected` (див. [`approx_eq`])
+    ///
+    /// Вираз, який не вдалося обчислити (див. [`try_evaluate`]), не дорівнює
+    /// нічому.
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("0.1 + 0.2").unwrap();
+    /// assert!(expr.value_approx_eq(0.3, 1e-9));
+    /// assert!(!expr.value_approx_eq(0.4, 1e-9));
+    /// ```
+    pub fn value_approx_eq(&self, expected: f64, epsilon: f64) -> bool {
+        try_evaluate(self).is_ok_and(|value| approx_eq(value, expected, epsilon))
+    }
+
+    /// Обчислює вираз, не чіпаючи піддерев, які не впливають на результат
+    ///
+    /// Множення на нуль дає нуль без обчислення другого множника (лівого,
+    /// якщо правий — літерал `0`, інакше правого), а `&` з нульовим лівим
+    /// операндом — нуль без правого. Для скінченних результатів значення те
+    /// саме, що й у [`evaluate`], хоча знак нуля може відрізнятися. Але
+    /// `0 * NaN` чи `0 * inf` тут дає `0`, а не NaN, як у звичайному
+    /// обчисленні, а помилки в пропущених піддеревах не виникають.
+    /// Користувацькі функції — через [`evaluate_lazy_with_functions`].
+    ///
+    /// # Паніка
+    /// Панікує, якщо обчислення неможливе (див. [`try_evaluate`]).
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("0 * (1 / 0)").unwrap();
+    /// assert_eq!(expr.evaluate_lazy(), 0.0);
+    /// assert!(tree_parser::evaluate::<f64>(&expr).is_nan());
+    /// ```
+    pub fn evaluate_lazy(&self) -> f64 {
+        match evaluate_lazy_with_functions(self, &FunctionTable::new()) {
+            Ok(value) => value,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Значення кожного підвиразу в порядку обчислення
+    ///
+    /// Підвирази — пари інфіксного запису ([`Expr::to_infix`]) і значення,
+    /// від листків до кореня: операнди йдуть перед операцією, корінь —
+    /// останнім. Змінні `let` у тілі мають значення свого зв'язування.
+    ///
+    /// # Паніка
+    /// Панікує, якщо обчислення неможливе (див. [`try_evaluate`]).
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 * 3 - 1").unwrap();
+    /// let results = expr.evaluate_all_subresults();
+    /// assert_eq!(results.last(), Some(&("((2 * 3) - 1)".to_string(), 5.0)));
+    /// ```
+    pub fn evaluate_all_subresults(&self) -> Vec<(String, f64)> {
+        let mut subresults = SubresultsEval(Vec::new());
+        if let Err(err) = eval_tree(self, &mut subresults, &mut Vec::new()) {
+            panic!("{}", err);
+        }
+        subresults.0
+    }
+
+    /// Обчислює вираз і повертає також значення кожного вузла за його шляхом
+    ///
+    /// Ключі — шляхи від кореня, як у [`Expr::at_path`]; порожній шлях
+    /// відповідає всьому виразу. Змінні `let` у тілі мають значення свого
+    /// зв'язування.
+    ///
+    /// # Паніка
+    /// Панікує, якщо обчислення неможливе (див. [`try_evaluate`]).
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// let (value, history) = expr.evaluate_with_history();
+    /// assert_eq!(value, 14.0);
+    /// assert_eq!(history[&vec![1]], 12.0);
+    /// ```
+    pub fn evaluate_with_history(&self) -> (f64, HashMap<Vec<usize>, f64>) {
+        let mut history = HistoryEval {
+            path: Vec::new(),
+            next: Vec::new(),
+            history: HashMap::new(),
+        };
+        match eval_tree(self, &mut history, &mut Vec::new()) {
+            Ok(value) => (value, history.history),
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Бінарний оператор, що трапляється в дереві найчастіше
+    ///
+    /// За однакової кількості перемагає дорожчий в обчисленні: `^`, потім
+    /// `/`, `*`, `-`, `+`, а далі решта операторів. `None`, якщо бінарних
+    /// операторів немає.
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("1 + 2 * 3 + 4").unwrap();
+    /// assert_eq!(expr.most_expensive_op(), Some('+'));
+    /// let tie = tree_parser::parse_expression("1 + 2 ^ 3").unwrap();
+    /// assert_eq!(tie.most_expensive_op(), Some('^'));
+    /// ```
+    pub fn most_expensive_op(&self) -> Option<char> {
+        let mut counts: Vec<(char, usize)> = Vec::new();
+        for node in self {
+            if let Expr::BinaryOp { op, .. } = node {
+                match counts.iter_mut().find(|(seen, _)| seen == op) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((*op, 1)),
+                }
+            }
+        }
+        counts
+            .into_iter()
+            .max_by_key(|&(op, count)| (count, OP_COST.iter().rev().position(|&c| c == op), op))
+            .map(|(op, _)| op)
+    }
+
+    /// Повертає кількість операндів у найдовшому суцільному ланцюжку
+    /// оператора `op`
+    ///
+    /// Ланцюжок — вузли `op`, з'єднані напряму, без інших вузлів між ними:
+    /// `(1 + 2) + (3 + 4)` — ланцюжок із чотирьох операндів, а в
+    /// `1 + 2 * (3 + 4)` їх два. Вираз без `op` — ланцюжок з одного операнда.
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("1 + 2 + 3 + 4").unwrap();
+    /// assert_eq!(expr.longest_associative_chain('+'), 4);
+    /// assert_eq!(expr.longest_associative_chain('*'), 1);
+    /// ```
+    pub fn longest_associative_chain(&self, op: char) -> usize {
+        let mut longest = 1;
+        self.chain_in(op, &mut longest);
+        longest
+    }
+
+    /// Кількість операндів ланцюжка `op` з коренем у цьому вузлі (1, якщо
+    /// корінь — не `op`); найдовший ланцюжок у піддереві йде в `longest`
+    fn chain_in(&self, op: char, longest: &mut usize) -> usize {
+        match self {
+            Expr::BinaryOp {
+                op: inner,
+                left,
+                right,
+            } if *inner == op => {
+                let operands = left.chain_in(op, longest) + right.chain_in(op, longest);
+                *longest = (*longest).max(operands);
+                operands
+            }
+            _ => {
+                for child in self.children() {
+                    child.chain_in(op, longest);
+                }
+                1
+            }
+        }
+    }
+
+    /// Повертає глибину дерева (одиночне число має глибину 1)
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// assert_eq!(expr.depth(), 3);
+    /// ```
+    pub fn depth(&self) -> usize {
+        match self {
+            Expr::Number(_) => 1,
+            #[cfg(feature = "units")]
+            Expr::Quantity { .. } => 1,
+            Expr::Variable(_) => 1,
+            #[cfg(feature = "complex")]
+            Expr::Imaginary => 1,
+            Expr::Let { value, body, .. } => 1 + value.depth().max(body.depth()),
+            Expr::Neg(operand) => 1 + operand.depth(),
+            Expr::BinaryOp { left, right, .. } => 1 + left.depth().max(right.depth()),
+            Expr::Func { args, .. } => 1 + args.iter().map(Expr::depth).max().unwrap_or(0),
+        }
+    }
+
+    /// Перевіряє, що в кожному вузлі глибини піддерев відрізняються не більше ніж на `tolerance`
+    ///
+    /// Для викликів функцій порівнюються найглибший і найменш глибокий
+    /// аргументи. Незбалансовані (однобічні) дерева потребують глибокої
+    /// рекурсії під час обходу.
+    ///
+    /// # Приклад
+    /// ```
+    /// let chain = tree_parser::parse_expression("1 + 2 + 3 + 4").unwrap();
+    /// assert!(!chain.is_balanced(1));
+    /// assert!(chain.is_balanced(2));
+    /// ```
+    pub fn is_balanced(&self, tolerance: usize) -> bool {
+        self.balanced_depth(tolerance).is_some()
+    }
+
+    /// Глибина піддерева або `None`, якщо воно незбалансоване
+    fn balanced_depth(&self, tolerance: usize) -> Option<usize> {
+        let children: Vec<&Expr> = match self {
+            Expr::Let { value, body, .. } => vec![value, body],
+            Expr::Neg(operand) => vec![operand],
+            Expr::BinaryOp { left, right, .. } => vec![left, right],
+            Expr::Func { args, .. } => args.iter().collect(),
+            _ => return Some(1),
+        };
+        let depths = children
+            .iter()
+            .map(|child| child.balanced_depth(tolerance))
+            .collect::<Option<Vec<usize>>>()?;
+        let deepest = depths.iter().copied().max().unwrap_or(0);
+        let shallowest = depths.iter().copied().min().unwrap_or(0);
+        (deepest - shallowest <= tolerance).then_some(1 + deepest)
+    }
+
+    /// Покрокове спрощення виразу: кожен крок згортає одну найглибшу операцію
+    ///
+    /// Операції згортаються зліва направо, вихідний вираз до результату не
+    /// входить. Останній крок — одне число; якщо операцію обчислити не
+    /// вдається (наприклад, невідома функція), кроки обриваються на ній.
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// let steps: Vec<String> = expr.reduce_steps().iter().map(|e| e.to_infix()).collect();
+    /// assert_eq!(steps, ["(2 + 12)", "14"]);
+    /// ```
+    pub fn reduce_steps(&self) -> Vec<Expr> {
+        let mut steps = Vec::new();
+        let mut current = self.clone();
+        while let Some(next) = current.reduce_once() {
+            steps.push(next.clone());
+            current = next;
+        }
+        steps
+    }
+
+    /// Згортає найлівішу операцію, всі операнди якої вже числа
+    fn reduce_once(&self) -> Option<Expr> {
+        match self {
+            Expr::Let { name, value, body } => match value.as_number() {
+                Some(n) => Some(body.substitute(name, n)),
+                None => Some(Expr::Let {
+                    name: name.clone(),
+                    value: Box::new(value.reduce_once()?),
+                    body: body.clone(),
+                }),
+            },
+            Expr::Neg(operand) => match operand.as_number() {
+                Some(n) => Some(Expr::Number(-n)),
+                None => Some(Expr::Neg(Box::new(operand.reduce_once()?))),
+            },
+            Expr::BinaryOp { op, left, right } => match (left.as_number(), right.as_number()) {
+                (Some(l), Some(r)) => apply_binary_op(*op, l, r).ok().map(Expr::Number),
+                _ => {
+                    let (left, right) = match left.reduce_once() {
+                        Some(reduced) => (reduced, (**right).clone()),
+                        None => ((**left).clone(), right.reduce_once()?),
+                    };
+                    Some(Expr::BinaryOp {
+                        op: *op,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    })
+                }
+            },
+            Expr::Func { name, args } => {
+                let values: Option<Vec<f64>> = args.iter().map(Expr::as_number).collect();
+                if let Some(values) = values {
+                    return apply_function(name, &values).ok().map(Expr::Number);
+                }
+                let (i, reduced) = args
+                    .iter()
+                    .enumerate()
+                    .find_map(|(i, arg)| arg.reduce_once().map(|reduced| (i, reduced)))?;
+                let mut args = args.clone();
+                args[i] = reduced;
+                Some(Expr::Func {
+                    name: name.clone(),
+                    args,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Підставляє число `n` замість вільних входжень змінної `name`
+    fn substitute(&self, name: &str, n: f64) -> Expr {
+        match self {
+            Expr::Variable(var) if var == name => Expr::Number(n),
+            Expr::Let {
+                name: bound,
+                value,
+                body,
+            } => Expr::Let {
+                name: bound.clone(),
+                value: Box::new(value.substitute(name, n)),
+                // Внутрішнє зв'язування з тією самою назвою перекриває зовнішнє
+                body: if bound == name {
+                    body.clone()
+                } else {
+                    Box::new(body.substitute(name, n))
+                },
+            },
+            Expr::Neg(operand) => Expr::Neg(Box::new(operand.substitute(name, n))),
+            Expr::BinaryOp { op, left, right } => Expr::BinaryOp {
+                op: *op,
+                left: Box::new(left.substitute(name, n)),
+                right: Box::new(right.substitute(name, n)),
+            },
+            Expr::Func { name: func, args } => Expr::Func {
+                name: func.clone(),
+                args: args.iter().map(|arg| arg.substitute(name, n)).collect(),
+            },
+            other => other.clone(),
+        }
+    }
+
+    /// Канонічна форма з упорядкованими операндами `+` та `*`
+    ///
+    /// Ланцюжки однакових комутативних операторів розгортаються, операнди
+    /// сортуються за фіксованим порядком піддерев і знову збираються зліва
+    /// направо, тож вирази, що різняться лише порядком доданків чи
+    /// множників, мають однакову канонічну форму. `-` та `/` не змінюються.
+    ///
+    /// # Приклад
+    /// ```
+    /// use tree_parser::parse_expression;
+    ///
+    /// let a = parse_expression("x * 2 + 3").unwrap();
+    /// let b = parse_expression("3 + 2 * x").unwrap();
+    /// assert_eq!(a.canonicalize(), b.canonicalize());
+    /// ```
+    pub fn canonicalize(&self) -> Expr {
+        match self {
+            Expr::BinaryOp {
+                op: op @ ('+' | '*'),
+                ..
+            } => {
+                let mut operands = Vec::new();
+                self.collect_operands(*op, &mut operands);
+                let mut operands: Vec<Expr> =
+                    operands.into_iter().map(Expr::canonicalize).collect();
+                operands.sort_by(Expr::canonical_cmp);
+                let first = operands.remove(0);
+                operands
+                    .into_iter()
+                    .fold(first, |left, right| Expr::BinaryOp {
+                        op: *op,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    })
+            }
+            other => other.map_children(Expr::canonicalize),
+        }
+    }
+
+    /// Копія з випадково переставленими й перегрупованими операндами `+` та `*`
+    ///
+    /// Ланцюжки однакових комутативних операторів розгортаються, операнди
+    /// перемішуються й групуються випадковими дужками, тож значення виразу
+    /// не змінюється (з точністю до округлення `f64`), а форма дерева — так.
+    /// Той самий `seed` завжди дає те саме дерево. `-` та `/` не змінюються.
+    ///
+    /// # Приклад
+    /// ```
+    /// use tree_parser::{evaluate, parse_expression};
+    ///
+    /// let expr = parse_expression("1 + 2 * 3 + 4 * 5 * 6").unwrap();
+    /// let shaken = expr.rearrange_commutative(7);
+    /// assert_eq!(shaken, expr.rearrange_commutative(7));
+    /// assert_eq!(evaluate::<f64>(&shaken), 127.0);
+    /// ```
+    pub fn rearrange_commutative(&self, seed: u64) -> Expr {
+        let mut state = seed;
+        self.rearrange_in(&mut state)
+    }
+
+    fn rearrange_in(&self, state: &mut u64) -> Expr {
+        match self {
+            Expr::BinaryOp {
+                op: op @ ('+' | '*'),
+                ..
+            } => {
+                let mut operands = Vec::new();
+                self.collect_operands(*op, &mut operands);
+                let mut operands: Vec<Expr> = operands
+                    .into_iter()
+                    .map(|operand| operand.rearrange_in(state))
+                    .collect();
+                // Тасування Фішера — Єйтса
+                for i in (1..operands.len()).rev() {
+                    let j = (next_random(state) % (i as u64 + 1)) as usize;
+                    operands.swap(i, j);
+                }
+                regroup(*op, operands, state)
+            }
+            other => other.map_children(|child| child.rearrange_in(state)),
+        }
+    }
+
+    /// Прибирає ланцюжки унарних мінусів: парна кількість зникає, непарна
+    /// стає одним мінусом
+    ///
+    /// # Приклад
+    /// ```
+    /// use tree_parser::parse_expression;
+    ///
+    /// let expr = parse_expression("- - x + -(-(-(2)))").unwrap();
+    /// assert_eq!(expr.simplify_double_negation().to_infix(), "(x + (-2))");
+    /// ```
+    pub fn simplify_double_negation(&self) -> Expr {
+        match self {
+            Expr::Neg(operand) => match operand.simplify_double_negation() {
+                Expr::Neg(inner) => *inner,
+                simplified => Expr::Neg(Box::new(simplified)),
+            },
+            other => other.map_children(Expr::simplify_double_negation),
+        }
+    }
+
+    /// Копія вузла, до кожного прямого нащадка якого (зліва направо)
+    /// застосовано `f`
+    fn map_children(&self, mut f: impl FnMut(&Expr) -> Expr) -> Expr {
+        match self {
+            Expr::Let { name, value, body } => Expr::Let {
+                name: name.clone(),
+                value: Box::new(f(value)),
+                body: Box::new(f(body)),
+            },
+            Expr::Neg(operand) => Expr::Neg(Box::new(f(operand))),
+            Expr::BinaryOp { op, left, right } => Expr::BinaryOp {
+                op: *op,
+                left: Box::new(f(left)),
+                right: Box::new(f(right)),
+            },
+            Expr::Func { name, args } => Expr::Func {
+                name: name.clone(),
+                args: args.iter().map(&mut f).collect(),
+            },
+            leaf => leaf.clone(),
+        }
+    }
+
+    /// Збирає операнди ланцюжка операторів `op` зліва направо
+    fn collect_operands<'a>(&'a self, op: char, out: &mut Vec<&'a Expr>) {
+        match self {
+            Expr::BinaryOp {
+                op: inner,
+                left,
+                right,
+            } if *inner == op => {
+                left.collect_operands(op, out);
+                right.collect_operands(op, out);
+            }
+            other => out.push(other),
+        }
+    }
+
+    /// Повний порядок піддерев для [`Expr::canonicalize`]
+    fn canonical_cmp(&self, other: &Expr) -> Ordering {
+        fn rank(expr: &Expr) -> u8 {
+            match expr {
+                Expr::Number(_) => 0,
+                #[cfg(feature = "units")]
+                Expr::Quantity { .. } => 1,
+                Expr::Variable(_) => 2,
+                #[cfg(feature = "complex")]
+                Expr::Imaginary => 3,
+                Expr::Let { .. } => 4,
+                Expr::Neg(_) => 5,
+                Expr::BinaryOp { .. } => 6,
+                Expr::Func { .. } => 7,
+            }
+        }
+
+        match (self, other) {
+            (Expr::Number(a), Expr::Number(b)) => a.total_cmp(b),
+            #[cfg(feature = "units")]
+            (Expr::Quantity { value: a, unit: u }, Expr::Quantity { value: b, unit: v }) => {
+                a.total_cmp(b).then_with(|| u.cmp(v))
+            }
+            (Expr::Variable(a), Expr::Variable(b)) => a.cmp(b),
+            (
+                Expr::Let {
+                    name: a,
+                    value: av,
+                    body: ab,
+                },
+                Expr::Let {
+                    name: b,
+                    value: bv,
+                    body: bb,
+                },
+            ) => a
+                .cmp(b)
+                .then_with(|| av.canonical_cmp(bv))
+                .then_with(|| ab.canonical_cmp(bb)),
+            (Expr::Neg(a), Expr::Neg(b)) => a.canonical_cmp(b),
+            (
+                Expr::BinaryOp {
+                    op: a,
+                    left: al,
+                    right: ar,
+                },
+                Expr::BinaryOp {
+                    op: b,
+                    left: bl,
+                    right: br,
+                },
+            ) => a
+                .cmp(b)
+                .then_with(|| al.canonical_cmp(bl))
+                .then_with(|| ar.canonical_cmp(br)),
+            (Expr::Func { name: a, args: aa }, Expr::Func { name: b, args: ba }) => a
+                .cmp(b)
+                .then_with(|| {
+                    aa.iter()
+                        .zip(ba)
+                        .map(|(x, y)| x.canonical_cmp(y))
+                        .find(|ordering| ordering.is_ne())
+                        .unwrap_or(Ordering::Equal)
+                })
+                .then_with(|| aa.len().cmp(&ba.len())),
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+
+    /// Виводить дерево AST у консоль
+    ///
+    /// Помилки запису (наприклад, закритий канал) повертаються, а не панікують.
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// expr.print_tree().unwrap();
+    /// ```
+    pub fn print_tree(&self) -> io::Result<()> {
+        self.write_tree(&mut io::stdout().lock())
+    }
+
+    /// Записує дерево AST у довільний `Write`
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3").unwrap();
+    /// let mut out = Vec::new();
+    /// expr.write_tree(&mut out).unwrap();
+    /// assert!(String::from_utf8(out).unwrap().contains("└── +"));
+    /// ```
+    pub fn write_tree<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.write_tree_impl(w, false)
+    }
+
+    /// Виводить дерево AST у консоль, підписуючи кожну операцію її значенням
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// expr.print_tree_with_values().unwrap();
+    /// ```
+    pub fn print_tree_with_values(&self) -> io::Result<()> {
+        self.write_tree_with_values(&mut io::stdout().lock())
+    }
+
+    /// Записує дерево AST у `Write`, підписуючи кожну операцію її значенням
+    ///
+    /// Вузол операції виводиться як `+ = 14`. Якщо піддерево не вдається
+    /// обчислити (див. [`try_evaluate`]), замість значення виводиться `?`.
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3").unwrap();
+    /// let mut out = Vec::new();
+    /// expr.write_tree_with_values(&mut out).unwrap();
+    /// assert!(String::from_utf8(out).unwrap().contains("└── + = 5"));
+    /// ```
+    pub fn write_tree_with_values<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.write_tree_impl(w, true)
+    }
+
+    fn write_tree_impl<W: Write>(&self, w: &mut W, with_values: bool) -> io::Result<()> {
+        writeln!(w, "\nExpression: {}\n", self.to_infix())?;
+        Self::write_node(w, self, "".to_string(), true, with_values)?;
+        writeln!(w)
+    }
+
+    fn write_node<W: Write>(
+        w: &mut W,
+        expr: &Expr,
+        prefix: String,
+        is_last: bool,
+        with_values: bool,
+    ) -> io::Result<()> {
+        let connector = if is_last { "└── " } else { "├── " };
+        write!(w, "{}", prefix)?;
+        write!(w, "{}", connector)?;
+
+        let label = expr.notation_label();
+        let children = expr.children();
+
+        if with_values && !children.is_empty() {
+            let value = match try_evaluate(expr) {
+                Ok(value) => format_number(value),
+                Err(_) => "?".to_string(),
+            };
+            writeln!(w, "{} = {}", label, value)?;
+        } else {
+            writeln!(w, "{}", label)?;
+        }
+
+        let new_prefix = prefix + if is_last { "    " } else { "│   " };
+        for (i, child) in children.iter().enumerate() {
+            let last = i == children.len() - 1;
+            Self::write_node(w, child, new_prefix.clone(), last, with_values)?;
+        }
+        Ok(())
+    }
+
+    /// Порівнює два дерева, виводячи їх спільну частину один раз
+    ///
+    /// Рядки відмінних піддерев позначаються `~` і записуються як
+    /// `старе → нове`. Кольори вмикаються, лише якщо stdout — термінал
+    /// (див. [`Expr::diff_tree_with`]).
+    pub fn diff_tree(&self, other: &Expr) -> String {
+        self.diff_tree_with(other, io::stdout().is_terminal())
+    }
+
+    /// Те саме, що [`Expr::diff_tree`], з явним вибором кольорового виводу
+    ///
+    /// # Приклад
+    /// ```
+    /// use tree_parser::parse_expression;
+    ///
+    /// let a = parse_expression("2 + 3").unwrap();
+    /// let b = parse_expression("2 + 4").unwrap();
+    /// assert_eq!(a.diff_tree_with(&b, false), "  └── +\n      ├── 2\n~     └── 3 → 4\n");
+    /// ```
+    pub fn diff_tree_with(&self, other: &Expr, colored: bool) -> String {
+        let mut out = String::new();
+        Self::diff_node(self, other, String::new(), true, colored, &mut out);
+        out
+    }
+
+    fn diff_node(
+        a: &Expr,
+        b: &Expr,
+        prefix: String,
+        is_last: bool,
+        colored: bool,
+        out: &mut String,
+    ) {
+        let connector = if is_last { "└── " } else { "├── " };
+        let (a_label, b_label) = (a.notation_label(), b.notation_label());
+        let (a_children, b_children) = (a.children(), b.children());
+
+        // Вузли з різними позначками чи кількістю нащадків виводяться цілими
+        if a_label != b_label || a_children.len() != b_children.len() {
+            let (old, new) = (a.to_infix_min(), b.to_infix_min());
+            let change = if colored {
+                format!("\x1b[31m{}\x1b[0m → \x1b[32m{}\x1b[0m", old, new)
+            } else {
+                format!("{} → {}", old, new)
+            };
+            out.push_str(&format!("~ {}{}{}\n", prefix, connector, change));
+            return;
+        }
+
+        out.push_str(&format!("  {}{}{}\n", prefix, connector, a_label));
+        let new_prefix = prefix + if is_last { "    " } else { "│   " };
+        for (i, (a, b)) in a_children.iter().zip(&b_children).enumerate() {
+            let last = i == a_children.len() - 1;
+            Self::diff_node(a, b, new_prefix.clone(), last, colored, out);
+        }
+    }
+
+    /// Повертає рядкове представлення виразу у звичайному інфіксному вигляді
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3").unwrap();
+    /// assert_eq!(expr.to_infix(), "(2 + 3)");
+    /// ```
+    pub fn to_infix(&self) -> String {
+        match self {
+            Expr::Number(n) => format_number(*n),
+            #[cfg(feature = "units")]
+            Expr::Quantity { value, unit } => format!("{}{}", format_number(*value), unit),
+            Expr::Variable(name) => name.clone(),
+            #[cfg(feature = "complex")]
+            Expr::Imaginary => "i".to_string(),
+            Expr::Let { name, value, body } => {
+                format!(
+                    "(let {} = {} in {})",
+                    name,
+                    value.to_infix(),
+                    body.to_infix()
+                )
+            }
+            Expr::Neg(operand) => format!("(-{})", operand.to_infix()),
+            Expr::BinaryOp { op, left, right } => {
+                format!("({} {} {})", left.to_infix(), op, right.to_infix())
+            }
+            Expr::Func { name, args } => {
+                let args: Vec<String> = args.iter().map(Expr::to_infix).collect();
+                format!("{}({})", name, args.join(", "))
+            }
+        }
+    }
+
+    /// Повертає інфіксне представлення виразу з налаштуваннями виводу
+    ///
+    /// # Приклад
+    /// ```
+    /// use tree_parser::{DisplayOptions, MulSign};
+    ///
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// let opts = DisplayOptions {
+    ///     mul_sign: MulSign::Dot,
+    ///     spaced: false,
+    ///     minimal_parens: true,
+    /// };
+    /// assert_eq!(expr.to_infix_with(&opts), "2+3·4");
+    /// ```
+    pub fn to_infix_with(&self, opts: &DisplayOptions) -> String {
+        let body = self.infix_body(opts);
+        if !opts.minimal_parens
+            && matches!(
+                self,
+                Expr::Let { .. } | Expr::Neg(_) | Expr::BinaryOp { .. }
+            )
+        {
+            format!("({})", body)
+        } else {
+            body
+        }
+    }
+
+    /// Рядок вузла без зовнішніх дужок
+    fn infix_body(&self, opts: &DisplayOptions) -> String {
+        match self {
+            Expr::Number(n) => format_number(*n),
+            #[cfg(feature = "units")]
+            Expr::Quantity { value, unit } => format!("{}{}", format_number(*value), unit),
+            Expr::Variable(name) => name.clone(),
+            #[cfg(feature = "complex")]
+            Expr::Imaginary => "i".to_string(),
+            Expr::Let { name, value, body } => format!(
+                "let {} = {} in {}",
+                name,
+                value.to_infix_with(opts),
+                body.to_infix_with(opts)
+            ),
+            Expr::Neg(operand) => {
+                // Унарний мінус зв'язує слабше за степінь, але сильніше за `*` і `/`
+                let (operand, _) = operand.infix_operand('^', true, opts);
+                format!("-{}", operand)
+            }
+            Expr::BinaryOp { op, left, right } => {
+                let (l, _) = left.infix_operand(*op, false, opts);
+                let (r, r_wrapped) = right.infix_operand(*op, true, opts);
+
+                let sign = match (op, opts.mul_sign) {
+                    ('*', MulSign::Implicit) => {
+                        return if r_wrapped {
+                            format!("{}{}", l, r)
+                        } else {
+                            format!("{}({})", l, r)
+                        };
+                    }
+                    ('*', MulSign::Dot) => '·',
+                    _ => *op,
+                };
+
+                if opts.spaced {
+                    format!("{} {} {}", l, sign, r)
+                } else {
+                    format!("{}{}{}", l, sign, r)
+                }
+            }
+            Expr::Func { name, args } => {
+                let args: Vec<String> = args.iter().map(|arg| arg.to_infix_with(opts)).collect();
+                let separator = if opts.spaced { ", " } else { "," };
+                format!("{}({})", name, args.join(separator))
+            }
+        }
+    }
+
+    /// Рядок операнда та ознака того, чи його взято в дужки
+    fn infix_operand(
+        &self,
+        parent_op: char,
+        is_right: bool,
+        opts: &DisplayOptions,
+    ) -> (String, bool) {
+        let body = self.infix_body(opts);
+        if self.needs_parens(parent_op, is_right, opts) {
+            (format!("({})", body), true)
+        } else {
+            (body, false)
+        }
+    }
+
+    /// Чи потрібні дужки навколо операнда оператора `parent_op`
+    fn needs_parens(&self, parent_op: char, is_right: bool, opts: &DisplayOptions) -> bool {
+        match self {
+            Expr::Number(_) => false,
+            #[cfg(feature = "units")]
+            Expr::Quantity { .. } => false,
+            Expr::Variable(_) => false,
+            #[cfg(feature = "complex")]
+            Expr::Imaginary => false,
+            Expr::Func { .. } => false,
+            // `-2 ^ 2` означає `-(2 ^ 2)`, тому лівий операнд степеня береться в дужки
+            Expr::Neg(_) if opts.minimal_parens => matches!(parent_op, '^' | '⊕') && !is_right,
+            Expr::BinaryOp { op, .. } if opts.minimal_parens => {
+                let (child, parent) = (precedence(*op), precedence(parent_op));
+                // Для правоасоціативного степеня дужки потрібні зліва, а не справа
+                let needs_on_tie = if parent_op == '^' {
+                    !is_right
+                } else {
+                    is_right
+                };
+                child < parent || (needs_on_tie && child == parent)
+            }
+            // Тіло `let` тягнеться до кінця виразу, тож як операнд воно завжди в дужках
+            Expr::Let { .. } | Expr::Neg(_) | Expr::BinaryOp { .. } => true,
+        }
+    }
+
+    /// Повертає інфіксне представлення лише з необхідними дужками
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("(1 + 2) * 3 + 4").unwrap();
+    /// assert_eq!(expr.to_infix_min(), "(1 + 2) * 3 + 4");
+    /// ```
+    pub fn to_infix_min(&self) -> String {
+        self.to_infix_with(&DisplayOptions {
+            minimal_parens: true,
+            ..DisplayOptions::default()
+        })
+    }
+
+    /// Форматує вираз із перенесенням рядків, щоб рядки не перевищували `max_width`
+    ///
+    /// Якщо вираз уміщується в один рядок, результат збігається з
+    /// [`Expr::to_infix_min`]. Інакше операція розбивається: лівий операнд,
+    /// далі рядок, що починається з оператора, з правим операндом. Операнд у
+    /// дужках, що не вміщується, переноситься з відступом у чотири пробіли.
+    /// Результат залишається коректним виразом із тим самим деревом.
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("(1 + 2) * (3 + 4)").unwrap();
+    /// assert_eq!(expr.pretty(80), "(1 + 2) * (3 + 4)");
+    /// assert_eq!(expr.pretty(10), "(1 + 2)\n* (3 + 4)");
+    /// ```
+    pub fn pretty(&self, max_width: usize) -> String {
+        let mut lines = Vec::new();
+        self.pretty_lines(0, max_width, false, &mut lines);
+        lines.join("\n")
+    }
+
+    fn pretty_lines(&self, indent: usize, max_width: usize, wrap: bool, out: &mut Vec<String>) {
+        let opts = DisplayOptions {
+            minimal_parens: true,
+            ..DisplayOptions::default()
+        };
+        let pad = " ".repeat(indent);
+        let flat = if wrap {
+            format!("({})", self.infix_body(&opts))
+        } else {
+            self.infix_body(&opts)
+        };
+
+        let Expr::BinaryOp { op, left, right } = self else {
+            out.push(format!("{}{}", pad, flat));
+            return;
+        };
+        if indent + flat.chars().count() <= max_width {
+            out.push(format!("{}{}", pad, flat));
+            return;
+        }
+
+        let inner = if wrap {
+            out.push(format!("{}(", pad));
+            indent + 4
+        } else {
+            indent
+        };
+
+        left.pretty_lines(inner, max_width, left.needs_parens(*op, false, &opts), out);
+
+        // Правий операнд форматується з відступом під "op ", а перший його
+        // рядок починається з самого оператора.
+        let first = out.len();
+        right.pretty_lines(
+            inner + 2,
+            max_width,
+            right.needs_parens(*op, true, &opts),
+            out,
+        );
+        out[first] = format!("{}{} {}", " ".repeat(inner), op, &out[first][inner + 2..]);
+
+        if wrap {
+            out.push(format!("{})", pad));
+        }
+    }
+
+    /// Перетворює AST у префіксний (польський) запис: `+ 2 * 3 4`
+    ///
+    /// Унарний мінус записується як `neg`, локальна змінна — як
+    /// `let x <значення> <тіло>`, виклик функції — як назва перед аргументами.
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// assert_eq!(expr.to_prefix(), "+ 2 * 3 4");
+    /// ```
+    pub fn to_prefix(&self) -> String {
+        let mut parts = vec![self.notation_label()];
+        parts.extend(self.children().iter().map(|child| child.to_prefix()));
+        parts.join(" ")
+    }
+
+    /// Перетворює AST у постфіксний запис (зворотний польський, RPN): `2 3 4 * +`
+    ///
+    /// Позначки вузлів ті самі, що й у [`Expr::to_prefix`], але йдуть після
+    /// операндів.
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// assert_eq!(expr.to_postfix(), "2 3 4 * +");
+    /// ```
+    pub fn to_postfix(&self) -> String {
+        let mut parts: Vec<String> = self
+            .children()
+            .iter()
+            .map(|child| child.to_postfix())
+            .collect();
+        parts.push(self.notation_label());
+        parts.join(" ")
+    }
+
+    /// Перетворює AST у S-вираз: `(+ 2 (* 3 4))`
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+    /// assert_eq!(expr.to_sexpr(), "(+ 2 (* 3 4))");
+    /// ```
+    pub fn to_sexpr(&self) -> String {
+        let children = self.children();
+        if children.is_empty() && !matches!(self, Expr::Func { .. }) {
+            return self.notation_label();
+        }
+        let mut parts = vec![self.notation_label()];
+        parts.extend(children.iter().map(|child| child.to_sexpr()));
+        format!("({})", parts.join(" "))
+    }
+
+    /// Перетворює AST у вираз мовою Rust над `f64`
+    ///
+    /// Числа стають літералами `2.0f64`, кожна операція береться в дужки,
+    /// `^` — виклик `powf`, а функції — методи `f64`. Вільна змінна
+    /// записується ідентифікатором з `var_map` або, якщо її там немає, своєю
+    /// назвою; `let` стає блоком `{ let x = ...; ... }`. Одиниці вимірювання
+    /// відкидаються, а уявна одиниця, що не має дійсного значення, дає `f64::NAN`.
+    ///
+    /// # Приклад
+    /// ```
+    /// use std::collections::HashMap;
+    ///
+    /// let expr = tree_parser::parse_expression("2 + 3 * x").unwrap();
+    /// let var_map = HashMap::from([("x".to_string(), "input[0]".to_string())]);
+    /// assert_eq!(expr.to_rust_expr(&var_map), "(2.0f64 + (3.0f64 * input[0]))");
+    /// ```
+    pub fn to_rust_expr(&self, var_map: &HashMap<String, String>) -> String {
+        self.rust_in(var_map, &mut Vec::new())
+    }
+
+    /// Код Rust для вузла; `bound` — змінні `let`, у тілі яких вузол
+    fn rust_in(&self, var_map: &HashMap<String, String>, bound: &mut Vec<String>) -> String {
+        match self {
+            Expr::Number(n) => rust_literal(*n),
+            #[cfg(feature = "units")]
+            Expr::Quantity { value, .. } => rust_literal(*value),
+            Expr::Variable(name) if bound.contains(name) => name.clone(),
+            Expr::Variable(name) => var_map.get(name).unwrap_or(name).clone(),
+            #[cfg(feature = "complex")]
+            Expr::Imaginary => "f64::NAN".to_string(),
+            Expr::Let { name, value, body } => {
+                let value = value.rust_in(var_map, bound);
+                bound.push(name.clone());
+                let body = body.rust_in(var_map, bound);
+                bound.pop();
+                format!("{{ let {} = {}; {} }}", name, value, body)
+            }
+            Expr::Neg(operand) => format!("(-{})", operand.rust_in(var_map, bound)),
+            Expr::BinaryOp { op, left, right } => {
+                let l = left.rust_in(var_map, bound);
+                let r = right.rust_in(var_map, bound);
+                match op {
+                    '^' => format!("{}.powf({})", l, r),
+                    '⊕' => format!("((({} as i64) ^ ({} as i64)) as f64)", l, r),
+                    '<' | '>' => format!("(({} {} {}) as i32 as f64)", l, op, r),
+                    '&' => format!("(({} != 0.0 && {} != 0.0) as i32 as f64)", l, r),
+                    _ => format!("({} {} {})", l, op, r),
+                }
+            }
+            Expr::Func { name, args } => {
+                let mut args = args.iter().map(|arg| arg.rust_in(var_map, bound));
+                match args.next() {
+                    Some(receiver) => {
+                        let rest: Vec<String> = args.collect();
+                        format!("{}.{}({})", receiver, name, rest.join(", "))
+                    }
+                    None => format!("{}()", name),
+                }
+            }
+        }
+    }
+
+    /// Перетворює AST у граф мовою DOT для Graphviz
+    ///
+    /// Вузли нумеруються в прямому порядку обходу (`n0` — корінь) і
+    /// підписуються так само, як у [`Expr::to_prefix`].
+    ///
+    /// # Приклад
+    /// ```
+    /// let expr = tree_parser::parse_expression("2 + 3").unwrap();
+    /// assert_eq!(
+    ///     expr.to_dot(),
+    ///     "digraph expr {\n  n0 [label=\"+\"];\n  n1 [label=\"2\"];\n  n0 -> n1;\n  \
+    ///      n2 [label=\"3\"];\n  n0 -> n2;\n}\n"
+    /// );
+    /// ```
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph expr {\n");
+        self.write_dot_node(&mut 0, &mut out);
+        out.push_str("}\n");
+        out
+    }
+
+    /// Дописує вузол і його піддерево, повертаючи номер вузла
+    fn write_dot_node(&self, next_id: &mut usize, out: &mut String) -> usize {
+        let id = *next_id;
+        *next_id += 1;
+        let label = self
+            .notation_label()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+        out.push_str(&format!("  n{} [label=\"{}\"];\n", id, label));
+        for child in self.children() {
+            let child_id = child.write_dot_node(next_id, out);
+            out.push_str(&format!("  n{} -> n{};\n", id, child_id));
+        }
+        id
+    }
+
+    /// Позначка вузла в префіксному та постфіксному записі
+    fn notation_label(&self) -> String {
+        match self {
+            Expr::Let { name, .. } => format!("let {}", name),
+            Expr::Neg(_) => "neg".to_string(),
+            Expr::BinaryOp { op, .. } => op.to_string(),
+            Expr::Func { name, .. } => name.clone(),
+            leaf => leaf.to_string(),
+        }
+    }
+
+    /// Прямі нащадки вузла зліва направо
+    fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Let { value, body, .. } => vec![value, body],
+            Expr::Neg(operand) => vec![operand],
+            Expr::BinaryOp { left, right, .. } => vec![left, right],
+            Expr::Func { args, .. } => args.iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Форматує число для виводу
+///
+/// Це єдиний канонічний запис чисел для всіх принтерів і CLI, однаковий на
+/// всіх платформах: найкоротша послідовність цифр, яка після парсингу дає
+/// те саме `f64`, без показника степеня (`0.1`, `1e21` як `1` і 21 нуль).
+/// Її будує `Display` зі стандартної бібліотеки Rust, а не системна libc.
+/// Від'ємний нуль `-0.0` виводиться як `0`, нескінченності — як `inf` і
+/// `-inf`, а NaN — як `nan`.
+///
+/// # Приклад
+/// ```
+/// use tree_parser::format_number;
+///
+/// assert_eq!(format_number(-0.0), "0");
+/// assert_eq!(format_number(-2.5), "-2.5");
+/// assert_eq!(format_number(0.1 + 0.2), "0.30000000000000004");
+/// assert_eq!(format_number(f64::NAN), "nan");
+/// ```
+pub fn format_number(n: f64) -> String {
+    if n.is_nan() {
+        "nan".to_string()
+    } else if n == 0.0 {
+        "0".to_string()
+    } else {
+        format!("{}", n)
+    }
+}
+
+/// Чи відрізняються числа не більше ніж на `epsilon`
+///
+/// Нескінченність дорівнює лише нескінченності того самого знака за будь-якого
+/// `epsilon`, а NaN не дорівнює нічому, навіть самому собі.
+///
+/// # Приклад
+/// ```
+/// use tree_parser::approx_eq;
+///
+/// assert!(approx_eq(0.1 + 0.2, 0.3, 1e-9));
+/// assert!(approx_eq(f64::INFINITY, f64::INFINITY, 1e-9));
+/// assert!(!approx_eq(f64::NAN, f64::NAN, f64::INFINITY));
+/// ```
+pub fn approx_eq(a: f64, b: f64, epsilon: f64) -> bool {
+    a == b || (a.is_finite() && b.is_finite() && (a - b).abs() <= epsilon)
+}
+
+/// Обчислює вираз і форматує результат за `opts`: з роздільником груп
+/// розрядів, заданим десятковим роздільником і кількістю знаків
+///
+/// Нескінченності й NaN виводяться як у [`format_number`]. Змінні тут не
+/// задані, тож дають [`EvalError::UndefinedVariable`].
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{NumberFormat, eval_to_string, parse_expression};
+///
+/// let expr = parse_expression("1000 * 1000 + 0.5").unwrap();
+/// let opts = NumberFormat::default();
+/// assert_eq!(eval_to_string(&expr, &opts).unwrap(), "1,000,000.5");
+/// ```
+pub fn eval_to_string(expr: &Expr, opts: &NumberFormat) -> Result<String, EvalError> {
+    let value = try_evaluate(expr)?;
+    if !value.is_finite() {
+        return Ok(format_number(value));
+    }
+    let text = match opts.decimals {
+        Some(decimals) => {
+            let text = format!("{:.*}", decimals, value);
+            // Округлене до нуля значення виводиться без мінуса: `0.00`, а не `-0.00`
+            match text.strip_prefix('-') {
+                Some(rest) if rest.parse::<f64>() == Ok(0.0) => rest.to_string(),
+                _ => text,
+            }
+        }
+        None => format_number(value),
+    };
+    let (sign, digits) = match text.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", text.as_str()),
+    };
+    let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+
+    let mut out = sign.to_string();
+    for (i, digit) in integer.chars().enumerate() {
+        if let Some(separator) = opts.group_separator
+            && i > 0
+            && (integer.len() - i) % 3 == 0
+        {
+            out.push(separator);
+        }
+        out.push(digit);
+    }
+    if !fraction.is_empty() {
+        out.push(opts.decimal_separator);
+        out.push_str(fraction);
+    }
+    Ok(out)
+}
+
+/// Найбільший знаменник, який шукає [`to_fraction`]
+const MAX_DENOMINATOR: f64 = 1_000_000.0;
+
+/// Відновлює звичайний дріб `(чисельник, знаменник)` зі значення `f64`
+///
+/// Дріб шукається ланцюговим дробом зі знаменником до мільйона й
+/// повертається, лише якщо він дорівнює `value` з точністю до округлення
+/// `f64`. Для цілих чисел знаменник дорівнює `1`.
+///
+/// # Приклад
+/// ```
+/// assert_eq!(tree_parser::to_fraction(0.75), Some((3, 4)));
+/// assert_eq!(tree_parser::to_fraction(2f64.sqrt()), None);
+/// ```
+pub fn to_fraction(value: f64) -> Option<(i64, u64)> {
+    if !value.is_finite() || value.abs() > i64::MAX as f64 {
+        return None;
+    }
+    let target = value.abs();
+    let tolerance = 4.0 * f64::EPSILON * target.max(1.0);
+
+    // Попередні підхідні дроби h/k ланцюгового дробу
+    let (mut h, mut h_prev) = (1.0, 0.0);
+    let (mut k, mut k_prev) = (0.0, 1.0);
+    let mut x = target;
+    loop {
+        let a = x.floor();
+        (h, h_prev) = (a * h + h_prev, h);
+        (k, k_prev) = (a * k + k_prev, k);
+        if k > MAX_DENOMINATOR {
+            return None;
+        }
+        if (h / k - target).abs() <= tolerance {
+            let numerator = if value < 0.0 { -h } else { h };
+            return Some((numerator as i64, k as u64));
+        }
+        x = 1.0 / (x - a);
+    }
+}
+
+/// Пріоритет бінарного оператора (більше — зв'язує сильніше)
+fn precedence(op: char) -> u8 {
+    match op {
+        '&' => 0,
+        '<' | '>' => 1,
+        '+' | '-' => 2,
+        '*' | '/' => 3,
+        '^' | '⊕' => 4,
+        _ => 5,
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "{}", format_number(*n)),
+            #[cfg(feature = "units")]
+            Expr::Quantity { value, unit } => write!(f, "{}{}", format_number(*value), unit),
+            Expr::Variable(name) => write!(f, "{}", name),
+            #[cfg(feature = "complex")]
+            Expr::Imaginary => write!(f, "i"),
+            Expr::Let { name, .. } => write!(f, "(let {})", name),
+            Expr::Neg(_) => write!(f, "(neg)"),
+            Expr::BinaryOp { op, .. } => write!(f, "({})", op),
+            Expr::Func { name, .. } => write!(f, "{}()", name),
+        }
+    }
+}
+
+/// Налаштування парсера для [`parse_expression_with`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParserConfig {
+    /// Чи означає `^` побітове XOR (оператор `'⊕'`) замість степеня.
+    ///
+    /// За замовчуванням `false`: `6 ^ 3` = 216. Коли `true`, `6 ^ 3` = 5;
+    /// XOR визначено лише для цілих операндів. `**` — завжди степінь, а
+    /// `⊕` — завжди XOR, тож вивід [`Expr::to_infix`] розбирається назад за
+    /// будь-якого значення.
+    pub caret_is_xor: bool,
+
+    /// Суворий режим: операнди без оператора між ними (`2 3`, `2 (3)`,
+    /// `(1) (2)`) дають [`ParseError::MissingOperator`], а інші зайві токени
+    /// після виразу (`2 )`) — [`ParseError::UnexpectedToken`].
+    ///
+    /// За замовчуванням `false`: пробіли ігноруються повністю, і `2 3`
+    /// зливається в одне число `23`, а зайві токени відкидаються (крім
+    /// оператора, записаного словом, див. [`ParseError::suggestion`]).
+    pub strict: bool,
+
+    /// Дозволені оператори, як вони записані у вводі (`'+'`, `'-'`, `'^'` тощо).
+    ///
+    /// За замовчуванням `None`: дозволено всі. Оператор поза списком дає
+    /// [`ParseError::OperatorNotAllowed`]; `'-'` охоплює й унарний мінус,
+    /// а `'^'` — запис `**`.
+    pub allowed_operators: Option<Vec<char>>,
+
+    /// Відсотки як у калькуляторі: `%` після доданка береться від лівого
+    /// операнда `+` чи `-`.
+    ///
+    /// За замовчуванням `false`: `%` не входить до граматики. Коли `true`,
+    /// `100 + 10%` = `100 + 100 * 10 / 100` = 110, а `100 - 10%` = 90.
+    /// Відсоток застосовується до всього доданка (`100 + 2 * 5%` = 110) і
+    /// до всієї суми ліворуч (`100 + 50 + 10%` = 165). Без `+` чи `-`
+    /// ліворуч `%` просто ділить на 100: `50%` = 0.5, `200 * 50%` = 100.
+    pub spreadsheet_percent: bool,
+
+    /// Коми між цифрами — роздільники розрядів: `1,000 + 2,000` = 3000.
+    ///
+    /// За замовчуванням `false`. Коли `true`, кома належить числу, тож
+    /// функції з кількома аргументами вимкнено: `max(1,000)` і `max(1, 2)`
+    /// дають [`ParseError::MultiArgCall`]. Групи після першої коми мають містити рівно
+    /// три цифри, а перша — від однієї до трьох, інакше (`1,00`, `1000,000`)
+    /// буде [`ParseError::InvalidDigitGrouping`]. Дробова частина й
+    /// показник степеня ком не мають.
+    pub comma_grouping: bool,
+
+    /// Ключові слова-префікси, що викликають унарну функцію без дужок:
+    /// пари (ключове слово, назва функції).
+    ///
+    /// За замовчуванням порожньо. З парою `("neg", "neg")` запис `neg 5`
+    /// дає `neg(5)`, а з `("abs", "abs")` — `abs -3` дає `abs(-3)`.
+    /// Ключове слово зв'язує як унарний мінус: `abs -2 ^ 2` = `abs(-(2 ^ 2))`,
+    /// а `abs 2 + 1` = `abs(2) + 1`. Таке слово вже не може бути змінною.
+    /// Вбудована функція з іншою кількістю аргументів дає
+    /// [`ParseError::WrongArity`].
+    pub prefix_keywords: Vec<(String, String)>,
+
+    /// Позиції неочікуваних токенів у помилках: [`ParseError::UnexpectedTokenAt`]
+    /// замість [`ParseError::UnexpectedToken`].
+    ///
+    /// За замовчуванням `false`. Позиція потрібна [`ParseError::with_context`]
+    /// і [`render_diagnostic`], щоб показати місце помилки у вводі.
+    pub token_positions: bool,
+}
+
+impl ParserConfig {
+    /// Назва функції для ключового слова-префікса `text`
+    fn prefix_function(&self, text: &str) -> Option<&str> {
+        self.prefix_keywords
+            .iter()
+            .find(|(keyword, _)| keyword == text)
+            .map(|(_, function)| function.as_str())
+    }
+
+    /// Перевіряє, що оператор `op` дозволено
+    fn check_operator(&self, op: char) -> Result<(), ParseError> {
+        match &self.allowed_operators {
+            Some(allowed) if !allowed.contains(&op) => Err(ParseError::OperatorNotAllowed(op)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Парсить арифметичний вираз у рядку та повертає AST
+///
+/// # Граматика
+///
+/// Comparison = Expr { ("<" | ">") Expr } ;
+/// Expr       = Term { ("+" | "-") Term } ;
+/// Term       = Unary { ("*" | "/") Unary } ;
+/// Unary      = "-" Unary | Power ;
+/// Power      = Factor [ ("^" | "**" | "⊕") Unary ] ;
+/// Factor     = Number | Let | Call | Ident | "e" | "i" | "(" Comparison ")" ;
+/// Let        = "let" Ident "=" Comparison "in" Comparison ;
+/// Call       = Ident "(" [ Comparison { "," Comparison } ] ")" ;
+/// Number     = digit { digit } [ "." digit { digit } ] \[ Exponent \] ;
+/// Exponent   = ("e" | "E") [ "+" | "-" ] digit { digit } ;
+///
+/// Ланцюжок порівнянь `1 < 2 < 3` означає `(1 < 2) & (2 < 3)`. Уявна
+/// одиниця `i` розпізнається лише з feature `complex`.
+///
+/// `e` впритул до цифр, за яким ідуть цифри, — показник степеня
+/// (`2e3` = 2000, `1.5e-3` = 0.0015). Окреме `e` або `e` після нецифри —
+/// константа Ейлера: `2 * e`, `e + 1`.
+///
+/// Повну граматику з усіма вбудованими функціями та увімкненими feature
+/// повертає [`grammar`].
+///
+/// # Приклад
+/// ```
+/// let expr = tree_parser::parse_expression("3 + 5 * (2 - 8) / 4").unwrap();
+/// ```
+pub fn parse_expression(input: &str) -> Result<Expr, ParseError> {
+    parse_expression_with(input, &ParserConfig::default())
+}
+
+/// Парсить арифметичний вираз із заданими налаштуваннями парсера
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{ParserConfig, evaluate, parse_expression_with};
+///
+/// let config = ParserConfig {
+///     caret_is_xor: true,
+///     ..ParserConfig::default()
+/// };
+/// let expr = parse_expression_with("6 ^ 3", &config).unwrap();
+/// assert_eq!(evaluate::<f64>(&expr), 5.0);
+/// ```
+pub fn parse_expression_with(input: &str, config: &ParserConfig) -> Result<Expr, ParseError> {
+    let mut tokens = tokenize(input, config)?;
+    parse_tokens(&mut tokens, config, &mut AstBuilder).map_err(|err| in_chars(err, input))
+}
+
+/// Парсить вираз і повертає AST разом із діапазонами вихідного тексту вузлів
+///
+/// Дерево [`SpanTree`] повторює форму AST: корінь відповідає кореню виразу,
+/// а діти — дітям вузла в тому самому порядку. Діапазон підвиразу в дужках
+/// включає самі дужки. Вузли, побудовані вручну, діапазонів не мають — для
+/// них достатньо звичайного [`Expr`].
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{Span, parse_expression_spanned};
+///
+/// let (_, spans) = parse_expression_spanned("2 + 3").unwrap();
+/// assert_eq!(spans.span, Span { start: 0, end: 5 });
+/// assert_eq!(spans.children[1].span, Span { start: 4, end: 5 });
+/// ```
+pub fn parse_expression_spanned(input: &str) -> Result<(Expr, SpanTree), ParseError> {
+    let config = ParserConfig::default();
+    let mut tokens = tokenize(input, &config)?;
+    parse_tokens(&mut tokens, &config, &mut SpannedBuilder).map_err(|err| in_chars(err, input))
+}
+
+/// Знаходить дужки, які не впливають на дерево розбору
+///
+/// Пара дужок зайва, якщо без неї вираз розбирається в те саме AST:
+/// зовнішні дужки `(2 + 3)` чи `2 + (3)`, але не `(2 + 3) * 4`. Дужки
+/// виклику функції не розглядаються. Діапазони включають обидві дужки й
+/// упорядковані за початком; для некоректного виразу результат порожній.
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{Span, find_redundant_parens};
+///
+/// assert_eq!(find_redundant_parens("2 + (3)"), [Span { start: 4, end: 7 }]);
+/// assert!(find_redundant_parens("(2 + 3) * 4").is_empty());
+/// ```
+pub fn find_redundant_parens(input: &str) -> Vec<Span> {
+    let config = ParserConfig::default();
+    let (Ok(expr), Ok(tokens)) = (parse_expression(input), tokenize(input, &config)) else {
+        return Vec::new();
+    };
+
+    // Пари дужок групування; дужки виклику позначаються `None`
+    let mut open: Vec<Option<Span>> = Vec::new();
+    let mut pairs = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        match token.text.as_str() {
+            "(" => {
+                let is_call = i > 0 && is_identifier(&tokens[i - 1].text);
+                open.push((!is_call).then_some(token.span));
+            }
+            ")" => {
+                if let Some(Some(start)) = open.pop() {
+                    pairs.push(start.to(token.span));
+                }
+            }
+            _ => {}
+        }
+    }
+    pairs.sort_by_key(|span| span.start);
+
+    pairs
+        .into_iter()
+        .filter(|span| {
+            // Дужки замінюються пробілами, щоб решта позицій не зсувалася
+            let mut without = input.to_string();
+            without.replace_range(span.start..span.start + 1, " ");
+            without.replace_range(span.end - 1..span.end, " ");
+            parse_expression(&without).is_ok_and(|other| other == expr)
+        })
+        .collect()
+}
+
+/// Парсить арифметичний вираз з обмеженнями на розмір вводу
+///
+/// Повертає [`ParseError::InputTooLarge`], якщо довжина `input` у байтах
+/// перевищує `max_input_len` (перевіряється до токенізації) або якщо
+/// токенів більше за `max_tokens` (перевіряється до парсингу).
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{ParseError, parse_expression_with_limits};
+///
+/// assert!(parse_expression_with_limits("2 + 3", 100, 10).is_ok());
+/// assert!(matches!(
+///     parse_expression_with_limits("2 + 3", 100, 2),
+///     Err(ParseError::InputTooLarge)
+/// ));
+/// ```
+pub fn parse_expression_with_limits(
+    input: &str,
+    max_input_len: usize,
+    max_tokens: usize,
+) -> Result<Expr, ParseError> {
+    if input.len() > max_input_len {
+        return Err(ParseError::InputTooLarge);
+    }
+
+    let config = ParserConfig::default();
+    let mut tokens = tokenize(input, &config)?;
+    if tokens.len() > max_tokens {
+        return Err(ParseError::InputTooLarge);
+    }
+    parse_tokens(&mut tokens, &config, &mut AstBuilder).map_err(|err| in_chars(err, input))
+}
+
+/// Парсить арифметичний вираз із байтового зрізу ASCII та повертає AST
+///
+/// Працює напряму з байтами без перевірки UTF-8, тому підходить для великих
+/// ASCII-вводів. Будь-який байт поза ASCII дає [`ParseError::NonAsciiByte`].
+///
+/// # Приклад
+/// ```
+/// let expr = tree_parser::parse_expression_bytes(b"2 + 3").unwrap();
+/// assert_eq!(expr, tree_parser::parse_expression("2 + 3").unwrap());
+/// ```
+pub fn parse_expression_bytes(input: &[u8]) -> Result<Expr, ParseError> {
+    if let Some(position) = input.iter().position(|b| !b.is_ascii()) {
+        return Err(ParseError::NonAsciiByte {
+            byte: input[position],
+            position,
+        });
+    }
+
+    let config = ParserConfig::default();
+    let chars = input.iter().enumerate().map(|(i, &b)| (i, b as char));
+    let mut tokens = tokenize_chars(chars, &config)?;
+    parse_tokens(&mut tokens, &config, &mut AstBuilder)
+}
+
+/// Парсить і одразу обчислює вираз за один прохід, не будуючи AST
+///
+/// Результат збігається з [`try_evaluate`] для [`parse_expression`]; помилки
+/// обчислення повертаються як [`ParseEvalError::Eval`].
+///
+/// # Приклад
+/// ```
+/// assert_eq!(tree_parser::parse_and_eval("2 + 3 * 4").unwrap(), 14.0);
+/// ```
+pub fn parse_and_eval(input: &str) -> Result<f64, ParseEvalError> {
+    parse_and_eval_with(input, &ParserConfig::default())
+}
+
+/// Те саме, що [`parse_and_eval`], але з налаштуваннями парсера `config`
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{ParseError, ParseEvalError, ParserConfig, parse_and_eval_with};
+///
+/// let config = ParserConfig {
+///     token_positions: true,
+///     ..ParserConfig::default()
+/// };
+/// let err = parse_and_eval_with("2 + )", &config).unwrap_err();
+/// assert!(matches!(
+///     err,
+///     ParseEvalError::Parse(ParseError::UnexpectedTokenAt { position: 4, .. })
+/// ));
+/// ```
+pub fn parse_and_eval_with(input: &str, config: &ParserConfig) -> Result<f64, ParseEvalError> {
+    let mut tokens = tokenize(input, config)?;
+    let value = parse_tokens(&mut tokens, config, &mut EvalBuilder::default())
+        .map_err(|err| in_chars(err, input))?;
+    Ok(value?)
+}
+
+/// Діапазон байтів `start..end` у вихідному рядку
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    /// Початок діапазону (включно)
+    pub start: usize,
+    /// Кінець діапазону (не включно)
+    pub end: usize,
+}
+
+impl Span {
+    /// Найменший діапазон, що охоплює обидва
+    fn to(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+/// Діапазони вихідного тексту для кожного вузла AST, див. [`parse_expression_spanned`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanTree {
+    /// Діапазон вузла
+    pub span: Span,
+    /// Діапазони дочірніх вузлів у порядку дітей [`Expr`]
+    pub children: Vec<SpanTree>,
+}
+
+/// Токен разом із його положенням у вхідному рядку
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    text: String,
+    span: Span,
+}
+
+impl Token {
+    /// Помилка про те, що цей токен тут не очікувався
+    fn unexpected(&self, config: &ParserConfig) -> ParseError {
+        if config.token_positions {
+            ParseError::UnexpectedTokenAt {
+                token: self.text.clone(),
+                position: self.span.start,
+            }
+        } else {
+            ParseError::UnexpectedToken(self.text.clone())
+        }
+    }
+}
+
+/// Спосіб побудови результату парсингу з розпізнаних вузлів
+///
+/// Граматика реалізована один раз, а реалізації цього трейту вирішують,
+/// що саме будувати: звичайне AST, AST з діапазонами чи одразу значення.
+trait Builder {
+    type Node: Clone;
+
+    fn number(&mut self, value: f64, span: Span) -> Self::Node;
+    fn variable(&mut self, name: String, span: Span) -> Self::Node;
+    #[cfg(feature = "units")]
+    fn quantity(&mut self, value: f64, unit: String, span: Span) -> Self::Node;
+    #[cfg(feature = "complex")]
+    fn imaginary(&mut self, span: Span) -> Self::Node;
+    fn neg(&mut self, operand: Self::Node, span: Span) -> Self::Node;
+    fn binary(&mut self, op: char, left: Self::Node, right: Self::Node, span: Span) -> Self::Node;
+    fn call(&mut self, name: String, args: Vec<Self::Node>, span: Span) -> Self::Node;
+    /// Підвираз у дужках із діапазоном, що включає дужки
+    fn group(&mut self, node: Self::Node, span: Span) -> Self::Node;
+    fn let_in(
+        &mut self,
+        name: String,
+        value: Self::Node,
+        body: Self::Node,
+        span: Span,
+    ) -> Self::Node;
+
+    /// Початок області видимості змінної `name` перед парсингом тіла `let`
+    fn bind(&mut self, _name: &str, _value: &Self::Node) {}
+    /// Кінець області видимості, відкритої останнім [`Builder::bind`]
+    fn unbind(&mut self) {}
+}
+
+/// Будує звичайне [`Expr`]
+struct AstBuilder;
+
+impl Builder for AstBuilder {
+    type Node = Expr;
+
+    fn number(&mut self, value: f64, _: Span) -> Expr {
+        Expr::Number(value)
+    }
+
+    fn variable(&mut self, name: String, _: Span) -> Expr {
+        Expr::Variable(name)
+    }
+
+    #[cfg(feature = "units")]
+    fn quantity(&mut self, value: f64, unit: String, _: Span) -> Expr {
+        Expr::Quantity { value, unit }
+    }
+
+    #[cfg(feature = "complex")]
+    fn imaginary(&mut self, _: Span) -> Expr {
+        Expr::Imaginary
+    }
+
+    fn neg(&mut self, operand: Expr, _: Span) -> Expr {
+        Expr::Neg(Box::new(operand))
+    }
+
+    fn binary(&mut self, op: char, left: Expr, right: Expr, _: Span) -> Expr {
+        Expr::BinaryOp {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    fn call(&mut self, name: String, args: Vec<Expr>, _: Span) -> Expr {
+        Expr::Func { name, args }
+    }
+
+    fn group(&mut self, node: Expr, _: Span) -> Expr {
+        node
+    }
+
+    fn let_in(&mut self, name: String, value: Expr, body: Expr, _: Span) -> Expr {
+        Expr::Let {
+            name,
+            value: Box::new(value),
+            body: Box::new(body),
+        }
+    }
+}
+
+/// Будує [`Expr`] разом із [`SpanTree`]
+struct SpannedBuilder;
+
+impl Builder for SpannedBuilder {
+    type Node = (Expr, SpanTree);
+
+    fn number(&mut self, value: f64, span: Span) -> Self::Node {
+        let children = Vec::new();
+        (Expr::Number(value), SpanTree { span, children })
+    }
+
+    fn variable(&mut self, name: String, span: Span) -> Self::Node {
+        let children = Vec::new();
+        (Expr::Variable(name), SpanTree { span, children })
+    }
+
+    #[cfg(feature = "units")]
+    fn quantity(&mut self, value: f64, unit: String, span: Span) -> Self::Node {
+        let children = Vec::new();
+        (Expr::Quantity { value, unit }, SpanTree { span, children })
+    }
+
+    #[cfg(feature = "complex")]
+    fn imaginary(&mut self, span: Span) -> Self::Node {
+        let children = Vec::new();
+        (Expr::Imaginary, SpanTree { span, children })
+    }
+
+    fn neg(&mut self, (operand, spans): Self::Node, span: Span) -> Self::Node {
+        let children = vec![spans];
+        (Expr::Neg(Box::new(operand)), SpanTree { span, children })
+    }
+
+    fn binary(&mut self, op: char, left: Self::Node, right: Self::Node, span: Span) -> Self::Node {
+        let expr = AstBuilder.binary(op, left.0, right.0, span);
+        let children = vec![left.1, right.1];
+        (expr, SpanTree { span, children })
+    }
+
+    fn call(&mut self, name: String, args: Vec<Self::Node>, span: Span) -> Self::Node {
+        let (args, children): (Vec<Expr>, Vec<SpanTree>) = args.into_iter().unzip();
+        (Expr::Func { name, args }, SpanTree { span, children })
+    }
+
+    fn group(&mut self, (expr, mut spans): Self::Node, span: Span) -> Self::Node {
+        spans.span = span;
+        (expr, spans)
+    }
+
+    fn let_in(
+        &mut self,
+        name: String,
+        value: Self::Node,
+        body: Self::Node,
+        span: Span,
+    ) -> Self::Node {
+        let expr = AstBuilder.let_in(name, value.0, body.0, span);
+        let children = vec![value.1, body.1];
+        (expr, SpanTree { span, children })
+    }
+}
+
+/// Обчислює значення вузлів одразу під час парсингу
+#[derive(Default)]
+struct EvalBuilder {
+    /// Значення змінних `let`, у тілі яких зараз парсер
+    scope: Scope<Result<f64, EvalError>>,
+}
+
+impl Builder for EvalBuilder {
+    type Node = Result<f64, EvalError>;
+
+    fn number(&mut self, value: f64, _: Span) -> Self::Node {
+        Ok(value)
+    }
+
+    fn variable(&mut self, name: String, _: Span) -> Self::Node {
+        match lookup(&self.scope, &name) {
+            Some(value) => value.clone(),
+            None => Err(EvalError::UndefinedVariable(name)),
+        }
+    }
+
+    #[cfg(feature = "units")]
+    fn quantity(&mut self, value: f64, _: String, _: Span) -> Self::Node {
+        Ok(value)
+    }
+
+    #[cfg(feature = "complex")]
+    fn imaginary(&mut self, _: Span) -> Self::Node {
+        Err(EvalError::NotReal)
+    }
+
+    fn neg(&mut self, operand: Self::Node, _: Span) -> Self::Node {
+        Ok(-operand?)
+    }
+
+    fn binary(&mut self, op: char, left: Self::Node, right: Self::Node, _: Span) -> Self::Node {
+        apply_binary_op(op, left?, right?)
+    }
+
+    fn call(&mut self, name: String, args: Vec<Self::Node>, _: Span) -> Self::Node {
+        let values = args.into_iter().collect::<Result<Vec<_>, _>>()?;
+        apply_function(&name, &values)
+    }
+
+    fn group(&mut self, node: Self::Node, _: Span) -> Self::Node {
+        node
+    }
+
+    fn let_in(&mut self, _: String, _: Self::Node, body: Self::Node, _: Span) -> Self::Node {
+        body
+    }
+
+    fn bind(&mut self, name: &str, value: &Self::Node) {
+        self.scope.push((name.to_string(), value.clone()));
+    }
+
+    fn unbind(&mut self) {
+        self.scope.pop();
+    }
+}
+
+/// Вузол, що будується, разом із його діапазоном
+type Parsed<B> = (<B as Builder>::Node, Span);
+
+/// Парсить увесь список токенів, починаючи з верхнього правила граматики
+fn parse_tokens<B: Builder>(
+    tokens: &mut Vec<Token>,
+    config: &ParserConfig,
+    b: &mut B,
+) -> Result<B::Node, ParseError> {
+    let (node, _) = parse_comparison(tokens, config, b)?;
+    // Поза суворим режимом решта токенів відкидається, але оператор,
+    // записаний словом чи іншим символом (`2 x 3`), дає помилку з підказкою
+    // (див. [`ParseError::suggestion`])
+    if let Some(token) = tokens.first() {
+        // Операнд одразу після виразу: `2 (3)`, `(1) (2)`. Позиція тут у
+        // байтах, у символи її переводить [`in_chars`]
+        if config.strict
+            && (token.text == "(" || token.text.starts_with(|c: char| c.is_ascii_digit()))
+        {
+            return Err(ParseError::MissingOperator {
+                token: token.text.clone(),
+                position: token.span.start,
+            });
+        }
+        if config.strict || spelled_operator(&token.text).is_some() {
+            return Err(token.unexpected(config));
+        }
+    }
+    Ok(node)
+}
+
+/// Перший символ наступного токена
+fn peek_char(tokens: &[Token]) -> Option<char> {
+    tokens.first().and_then(|t| t.text.chars().next())
+}
+
+/// Текст наступного токена
+fn peek_text(tokens: &[Token]) -> Option<&str> {
+    tokens.first().map(|t| t.text.as_str())
+}
+
+/// Токенізація рядка у вектор токенів
+fn tokenize(input: &str, config: &ParserConfig) -> Result<Vec<Token>, ParseError> {
+    tokenize_chars(input.char_indices(), config).map_err(|err| in_chars(err, input))
+}
+
+/// Переводить байтову позицію [`ParseError::MissingOperator`] у `input` в
+/// символьну; решта помилок лишається без змін
+fn in_chars(err: ParseError, input: &str) -> ParseError {
+    match err {
+        ParseError::MissingOperator { token, position } => ParseError::MissingOperator {
+            token,
+            position: input[..position].chars().count(),
+        },
+        other => other,
+    }
+}
+
+/// Токенізація послідовності символів з їхніми позиціями (у байтах) у вектор токенів
+///
+/// У суворому режимі ([`ParserConfig::strict`]) число, відокремлене від
+/// попереднього числа лише пробілами, дає [`ParseError::MissingOperator`].
+fn tokenize_chars(
+    input: impl Iterator<Item = (usize, char)> + Clone,
+    config: &ParserConfig,
+) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut number = String::new();
+    let mut gap_after_number = false;
+    // Діапазон поточного числа
+    let mut number_span = Span { start: 0, end: 0 };
+    let mut chars = input.peekable();
+
+    let flush =
+        |number: &mut String, span: Span, tokens: &mut Vec<Token>| -> Result<(), ParseError> {
+            if !number.is_empty() {
+                let mut text = std::mem::take(number);
+                if text.contains(',') {
+                    text = ungroup_digits(&text).ok_or(ParseError::InvalidDigitGrouping {
+                        token: text,
+                        position: span.start,
+                    })?;
+                }
+                tokens.push(Token { text, span });
+            }
+            Ok(())
+        };
+
+    while let Some((position, ch)) = chars.next() {
+        let end = position + ch.len_utf8();
+        if is_space(ch) {
+            if config.strict && !number.is_empty() {
+                flush(&mut number, number_span, &mut tokens)?;
+                gap_after_number = true;
+            }
+            continue;
+        } else if ch.is_ascii_digit() {
+            if gap_after_number {
+                let mut token = ch.to_string();
+                while let Some((_, digit)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+                    token.push(digit);
+                }
+                return Err(ParseError::MissingOperator { token, position });
+            }
+            if number.is_empty() {
+                number_span.start = position;
+            }
+            number.push(ch);
+            number_span.end = end;
+        } else if ch == ','
+            && config.comma_grouping
+            && number_span.end == position
+            && !number.is_empty()
+            && number.chars().all(|c| c.is_ascii_digit() || c == ',')
+            && chars.peek().is_some_and(|(_, c)| c.is_ascii_digit())
+        {
+            // Роздільник розрядів між цифрами: `1,000`
+            number.push(ch);
+            number_span.end = end;
+        } else if ch == '.'
+            && number_span.end == position
+            && !number.is_empty()
+            && number.chars().all(|c| c.is_ascii_digit() || c == ',')
+            && chars.peek().is_some_and(|(_, c)| c.is_ascii_digit())
+        {
+            // Десяткова крапка між цифрами: `0.5`
+            number.push(ch);
+            number_span.end = end;
+        } else if (ch == 'e' || ch == 'E')
+            && number_span.end == position
+            && !number.is_empty()
+            && number
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+            && starts_exponent(chars.clone())
+        {
+            // Експонента впритул до числа: `2e3`, `1.5e-3`
+            number.push(ch);
+            number_span.end = end;
+            if let Some((i, sign)) = chars.next_if(|(_, c)| *c == '+' || *c == '-') {
+                number.push(sign);
+                number_span.end = i + 1;
+            }
+        } else if !cfg!(feature = "units")
+            && (ch == 'e' || ch == 'E')
+            && number_span.end == position
+            && !number.is_empty()
+            && number
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+        {
+            // `e` впритул до числа без цифр показника: `1e`, `2e+`
+            let mut token = format!("{}{}", number, ch);
+            if let Some((_, sign)) = chars.next_if(|(_, c)| *c == '+' || *c == '-') {
+                token.push(sign);
+            }
+            return Err(ParseError::MalformedExponent {
+                token,
+                position: number_span.start,
+            });
+        } else if cfg!(feature = "units")
+            && ch.is_alphabetic()
+            && !number.is_empty()
+            && number_span.end == position
+        {
+            // Суфікс одиниці вимірювання впритул до числа: `3m`
+            number.push(ch);
+            number_span.end = end;
+        } else if ch.is_alphabetic() {
+            gap_after_number = false;
+            flush(&mut number, number_span, &mut tokens)?;
+            let mut text = ch.to_string();
+            let mut span = Span {
+                start: position,
+                end,
+            };
+            while let Some((i, c)) = chars.next_if(|(_, c)| c.is_alphanumeric() || *c == '_') {
+                text.push(c);
+                span.end = i + c.len_utf8();
+            }
+            // Перевіряється до аргументів, бо `max(1,2)` інакше дало б
+            // помилку групування розрядів
+            if config.comma_grouping
+                && builtin_arity(&text).is_some_and(|arity| arity > 1)
+                && chars.clone().find(|(_, c)| !is_space(*c)).map(|(_, c)| c) == Some('(')
+            {
+                return Err(ParseError::MultiArgCall {
+                    name: text,
+                    position,
+                });
+            }
+            tokens.push(Token { text, span });
+        } else {
+            gap_after_number = false;
+            flush(&mut number, number_span, &mut tokens)?;
+            let mut span = Span {
+                start: position,
+                end,
+            };
+            let mut text = ch.to_string();
+            // `**` — окремий токен степеня
+            if ch == '*'
+                && let Some((i, star)) = chars.next_if(|(_, c)| *c == '*')
+            {
+                text.push(star);
+                span.end = i + star.len_utf8();
+            }
+            tokens.push(Token { text, span });
+        }
+    }
+
+    flush(&mut number, number_span, &mut tokens)?;
+    Ok(tokens)
+}
+
+/// Прибирає коми-роздільники з числа `1,234.5`, якщо групи розрядів правильні
+fn ungroup_digits(number: &str) -> Option<String> {
+    let split = number
+        .find(|c: char| !c.is_ascii_digit() && c != ',')
+        .unwrap_or(number.len());
+    let (integer, rest) = number.split_at(split);
+    let mut groups = integer.split(',');
+    let first = groups.next()?;
+    let valid = (1..=3).contains(&first.len()) && groups.all(|group| group.len() == 3);
+    valid.then(|| format!("{}{}", integer.replace(',', ""), rest))
+}
+
+/// Чи пропускається символ між токенами: пробільні символи Unicode
+/// (зокрема нерозривний пробіл U+00A0, табуляція й `\r\n`) та символи
+/// нульової ширини U+200B і U+FEFF (BOM на початку файлу)
+fn is_space(ch: char) -> bool {
+    ch.is_whitespace() || ch == '\u{200b}' || ch == '\u{feff}'
+}
+
+/// Чи починається `rest` (символи після `e`) з показника степеня: `3`, `-3`, `+3`
+fn starts_exponent(mut rest: impl Iterator<Item = (usize, char)>) -> bool {
+    match rest.next() {
+        Some((_, '+' | '-')) => rest.next().is_some_and(|(_, c)| c.is_ascii_digit()),
+        Some((_, c)) => c.is_ascii_digit(),
+        None => false,
+    }
+}
+
+/// Реалізація правила граматики Comparison = Expr { ("<" | ">") Expr }
+///
+/// Ланцюжок `a < b < c` розгортається у кон'юнкцію `(a < b) & (b < c)`,
+/// де кожна пара сусідніх операндів порівнюється окремо.
+fn parse_comparison<B: Builder>(
+    tokens: &mut Vec<Token>,
+    config: &ParserConfig,
+    b: &mut B,
+) -> Result<Parsed<B>, ParseError> {
+    let mut left = parse_expr(tokens, config, b)?;
+    let mut chain: Option<Parsed<B>> = None;
+
+    while let Some(op) = peek_char(tokens) {
+        if !COMPARISON_OPS.contains(&op) {
+            break;
+        }
+        config.check_operator(op)?;
+        take_operator(tokens)?;
+        let right = parse_expr(tokens, config, b)?;
+        let span = left.1.to(right.1);
+        let comparison = (b.binary(op, left.0, right.0.clone(), span), span);
+        chain = Some(match chain {
+            None => comparison,
+            Some(prev) => {
+                let span = prev.1.to(comparison.1);
+                (b.binary('&', prev.0, comparison.0, span), span)
+            }
+        });
+        left = right;
+    }
+
+    Ok(chain.unwrap_or(left))
+}
+
+/// Реалізація правила граматики Expr = Term { ("+" | "-") Term }
+///
+/// З [`ParserConfig::spreadsheet_percent`] після кожного Term може стояти `%`.
+fn parse_expr<B: Builder>(
+    tokens: &mut Vec<Token>,
+    config: &ParserConfig,
+    b: &mut B,
+) -> Result<Parsed<B>, ParseError> {
+    if !config.spreadsheet_percent {
+        return parse_binary_op(tokens, config, b, parse_term, ADDITIVE_OPS);
+    }
+
+    let term = parse_term(tokens, config, b)?;
+    let mut left = match take_percent(tokens, config)? {
+        Some(percent) => {
+            let span = term.1.to(percent);
+            let hundred = b.number(100.0, percent);
+            (b.binary('/', term.0, hundred, span), span)
+        }
+        None => term,
+    };
+    while let Some(op) = peek_char(tokens) {
+        if !ADDITIVE_OPS.contains(&op) {
+            break;
+        }
+        config.check_operator(op)?;
+        take_operator(tokens)?;
+        let right = parse_term(tokens, config, b)?;
+        left = match take_percent(tokens, config)? {
+            // `l ± t%` стає `l * (100 ± t) / 100`, щоб лівий операнд, який
+            // містить усі попередні відсотки, не копіювався
+            Some(percent) => {
+                let span = left.1.to(percent);
+                let hundred = b.number(100.0, percent);
+                let factor = b.binary(op, hundred, right.0, right.1.to(percent));
+                let scaled = b.binary('*', left.0, factor, span);
+                let hundred = b.number(100.0, percent);
+                (b.binary('/', scaled, hundred, span), span)
+            }
+            None => {
+                let span = left.1.to(right.1);
+                (b.binary(op, left.0, right.0, span), span)
+            }
+        };
+    }
+    Ok(left)
+}
+
+/// Забирає `%` після доданка й повертає його діапазон
+fn take_percent(
+    tokens: &mut Vec<Token>,
+    config: &ParserConfig,
+) -> Result<Option<Span>, ParseError> {
+    if peek_text(tokens) != Some("%") {
+        return Ok(None);
+    }
+    config.check_operator('%')?;
+    Ok(Some(tokens.remove(0).span))
+}
+
+/// Реалізація правила граматики Term = Unary { ("*" | "/") Unary }
+fn parse_term<B: Builder>(
+    tokens: &mut Vec<Token>,
+    config: &ParserConfig,
+    b: &mut B,
+) -> Result<Parsed<B>, ParseError> {
+    parse_binary_op(tokens, config, b, parse_unary, MULTIPLICATIVE_OPS)
+}
+
+/// Реалізація правила граматики Unary = "-" Unary | Power
+///
+/// Унарний мінус зв'язує слабше за степінь: `-2 ^ 2` = `-(2 ^ 2)`. Так само
+/// зв'язують ключові слова з [`ParserConfig::prefix_keywords`].
+fn parse_unary<B: Builder>(
+    tokens: &mut Vec<Token>,
+    config: &ParserConfig,
+    b: &mut B,
+) -> Result<Parsed<B>, ParseError> {
+    if let Some(function) = peek_text(tokens).and_then(|text| config.prefix_function(text)) {
+        let function = function.to_string();
+        if let Some(expected) = builtin_arity(&function)
+            && expected != 1
+        {
+            return Err(ParseError::WrongArity {
+                name: function,
+                expected,
+                found: 1,
+            });
+        }
+        let keyword = take_operator(tokens)?;
+        let (operand, operand_span) = parse_unary(tokens, config, b)?;
+        let span = keyword.span.to(operand_span);
+        return Ok((b.call(function, vec![operand], span), span));
+    }
+    if peek_text(tokens) != Some("-") {
+        return parse_power(tokens, config, b);
+    }
+
+    config.check_operator('-')?;
+    let minus = take_operator(tokens)?;
+    let (operand, operand_span) = parse_unary(tokens, config, b)?;
+    let span = minus.span.to(operand_span);
+    Ok((b.neg(operand, span), span))
+}
+
+/// Парсинг бінарної операції
+fn parse_binary_op<B: Builder, F>(
+    tokens: &mut Vec<Token>,
+    config: &ParserConfig,
+    b: &mut B,
+    subparser: F,
+    ops: &[char],
+) -> Result<Parsed<B>, ParseError>
+where
+    F: Fn(&mut Vec<Token>, &ParserConfig, &mut B) -> Result<Parsed<B>, ParseError>,
+{
+    let mut left = subparser(tokens, config, b)?;
+    while let Some(op) = peek_char(tokens) {
+        if ops.contains(&op) {
+            config.check_operator(op)?;
+            take_operator(tokens)?;
+            let right = subparser(tokens, config, b)?;
+            let span = left.1.to(right.1);
+            left = (b.binary(op, left.0, right.0, span), span);
+        } else {
+            break;
+        }
+    }
+    Ok(left)
+}
+
+/// Реалізація правила граматики Power = Factor [ ("^" | "**" | "⊕") Unary ]
+///
+/// Степінь правоасоціативний: `2 ^ 3 ^ 2` = `2 ^ (3 ^ 2)`, а показник
+/// може мати унарний мінус: `2 ^ -1`. Якщо
+/// [`ParserConfig::caret_is_xor`] увімкнено, `^` стає оператором XOR `'⊕'`,
+/// а `**` лишається степенем. Сам символ `⊕`, яким XOR друкує
+/// [`Expr::to_infix`], — XOR за будь-яких налаштувань.
+fn parse_power<B: Builder>(
+    tokens: &mut Vec<Token>,
+    config: &ParserConfig,
+    b: &mut B,
+) -> Result<Parsed<B>, ParseError> {
+    let base = parse_factor(tokens, config, b)?;
+    // Оператор у дереві та символ, яким його перевіряє `allowed_operators`
+    let (op, written) = match peek_text(tokens) {
+        Some("^") if config.caret_is_xor => ('⊕', '^'),
+        Some("^" | "**") => ('^', '^'),
+        Some("⊕") => ('⊕', '⊕'),
+        _ => return Ok(base),
+    };
+
+    config.check_operator(written)?;
+    take_operator(tokens)?;
+    let exponent = parse_unary(tokens, config, b)?;
+    let span = base.1.to(exponent.1);
+    Ok((b.binary(op, base.0, exponent.0, span), span))
+}
+
+/// Реалізація правила граматики Factor = Number | Let | Call | Ident | "i" | "(" Comparison ")"
+///
+/// Ідентифікатор без дужок після нього — змінна.
+fn parse_factor<B: Builder>(
+    tokens: &mut Vec<Token>,
+    config: &ParserConfig,
+    b: &mut B,
+) -> Result<Parsed<B>, ParseError> {
+    if tokens.is_empty() {
+        return Err(ParseError::UnexpectedEnd);
+    }
+
+    let Token { text, span } = tokens.remove(0);
+
+    if text == "let" {
+        parse_let(span, tokens, config, b)
+    } else if is_identifier(&text) && peek_text(tokens) == Some("(") {
+        tokens.remove(0);
+        parse_call(text, span, tokens, config, b)
+    } else if text == "(" {
+        let (expr, _) = parse_comparison(tokens, config, b)?;
+        if peek_text(tokens) != Some(")") {
+            return Err(ParseError::MissingClosingParenthesis);
+        }
+        let span = span.to(tokens.remove(0).span);
+        Ok((b.group(expr, span), span))
+    } else if let Ok(num) = text.parse::<f64>() {
+        // Надто довгий літерал парситься як нескінченність
+        if !num.is_finite() {
+            return Err(ParseError::NumberOutOfRange(text));
+        }
+        Ok((b.number(num, span), span))
+    } else {
+        if let Some(&(_, value)) = CONSTANTS.iter().find(|(name, _)| *name == text) {
+            return Ok((b.number(value, span), span));
+        }
+        #[cfg(feature = "complex")]
+        if text == "i" {
+            return Ok((b.imaginary(span), span));
+        }
+        #[cfg(feature = "units")]
+        if let Some((value, unit)) = parse_quantity(&text) {
+            if !value.is_finite() {
+                return Err(ParseError::NumberOutOfRange(text));
+            }
+            return Ok((b.quantity(value, unit, span), span));
+        }
+        if is_identifier(&text) {
+            return Ok((b.variable(text, span), span));
+        }
+        Err(Token { text, span }.unexpected(config))
+    }
+}
+
+/// Реалізація правила граматики Let = "let" Ident "=" Comparison "in" Comparison
+///
+/// Викликається після ключового слова `let`. Тіло тягнеться якомога далі
+/// праворуч: `let x = 1 in x + 1` = `let x = 1 in (x + 1)`.
+fn parse_let<B: Builder>(
+    let_span: Span,
+    tokens: &mut Vec<Token>,
+    config: &ParserConfig,
+    b: &mut B,
+) -> Result<Parsed<B>, ParseError> {
+    let name = match tokens.first() {
+        Some(token) if is_identifier(&token.text) => tokens.remove(0).text,
+        Some(token) => return Err(token.unexpected(config)),
+        None => return Err(ParseError::UnexpectedEnd),
+    };
+    expect_token(tokens, "=", config)?;
+    let (value, _) = parse_comparison(tokens, config, b)?;
+    expect_token(tokens, "in", config)?;
+
+    b.bind(&name, &value);
+    let body = parse_comparison(tokens, config, b);
+    b.unbind();
+    let (body, body_span) = body?;
+
+    let span = let_span.to(body_span);
+    Ok((b.let_in(name, value, body, span), span))
+}
+
+/// Знімає токен оператора; якщо після нього вводу немає, повертає
+/// [`ParseError::MissingOperand`] з позицією одразу після оператора
+fn take_operator(tokens: &mut Vec<Token>) -> Result<Token, ParseError> {
+    let operator = tokens.remove(0);
+    if tokens.is_empty() {
+        return Err(ParseError::MissingOperand {
+            op: operator.text,
+            position: operator.span.end,
+        });
+    }
+    Ok(operator)
+}
+
+/// Знімає наступний токен, якщо він дорівнює `expected`
+fn expect_token(
+    tokens: &mut Vec<Token>,
+    expected: &str,
+    config: &ParserConfig,
+) -> Result<(), ParseError> {
+    match tokens.first() {
+        Some(token) if token.text == expected => {
+            tokens.remove(0);
+            Ok(())
+        }
+        Some(token) => Err(token.unexpected(config)),
+        None => Err(ParseError::UnexpectedEnd),
+    }
+}
+
+/// Оператори порівняння, правило Comparison
+const COMPARISON_OPS: &[char] = &['<', '>'];
+
+/// Адитивні оператори, правило Expr
+const ADDITIVE_OPS: &[char] = &['+', '-'];
+
+/// Мультиплікативні оператори, правило Term
+const MULTIPLICATIVE_OPS: &[char] = &['*', '/'];
+
+/// Оператори правила Power: степінь `^` чи `**` та XOR `⊕`
+const POWER_TOKENS: &[&str] = &["^", "**", "⊕"];
+
+/// Вбудовані функції з кількістю аргументів
+const BUILTINS: &[(&str, usize)] = &[
+    ("abs", 1),
+    ("floor", 1),
+    ("ceil", 1),
+    ("round", 1),
+    ("sin", 1),
+    ("cos", 1),
+    ("tan", 1),
+    ("min", 2),
+    ("max", 2),
+];
+
+/// Ключові слова, які не можуть бути назвами змінних чи функцій
+const KEYWORDS: &[&str] = &["let", "in"];
+
+/// Вбудовані константи; їхні назви теж не можуть бути назвами змінних
+const CONSTANTS: &[(&str, f64)] = &[("e", std::f64::consts::E)];
+
+/// Чи є токен ідентифікатором (назвою змінної чи функції)
+fn is_identifier(text: &str) -> bool {
+    text.starts_with(char::is_alphabetic)
+        && !KEYWORDS.contains(&text)
+        && !CONSTANTS.iter().any(|(name, _)| *name == text)
+}
+
+/// Реалізація правила граматики Call = Ident "(" [ Comparison { "," Comparison } ] ")"
+///
+/// Викликається після назви функції та відкривної дужки. Для вбудованих
+/// функцій одразу перевіряється кількість аргументів; невідомі функції
+/// дозволені й перевіряються лише під час обчислення.
+fn parse_call<B: Builder>(
+    name: String,
+    name_span: Span,
+    tokens: &mut Vec<Token>,
+    config: &ParserConfig,
+    b: &mut B,
+) -> Result<Parsed<B>, ParseError> {
+    let mut args = Vec::new();
+    let close = if peek_text(tokens) == Some(")") {
+        tokens.remove(0)
+    } else {
+        loop {
+            args.push(parse_comparison(tokens, config, b)?.0);
+            match peek_text(tokens) {
+                Some(",") if config.comma_grouping => {
+                    return Err(ParseError::MultiArgCall {
+                        name,
+                        position: name_span.start,
+                    });
+                }
+                Some(",") => {
+                    tokens.remove(0);
+                }
+                Some(")") => break tokens.remove(0),
+                _ => return Err(ParseError::MissingClosingParenthesis),
+            }
+        }
+    };
+
+    if let Some(expected) = builtin_arity(&name)
+        && expected != args.len()
+    {
+        return Err(ParseError::WrongArity {
+            name,
+            expected,
+            found: args.len(),
+        });
+    }
+    let span = name_span.to(close.span);
+    Ok((b.call(name, args, span), span))
+}
+
+/// Кількість аргументів вбудованої функції або `None` для невідомої назви
+fn builtin_arity(name: &str) -> Option<usize> {
+    BUILTINS
+        .iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|&(_, arity)| arity)
+}
+
+/// Розбирає токен виду `3m` на число й одиницю
+#[cfg(feature = "units")]
+fn parse_quantity(token: &str) -> Option<(f64, String)> {
+    // Одиниця — кінцеві літери, тож експонента `2e3m` лишається в числі
+    let split = token.rfind(|c: char| !c.is_alphabetic())? + 1;
+    let (value, unit) = token.split_at(split);
+    if !unit.chars().all(char::is_alphabetic) {
+        return None;
+    }
+    Some((value.parse().ok()?, unit.to_string()))
+}
+
+/// Обчислює значення AST у числовому типі `T` (див. [`Num`])
+///
+/// # Паніка
+/// Панікує, якщо обчислення неможливе, наприклад через невідомий оператор
+/// (див. [`try_evaluate`]).
+///
+/// # Приклад
+/// ```
+/// let expr = tree_parser::parse_expression("3 + 5").unwrap();
+/// assert_eq!(tree_parser::evaluate::<f64>(&expr), 8.0);
+/// assert_eq!(tree_parser::evaluate::<f32>(&expr), 8.0);
+/// ```
+pub fn evaluate<T: Num>(expr: &Expr) -> T {
+    match evaluate_num(expr, &mut Vec::new()) {
+        Ok(value) => value,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+fn evaluate_num<T: Num>(expr: &Expr, scope: &mut Scope<T>) -> Result<T, EvalError> {
+    eval_tree(expr, &mut NumEval(PhantomData), scope)
+}
+
+/// Обчислювач у числовому типі `T`, див. [`evaluate`]
+struct NumEval<T>(PhantomData<T>);
+
+impl<T: Num> Evaluator for NumEval<T> {
+    type Value = T;
+
+    fn number(&mut self, value: f64) -> Result<T, EvalError> {
+        Ok(T::from_f64(value))
+    }
+
+    fn neg(&mut self, operand: T) -> Result<T, EvalError> {
+        Ok(operand.neg())
+    }
+
+    fn binary(&mut self, op: char, l: T, r: T) -> Result<T, EvalError> {
+        match op {
+            '+' => Ok(l.add(r)),
+            '-' => Ok(l.sub(r)),
+            '*' => Ok(l.mul(r)),
+            '/' => Ok(l.div(r)),
+            _ => apply_binary_op(op, l.to_f64(), r.to_f64()).map(T::from_f64),
+        }
+    }
+
+    fn call(&mut self, name: &str, args: Vec<T>) -> Result<T, EvalError> {
+        let values: Vec<f64> = args.into_iter().map(T::to_f64).collect();
+        apply_function(name, &values).map(T::from_f64)
+    }
+}
+
+/// Найбільше ціле, до якого `f64` точно представляє всі цілі числа (2^53)
+const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Обчислює значення AST і повідомляє, чи обійшлося без округлень
+///
+/// Обчислення точне, якщо кожен літерал, проміжний результат і сам
+/// результат — ціле число, не більше за модулем 2^53, тобто `f64` не
+/// округлював і ділення не давало дробів.
+///
+/// # Паніка
+/// Панікує, якщо обчислення неможливе (див. [`try_evaluate`]).
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{evaluate_exact, parse_expression};
+///
+/// assert_eq!(evaluate_exact(&parse_expression("6 / 2").unwrap()), (3.0, true));
+/// assert_eq!(evaluate_exact(&parse_expression("7 / 2").unwrap()), (3.5, false));
+/// ```
+pub fn evaluate_exact(expr: &Expr) -> (f64, bool) {
+    match eval_tree(expr, &mut ExactEval, &mut Vec::new()) {
+        Ok(result) => result,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+/// Обчислювач [`evaluate_exact`]: значення та чи обійшлося без округлень
+struct ExactEval;
+
+impl ExactEval {
+    /// Результат `value` точний, якщо точні операнди й він сам — ціле до 2^53
+    fn exact(value: f64, operands_exact: bool) -> (f64, bool) {
+        let exact = operands_exact && value.fract() == 0.0 && value.abs() <= MAX_EXACT_INTEGER;
+        (value, exact)
+    }
+}
+
+impl Evaluator for ExactEval {
+    type Value = (f64, bool);
+
+    fn number(&mut self, value: f64) -> Result<(f64, bool), EvalError> {
+        Ok(Self::exact(value, true))
+    }
+
+    fn neg(&mut self, (value, exact): (f64, bool)) -> Result<(f64, bool), EvalError> {
+        Ok(Self::exact(-value, exact))
+    }
+
+    fn binary(
+        &mut self,
+        op: char,
+        (l, l_exact): (f64, bool),
+        (r, r_exact): (f64, bool),
+    ) -> Result<(f64, bool), EvalError> {
+        Ok(Self::exact(apply_binary_op(op, l, r)?, l_exact && r_exact))
+    }
+
+    fn call(&mut self, name: &str, args: Vec<(f64, bool)>) -> Result<(f64, bool), EvalError> {
+        let values: Vec<f64> = args.iter().map(|arg| arg.0).collect();
+        let value = apply_function(name, &values)?;
+        Ok(Self::exact(value, args.iter().all(|arg| arg.1)))
+    }
+
+    fn let_in(
+        &mut self,
+        value: (f64, bool),
+        (body, body_exact): (f64, bool),
+    ) -> Result<(f64, bool), EvalError> {
+        Ok(Self::exact(body, value.1 && body_exact))
+    }
+}
+
+/// Обчислює значення AST, обриваючи обчислення, щойно літерал чи проміжний
+/// результат перевищить за модулем `max_abs`
+///
+/// Захищає від надто великих чисел, наприклад від ланцюжків `^`. Змінні тут
+/// не задані, тож дають [`EvalError::UndefinedVariable`].
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{EvalError, evaluate_bounded, parse_expression};
+///
+/// let expr = parse_expression("10 ^ 400 - 10 ^ 400").unwrap();
+/// assert!(matches!(
+///     evaluate_bounded(&expr, 1e300),
+///     Err(EvalError::ValueTooLarge { .. })
+/// ));
+/// ```
+pub fn evaluate_bounded(expr: &Expr, max_abs: f64) -> Result<f64, EvalError> {
+    let check = |value: f64| {
+        if value.abs() > max_abs {
+            Err(EvalError::ValueTooLarge { value, max_abs })
+        } else {
+            Ok(())
+        }
+    };
+    eval_tree(expr, &mut CheckedEval(check), &mut Vec::new())
+}
+
+/// Обчислює значення AST, обриваючи обчислення, щойно літерал чи проміжний
+/// результат стане від'ємним
+///
+/// Для лічильників, що не можуть опуститися нижче нуля: `5 - 3 - 4`
+/// зупиняється на `2 - 4` з [`EvalError::NegativeResult`]. Змінні тут не
+/// задані, тож дають [`EvalError::UndefinedVariable`].
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{EvalError, evaluate_non_negative, parse_expression};
+///
+/// let expr = parse_expression("5 - 3 - 4").unwrap();
+/// assert_eq!(evaluate_non_negative(&expr), Err(EvalError::NegativeResult(-2.0)));
+/// ```
+pub fn evaluate_non_negative(expr: &Expr) -> Result<f64, EvalError> {
+    let check = |value: f64| {
+        if value < 0.0 {
+            Err(EvalError::NegativeResult(value))
+        } else {
+            Ok(())
+        }
+    };
+    eval_tree(expr, &mut CheckedEval(check), &mut Vec::new())
+}
+
+/// Обчислює значення AST, виконуючи не більше `max_ops` операцій
+///
+/// Операцією вважається кожен бінарний оператор, унарний мінус і виклик
+/// функції; літерали, змінні й `let` бюджету не витрачають. Щойно бюджет
+/// вичерпано, решта дерева не обчислюється, а результат —
+/// [`EvalError::BudgetExceeded`]. Змінні тут не задані, тож дають
+/// [`EvalError::UndefinedVariable`].
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{EvalError, evaluate_with_budget, parse_expression};
+///
+/// let expr = parse_expression("1 + 2 * 3").unwrap();
+/// assert_eq!(evaluate_with_budget(&expr, 2), Ok(7.0));
+/// assert_eq!(evaluate_with_budget(&expr, 1), Err(EvalError::BudgetExceeded(1)));
+/// ```
+pub fn evaluate_with_budget(expr: &Expr, max_ops: usize) -> Result<f64, EvalError> {
+    let mut budget = BudgetEval {
+        max_ops,
+        remaining: max_ops,
+    };
+    eval_tree(expr, &mut budget, &mut Vec::new())
+}
+
+/// Обчислювач [`evaluate_with_budget`], що витрачає бюджет перед кожною операцією
+struct BudgetEval {
+    max_ops: usize,
+    remaining: usize,
+}
+
+impl Evaluator for BudgetEval {
+    type Value = f64;
+
+    fn number(&mut self, value: f64) -> Result<f64, EvalError> {
+        Ok(value)
+    }
+
+    fn neg(&mut self, operand: f64) -> Result<f64, EvalError> {
+        Ok(-operand)
+    }
+
+    fn binary(&mut self, op: char, l: f64, r: f64) -> Result<f64, EvalError> {
+        apply_binary_op(op, l, r)
+    }
+
+    fn call(&mut self, name: &str, args: Vec<f64>) -> Result<f64, EvalError> {
+        apply_function(name, &args)
+    }
+
+    fn enter(&mut self, expr: &Expr) -> Result<(), EvalError> {
+        if matches!(
+            expr,
+            Expr::Neg(_) | Expr::BinaryOp { .. } | Expr::Func { .. }
+        ) {
+            self.remaining = self
+                .remaining
+                .checked_sub(1)
+                .ok_or(EvalError::BudgetExceeded(self.max_ops))?;
+        }
+        Ok(())
+    }
+}
+
+/// Обчислювач, що перевіряє функцією кожен літерал і проміжний результат
+struct CheckedEval<F>(F);
+
+impl<F: Fn(f64) -> Result<(), EvalError>> Evaluator for CheckedEval<F> {
+    type Value = f64;
+
+    fn number(&mut self, value: f64) -> Result<f64, EvalError> {
+        Ok(value)
+    }
+
+    fn neg(&mut self, operand: f64) -> Result<f64, EvalError> {
+        Ok(-operand)
+    }
+
+    fn binary(&mut self, op: char, l: f64, r: f64) -> Result<f64, EvalError> {
+        apply_binary_op(op, l, r)
+    }
+
+    fn call(&mut self, name: &str, args: Vec<f64>) -> Result<f64, EvalError> {
+        apply_function(name, &args)
+    }
+
+    fn leave(&mut self, _expr: &Expr, value: f64) -> Result<f64, EvalError> {
+        (self.0)(value)?;
+        Ok(value)
+    }
+}
+
+/// Обчислювач [`Expr::evaluate_all_subresults`], що записує кожен підвираз
+struct SubresultsEval(Vec<(String, f64)>);
+
+impl Evaluator for SubresultsEval {
+    type Value = f64;
+
+    fn number(&mut self, value: f64) -> Result<f64, EvalError> {
+        Ok(value)
+    }
+
+    fn neg(&mut self, operand: f64) -> Result<f64, EvalError> {
+        Ok(-operand)
+    }
+
+    fn binary(&mut self, op: char, l: f64, r: f64) -> Result<f64, EvalError> {
+        apply_binary_op(op, l, r)
+    }
+
+    fn call(&mut self, name: &str, args: Vec<f64>) -> Result<f64, EvalError> {
+        apply_function(name, &args)
+    }
+
+    fn leave(&mut self, expr: &Expr, value: f64) -> Result<f64, EvalError> {
+        self.0.push((expr.to_infix(), value));
+        Ok(value)
+    }
+}
+
+/// Обчислювач [`Expr::evaluate_with_history`], що записує значення за шляхом вузла
+struct HistoryEval {
+    /// Шлях до поточного вузла
+    path: Vec<usize>,
+    /// Індекс наступної дитини для кожного вузла на шляху
+    next: Vec<usize>,
+    history: HashMap<Vec<usize>, f64>,
+}
+
+impl Evaluator for HistoryEval {
+    type Value = f64;
+
+    fn number(&mut self, value: f64) -> Result<f64, EvalError> {
+        Ok(value)
+    }
+
+    fn neg(&mut self, operand: f64) -> Result<f64, EvalError> {
+        Ok(-operand)
+    }
+
+    fn binary(&mut self, op: char, l: f64, r: f64) -> Result<f64, EvalError> {
+        apply_binary_op(op, l, r)
+    }
+
+    fn call(&mut self, name: &str, args: Vec<f64>) -> Result<f64, EvalError> {
+        apply_function(name, &args)
+    }
+
+    fn enter(&mut self, _expr: &Expr) -> Result<(), EvalError> {
+        if let Some(index) = self.next.last_mut() {
+            self.path.push(*index);
+            *index += 1;
+        }
+        self.next.push(0);
+        Ok(())
+    }
+
+    fn leave(&mut self, _expr: &Expr, value: f64) -> Result<f64, EvalError> {
+        self.history.insert(self.path.clone(), value);
+        self.next.pop();
+        self.path.pop();
+        Ok(value)
+    }
+}
+
+/// Обчислює значення AST, повертаючи помилку замість паніки
+///
+/// Корисно для виразів, побудованих вручну, які можуть містити
+/// оператор, невідомий обчислювачу, а також для XOR з нецілими операндами.
+/// Змінні тут не задані, тож дають [`EvalError::UndefinedVariable`].
+///
+/// # Приклад
+/// ```
+/// let expr = tree_parser::parse_expression("3 + 5").unwrap();
+/// assert_eq!(tree_parser::try_evaluate(&expr), Ok(8.0));
+/// ```
+pub fn try_evaluate(expr: &Expr) -> Result<f64, EvalError> {
+    evaluate_with(expr, &HashMap::new())
+}
+
+/// Обчислює значення AST зі значеннями змінних із `vars`
+///
+/// # Приклад
+/// ```
+/// use std::collections::HashMap;
+///
+/// let expr = tree_parser::parse_expression("x * x + 1").unwrap();
+/// let vars = HashMap::from([("x".to_string(), 3.0)]);
+/// assert_eq!(tree_parser::evaluate_with(&expr, &vars), Ok(10.0));
+/// ```
+pub fn evaluate_with(expr: &Expr, vars: &HashMap<String, f64>) -> Result<f64, EvalError> {
+    expr.fold(
+        &|n| Ok(n),
+        &|op, l, r| apply_binary_op(op, l?, r?),
+        &|name, args| apply_function(name, &args.into_iter().collect::<Result<Vec<_>, _>>()?),
+        &|leaf| match leaf {
+            Expr::Variable(name) => vars
+                .get(name)
+                .copied()
+                .ok_or_else(|| EvalError::UndefinedVariable(name.clone())),
+            other => Err(leaf_error(other)),
+        },
+    )
+}
+
+/// Обчислювач [`Expr::fold`], що передає кожен вузол функціям згортки
+struct FoldEval<'a, T> {
+    on_number: &'a dyn Fn(f64) -> T,
+    on_op: &'a dyn Fn(char, T, T) -> T,
+    on_call: &'a dyn Fn(&str, Vec<T>) -> T,
+    on_leaf: &'a dyn Fn(&Expr) -> T,
+}
+
+impl<T: Clone> Evaluator for FoldEval<'_, T> {
+    type Value = T;
+
+    fn number(&mut self, value: f64) -> Result<T, EvalError> {
+        Ok((self.on_number)(value))
+    }
+
+    fn neg(&mut self, operand: T) -> Result<T, EvalError> {
+        Ok((self.on_op)('*', (self.on_number)(-1.0), operand))
+    }
+
+    fn binary(&mut self, op: char, l: T, r: T) -> Result<T, EvalError> {
+        Ok((self.on_op)(op, l, r))
+    }
+
+    fn call(&mut self, name: &str, args: Vec<T>) -> Result<T, EvalError> {
+        Ok((self.on_call)(name, args))
+    }
+
+    fn leaf(&mut self, leaf: &Expr) -> Result<T, EvalError> {
+        Ok((self.on_leaf)(leaf))
+    }
+}
+
+/// Обчислює значення AST, вважаючи аргументи `sin`, `cos` і `tan` кутами в
+/// одиницях `mode`
+///
+/// Змінні тут не задані, тож дають [`EvalError::UndefinedVariable`].
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{AngleMode, evaluate_with_angle_mode, parse_expression};
+///
+/// let expr = parse_expression("sin(90)").unwrap();
+/// assert_eq!(evaluate_with_angle_mode(&expr, AngleMode::Degrees), Ok(1.0));
+/// ```
+pub fn evaluate_with_angle_mode(expr: &Expr, mode: AngleMode) -> Result<f64, EvalError> {
+    match mode {
+        AngleMode::Radians => try_evaluate(expr),
+        AngleMode::Degrees => try_evaluate(&degrees_to_radians(expr)),
+    }
+}
+
+/// Копія AST, де кожен аргумент тригонометричної функції переведено з
+/// градусів у радіани
+fn degrees_to_radians(expr: &Expr) -> Expr {
+    match expr {
+        Expr::Func { name, args } if matches!(name.as_str(), "sin" | "cos" | "tan") => Expr::Func {
+            name: name.clone(),
+            args: args
+                .iter()
+                .map(|arg| Expr::BinaryOp {
+                    op: '*',
+                    left: Box::new(degrees_to_radians(arg)),
+                    right: Box::new(Expr::Number(PI / 180.0)),
+                })
+                .collect(),
+        },
+        other => other.map_children(degrees_to_radians),
+    }
+}
+
+/// Таблиця користувацьких функцій для [`evaluate_with_functions`]: назва та
+/// реалізація, що отримує значення аргументів
+pub type FunctionTable = HashMap<String, Box<dyn Fn(&[f64]) -> f64>>;
+
+/// Обчислює значення AST з додатковими функціями із таблиці `funcs`
+///
+/// Функція з таблиці має перевагу над вбудованою з тією самою назвою й сама
+/// перевіряє кількість аргументів. Назва, якої немає ні в таблиці, ні серед
+/// вбудованих, дає [`EvalError::UnknownFunction`].
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{FunctionTable, evaluate_with_functions, parse_expression};
+///
+/// let mut funcs = FunctionTable::new();
+/// funcs.insert("double".to_string(), Box::new(|args| args[0] * 2.0));
+/// let expr = parse_expression("double(21)").unwrap();
+/// assert_eq!(evaluate_with_functions(&expr, &funcs), Ok(42.0));
+/// ```
+pub fn evaluate_with_functions(expr: &Expr, funcs: &FunctionTable) -> Result<f64, EvalError> {
+    let mut table = TableEval { funcs, lazy: false };
+    eval_tree(expr, &mut table, &mut Vec::new())
+}
+
+/// Обчислювач з користувацькими функціями; `lazy` пропускає непотрібні
+/// піддерева, див. [`Expr::evaluate_lazy`]
+struct TableEval<'a> {
+    funcs: &'a FunctionTable,
+    lazy: bool,
+}
+
+impl Evaluator for TableEval<'_> {
+    type Value = f64;
+
+    fn number(&mut self, value: f64) -> Result<f64, EvalError> {
+        Ok(value)
+    }
+
+    fn neg(&mut self, operand: f64) -> Result<f64, EvalError> {
+        Ok(-operand)
+    }
+
+    fn binary(&mut self, op: char, l: f64, r: f64) -> Result<f64, EvalError> {
+        apply_binary_op(op, l, r)
+    }
+
+    fn call(&mut self, name: &str, args: Vec<f64>) -> Result<f64, EvalError> {
+        match self.funcs.get(name) {
+            Some(func) => Ok(func(&args)),
+            None => apply_function(name, &args),
+        }
+    }
+
+    fn shortcut(&mut self, op: char, right: &Expr) -> Option<f64> {
+        // Нульовий літерал праворуч відомий без обчислення лівого операнда
+        (self.lazy && op == '*' && right.as_number() == Some(0.0)).then_some(0.0)
+    }
+
+    fn shortcut_left(&mut self, op: char, left: &f64) -> Option<f64> {
+        (self.lazy && *left == 0.0 && (op == '*' || op == '&')).then_some(0.0)
+    }
+}
+
+/// Обчислює значення AST з функціями з `funcs`, пропускаючи непотрібні
+/// піддерева (див. [`Expr::evaluate_lazy`])
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{FunctionTable, evaluate_lazy_with_functions, parse_expression};
+///
+/// let mut funcs = FunctionTable::new();
+/// funcs.insert("boom".to_string(), Box::new(|_| panic!("not evaluated")));
+/// let expr = parse_expression("(2 - 2) * boom(1)").unwrap();
+/// assert_eq!(evaluate_lazy_with_functions(&expr, &funcs), Ok(0.0));
+/// ```
+pub fn evaluate_lazy_with_functions(expr: &Expr, funcs: &FunctionTable) -> Result<f64, EvalError> {
+    let mut table = TableEval { funcs, lazy: true };
+    eval_tree(expr, &mut table, &mut Vec::new())
+}
+
+/// Обчислює вираз для кожного значення змінної `var` із `values`
+///
+/// Вираз компілюється в байткод один раз (див. [`Expr::to_bytecode`]), тож
+/// підходить для побудови графіків на багатьох точках.
+///
+/// # Паніка
+/// Панікує, якщо вираз містить інші змінні або обчислення неможливе.
+///
+/// # Приклад
+/// ```
+/// let expr = tree_parser::parse_expression("x * x").unwrap();
+/// assert_eq!(tree_parser::evaluate_over(&expr, "x", &[1.0, 2.0, 3.0]), [1.0, 4.0, 9.0]);
+/// ```
+pub fn evaluate_over(expr: &Expr, var: &str, values: &[f64]) -> Vec<f64> {
+    let code = match expr.to_bytecode(&[var]) {
+        Ok(code) => code,
+        Err(err) => panic!("{}", err),
+    };
+    values
+        .iter()
+        .map(|&value| run_bytecode(&code, &[value]))
+        .collect()
+}
+
+/// Обчислює вираз `iterations` разів, щоразу підставляючи попередній
+/// результат як значення змінної `var`, починаючи з `x0`
+///
+/// Вираз компілюється в байткод один раз. За нуля ітерацій повертається `x0`.
+///
+/// # Паніка
+/// Панікує, якщо вираз містить інші змінні або обчислення неможливе.
+///
+/// # Приклад
+/// ```
+/// // Метод Герона: x = (x + 2 / x) / 2 збігається до √2
+/// let expr = tree_parser::parse_expression("(x + 2 / x) / 2").unwrap();
+/// let root = tree_parser::eval_fixed_point(&expr, "x", 1.0, 6);
+/// assert!((root - 2f64.sqrt()).abs() < 1e-12);
+/// ```
+pub fn eval_fixed_point(expr: &Expr, var: &str, x0: f64, iterations: usize) -> f64 {
+    let code = match expr.to_bytecode(&[var]) {
+        Ok(code) => code,
+        Err(err) => panic!("{}", err),
+    };
+    (0..iterations).fold(x0, |x, _| run_bytecode(&code, &[x]))
+}
+
+/// Застосовує вбудовану функцію до значень аргументів
+fn apply_function(name: &str, args: &[f64]) -> Result<f64, EvalError> {
+    let expected =
+        builtin_arity(name).ok_or_else(|| EvalError::UnknownFunction(name.to_string()))?;
+    if args.len() != expected {
+        return Err(EvalError::WrongArity {
+            name: name.to_string(),
+            expected,
+            found: args.len(),
+        });
+    }
+    match name {
+        "abs" => Ok(args[0].abs()),
+        "floor" => Ok(args[0].floor()),
+        "ceil" => Ok(args[0].ceil()),
+        // Половини округлюються від нуля: round(2.5) = 3, round(-2.5) = -3
+        "round" => Ok(args[0].round()),
+        // Кути в радіанах; градуси — через evaluate_with_angle_mode
+        "sin" => Ok(args[0].sin()),
+        "cos" => Ok(args[0].cos()),
+        "tan" => Ok(args[0].tan()),
+        "min" => Ok(args[0].min(args[1])),
+        "max" => Ok(args[0].max(args[1])),
+        _ => Err(EvalError::UnknownFunction(name.to_string())),
+    }
+}
+
+/// Застосовує бінарний оператор до двох значень
+fn apply_binary_op(op: char, l: f64, r: f64) -> Result<f64, EvalError> {
+    match op {
+        '+' => Ok(l + r),
+        '-' => Ok(l - r),
+        '*' => Ok(l * r),
+        '/' => Ok(l / r),
+        '^' => Ok(l.powf(r)),
+        '⊕' => Ok((to_integer(op, l)? ^ to_integer(op, r)?) as f64),
+        '<' => Ok(bool_to_number(l < r)),
+        '>' => Ok(bool_to_number(l > r)),
+        '&' => Ok(bool_to_number(l != 0.0 && r != 0.0)),
+        _ => Err(EvalError::UnknownOperator(op)),
+    }
+}
+
+/// Перетворює значення на ціле для цілочисельних операцій
+fn to_integer(op: char, value: f64) -> Result<i64, EvalError> {
+    if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+        Ok(value as i64)
+    } else {
+        Err(EvalError::NonIntegerOperand { op, value })
+    }
+}
+
+/// Значення змінних `let` у поточній області видимості, від зовнішньої до внутрішньої
+pub(crate) type Scope<T> = Vec<(String, T)>;
+
+/// Шукає найближче (найвнутрішніше) зв'язування змінної `name`
+pub(crate) fn lookup<'a, T>(scope: &'a Scope<T>, name: &str) -> Option<&'a T> {
+    scope
+        .iter()
+        .rev()
+        .find(|(bound, _)| bound == name)
+        .map(|(_, value)| value)
+}
+
+/// Оператори від найдорожчого в обчисленні, див. [`Expr::most_expensive_op`]
+const OP_COST: &[char] = &['^', '/', '*', '-', '+'];
+
+/// Літерал `f64` мовою Rust: `2.0f64`, `(-1.5f64)`, `f64::INFINITY`
+fn rust_literal(value: f64) -> String {
+    if value.is_nan() {
+        "f64::NAN".to_string()
+    } else if value.is_infinite() {
+        let sign = if value < 0.0 { "-" } else { "" };
+        format!("({}f64::INFINITY)", sign)
+    } else if value.is_sign_negative() {
+        format!("({:?}f64)", value)
+    } else {
+        format!("{:?}f64", value)
+    }
+}
+
+/// Наступне псевдовипадкове число генератора SplitMix64
+fn next_random(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Збирає непорожній список операндів `op` у дерево з випадковими дужками
+fn regroup(op: char, mut operands: Vec<Expr>, state: &mut u64) -> Expr {
+    if operands.len() == 1 {
+        return operands.remove(0);
+    }
+    let split = 1 + (next_random(state) % (operands.len() as u64 - 1)) as usize;
+    let right = operands.split_off(split);
+    Expr::BinaryOp {
+        op,
+        left: Box::new(regroup(op, operands, state)),
+        right: Box::new(regroup(op, right, state)),
+    }
+}
+
+/// Логічне значення як число: `1` — істина, `0` — хиба
+fn bool_to_number(b: bool) -> f64 {
+    if b { 1.0 } else { 0.0 }
+}
+
+/// Обчислює вираз в інтервальній арифметиці, де кожне число — точковий інтервал `[n, n]`
+///
+/// Повертає пару `(lo, hi)`. Див. [`evaluate_interval_with`].
+///
+/// # Приклад
+/// ```
+/// let expr = tree_parser::parse_expression("2 * 3 + 1").unwrap();
+/// assert_eq!(tree_parser::evaluate_interval(&expr), (7.0, 7.0));
+/// ```
+pub fn evaluate_interval(expr: &Expr) -> (f64, f64) {
+    evaluate_interval_with(expr, &|n| (n, n))
+}
+
+/// Обчислює вираз в інтервальній арифметиці, перетворюючи кожне число на інтервал
+///
+/// Функція `to_interval` задає інтервал `[lo, hi]` для кожного числового вузла.
+/// Ділення на інтервал, що містить нуль, дає необмежений інтервал
+/// `(-inf, inf)`, а не помилку. Так само консервативно необмеженими є степінь
+/// з основою, що може бути від'ємною, та XOR неточкових інтервалів.
+///
+/// # Паніка
+/// Панікує, якщо обчислення неможливе (див. [`try_evaluate_interval_with`]).
+///
+/// # Приклад
+/// ```
+/// let expr = tree_parser::parse_expression("2 + 3").unwrap();
+/// let bounds = tree_parser::evaluate_interval_with(&expr, &|n| (n - 0.5, n + 0.5));
+/// assert_eq!(bounds, (4.0, 6.0));
+/// ```
+pub fn evaluate_interval_with(expr: &Expr, to_interval: &dyn Fn(f64) -> (f64, f64)) -> (f64, f64) {
+    match try_evaluate_interval_with(expr, to_interval) {
+        Ok(bounds) => bounds,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+/// Як [`evaluate_interval_with`], але повертає помилку замість паніки
+///
+/// Невідомий оператор дає [`EvalError::UnknownOperator`], невідома функція —
+/// [`EvalError::UnknownFunction`], а незадана змінна —
+/// [`EvalError::UndefinedVariable`].
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{EvalError, Expr, try_evaluate_interval_with};
+///
+/// let expr = Expr::BinaryOp {
+///     op: '$',
+///     left: Box::new(Expr::Number(1.0)),
+///     right: Box::new(Expr::Number(2.0)),
+/// };
+/// let result = try_evaluate_interval_with(&expr, &|n| (n, n));
+/// assert_eq!(result, Err(EvalError::UnknownOperator('$')));
+/// ```
+pub fn try_evaluate_interval_with(
+    expr: &Expr,
+    to_interval: &dyn Fn(f64) -> (f64, f64),
+) -> Result<(f64, f64), EvalError> {
+    eval_tree(expr, &mut IntervalEval { to_interval }, &mut Vec::new())
+}
+
+/// Обчислювач в інтервальній арифметиці, див. [`evaluate_interval_with`]
+struct IntervalEval<'a> {
+    to_interval: &'a dyn Fn(f64) -> (f64, f64),
+}
+
+impl Evaluator for IntervalEval<'_> {
+    type Value = (f64, f64);
+
+    fn number(&mut self, value: f64) -> Result<(f64, f64), EvalError> {
+        Ok((self.to_interval)(value))
+    }
+
+    fn neg(&mut self, (a, b): (f64, f64)) -> Result<(f64, f64), EvalError> {
+        Ok((-b, -a))
+    }
+
+    fn binary(
+        &mut self,
+        op: char,
+        (a, b): (f64, f64),
+        (c, d): (f64, f64),
+    ) -> Result<(f64, f64), EvalError> {
+        Ok(match op {
+            '+' => (a + c, b + d),
+            '-' => (a - d, b - c),
+            '*' => interval_bounds(&[a * c, a * d, b * c, b * d]),
+            '/' => {
+                if c <= 0.0 && d >= 0.0 {
+                    (f64::NEG_INFINITY, f64::INFINITY)
+                } else {
+                    interval_bounds(&[a / c, a / d, b / c, b / d])
+                }
+            }
+            '^' if a >= 0.0 => interval_bounds(&[a.powf(c), a.powf(d), b.powf(c), b.powf(d)]),
+            '⊕' if a == b && c == d => {
+                let x = apply_binary_op('⊕', a, c)?;
+                (x, x)
+            }
+            '^' | '⊕' => (f64::NEG_INFINITY, f64::INFINITY),
+            '<' => interval_less((a, b), (c, d)),
+            '>' => interval_less((c, d), (a, b)),
+            '&' => {
+                let (l, r) = (interval_truth((a, b)), interval_truth((c, d)));
+                (l.0.min(r.0), l.1.min(r.1))
+            }
+            _ => return Err(EvalError::UnknownOperator(op)),
+        })
+    }
+
+    fn call(&mut self, name: &str, args: Vec<(f64, f64)>) -> Result<(f64, f64), EvalError> {
+        match (name, args.as_slice()) {
+            ("abs", [(lo, hi)]) => return Ok(interval_abs((*lo, *hi))),
+            ("sin", [(lo, hi)]) => return Ok(interval_sin((*lo, *hi))),
+            ("cos", [(lo, hi)]) => return Ok(interval_sin((lo + FRAC_PI_2, hi + FRAC_PI_2))),
+            ("tan", [(lo, hi)]) => return Ok(interval_tan((*lo, *hi))),
+            _ => {}
+        }
+        // Решта функцій неспадні за кожним аргументом, тож межі обчислюються окремо
+        let lo: Vec<f64> = args.iter().map(|b| b.0).collect();
+        let hi: Vec<f64> = args.iter().map(|b| b.1).collect();
+        Ok((apply_function(name, &lo)?, apply_function(name, &hi)?))
+    }
+}
+
+/// Інтервал `abs(x)`: мінімум у нулі, якщо інтервал його містить
+fn interval_abs((lo, hi): (f64, f64)) -> (f64, f64) {
+    if lo >= 0.0 {
+        (lo, hi)
+    } else if hi <= 0.0 {
+        (-hi, -lo)
+    } else {
+        (0.0, (-lo).max(hi))
+    }
+}
+
+/// Інтервал `sin(x)`: до меж додаються екстремуми ±1, що потрапили всередину
+fn interval_sin((lo, hi): (f64, f64)) -> (f64, f64) {
+    if hi - lo >= TAU || !(hi - lo).is_finite() {
+        return (-1.0, 1.0);
+    }
+    // Чи є всередині точка `offset + 2πk`
+    let contains = |offset: f64| ((lo - offset) / TAU).ceil() <= ((hi - offset) / TAU).floor();
+    let (a, b) = (lo.sin(), hi.sin());
+    (
+        if contains(-FRAC_PI_2) { -1.0 } else { a.min(b) },
+        if contains(FRAC_PI_2) { 1.0 } else { a.max(b) },
+    )
+}
+
+/// Інтервал `tan(x)`: зростає між полюсами `π/2 + πk`, а через полюс — необмежений
+fn interval_tan((lo, hi): (f64, f64)) -> (f64, f64) {
+    let pole_inside = ((lo - FRAC_PI_2) / PI).ceil() <= ((hi - FRAC_PI_2) / PI).floor();
+    if !(hi - lo).is_finite() || pole_inside {
+        (f64::NEG_INFINITY, f64::INFINITY)
+    } else {
+        (lo.tan(), hi.tan())
+    }
+}
+
+/// Інтервал результату `x < y`: `[1, 1]`, `[0, 0]` або невизначене `[0, 1]`
+fn interval_less((a, b): (f64, f64), (c, d): (f64, f64)) -> (f64, f64) {
+    if b < c {
+        (1.0, 1.0)
+    } else if a >= d {
+        (0.0, 0.0)
+    } else {
+        (0.0, 1.0)
+    }
+}
+
+/// Інтервал логічного значення інтервалу: чи може він бути (не)нульовим
+fn interval_truth((lo, hi): (f64, f64)) -> (f64, f64) {
+    if lo == 0.0 && hi == 0.0 {
+        (0.0, 0.0)
+    } else if lo > 0.0 || hi < 0.0 {
+        (1.0, 1.0)
+    } else {
+        (0.0, 1.0)
+    }
+}
+
+/// Найменше та найбільше значення серед кандидатів на межі інтервалу
+fn interval_bounds(candidates: &[f64]) -> (f64, f64) {
+    candidates
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &x| {
+            (lo.min(x), hi.max(x))
+        })
 }
@@ -1,7 +1,16 @@
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, anyhow, bail};
+use std::collections::HashMap;
 use std::env;
 use std::fs;
-use tree_parser::{evaluate, parse_expression};
+use std::io::{self, BufRead, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, SystemTime};
+use tree_parser::{
+    EvalError, Expr, ParseEvalError, ParserConfig, eval_csv, evaluate_with, format_number, grammar,
+    parse_and_eval, parse_and_eval_with, parse_expression_with, render_diagnostic, to_fraction,
+    try_evaluate,
+};
 
 fn print_help() {
     println!(
@@ -9,13 +18,43 @@ fn print_help() {
 
 Usage (via cargo):
   cargo run -- parse <file>   - Зчитати вираз із файлу та вивести AST
-  cargo run -- eval <file>    - Зчитати вираз із файлу та обчислити результат
+  cargo run -- eval <file> [name=value ...] [--strict-nan] [--fraction] [--scientific[=N]]
+                              - Зчитати вираз із файлу та обчислити результат
+                                (name=value: значення змінної, наприклад x=2;
+                                --strict-nan: помилка, якщо результат NaN чи нескінченність;
+                                --fraction: показати результат також звичайним дробом;
+                                --scientific: показати результат у науковому записі,
+                                наприклад 6.022e23, з N значущими цифрами)
+  cargo run -- check <file>   - Перевірити вираз у файлі без обчислення
+  cargo run -- evalcsv <file> <column>
+                              - Обчислити вирази зі стовпця CSV
+  cargo run -- diff <file1> <file2>
+                              - Порівняти значення виразів із двох файлів
+  cargo run -- formats <file> - Вивести вираз в інфіксному, префіксному,
+                                постфіксному записі та як S-вираз
+  cargo run -- filter         - Обчислювати вирази зі stdin, по одному на рядок
+                                (результат або `ERR: ...` одразу після кожного рядка)
+  cargo run -- dot <file> [--svg <out.svg>]
+                              - Вивести дерево виразу мовою DOT (Graphviz)
+                                (--svg: зберегти SVG, якщо `dot` є в PATH)
+  cargo run -- watch <file>   - Обчислювати вираз із файлу після кожної його зміни
+                                (помилки виводяться, спостереження триває)
+  cargo run -- grammar        - Вивести граматику виразів в EBNF
   cargo run -- help           - Показати довідку
   cargo run -- about          - Інформація про автора і проєкт
 
 Usage (via Makefile):
   make parse <file>           - Зчитати вираз із файлу та вивести AST
   make eval <file>            - Зчитати вираз із файлу та обчислити результат
+  make check <file>           - Перевірити вираз у файлі без обчислення
+  make evalcsv <file> <column>
+                              - Обчислити вирази зі стовпця CSV
+  make diff <file1> <file2>   - Порівняти значення виразів із двох файлів
+  make formats <file>         - Вивести вираз у різних записах
+  make filter                 - Обчислювати вирази зі stdin, по одному на рядок
+  make dot <file>             - Вивести дерево виразу мовою DOT
+  make watch <file>           - Обчислювати вираз із файлу після кожної зміни
+  make grammar                - Вивести граматику виразів в EBNF
   make help                   - Показати довідку
   make about                  - Інформація про автора і проєкт
 "#
@@ -27,6 +66,176 @@ fn print_about() {
     println!("Created by Yehor Danylov, 2025");
 }
 
+/// Допустима різниця значень у команді `diff`
+const DIFF_EPSILON: f64 = 1e-9;
+
+/// Форматує число в науковому записі (`6.022e23`)
+///
+/// З `digits` мантиса має рівно стільки значущих цифр, без нього — найменше,
+/// достатнє для точного значення. Нуль, нескінченності й NaN виводяться як у
+/// [`format_number`].
+fn format_scientific(value: f64, digits: Option<usize>) -> String {
+    if value == 0.0 || !value.is_finite() {
+        return format_number(value);
+    }
+    match digits {
+        Some(digits) => format!("{:.*e}", digits.saturating_sub(1), value),
+        None => format!("{:e}", value),
+    }
+}
+
+/// Розбирає прапорець `--scientific[=N]`: `None`, якщо його немає,
+/// `Some(None)` без кількості цифр
+fn parse_scientific(flags: &[&String]) -> Result<Option<Option<usize>>> {
+    let Some(flag) = flags.iter().find(|arg| arg.starts_with("--scientific")) else {
+        return Ok(None);
+    };
+    match flag.strip_prefix("--scientific") {
+        Some("") => Ok(Some(None)),
+        Some(rest) => {
+            let digits = rest
+                .strip_prefix('=')
+                .and_then(|digits| digits.parse::<usize>().ok())
+                .filter(|digits| *digits > 0)
+                .with_context(|| format!("Invalid significant digits in '{}'", flag))?;
+            Ok(Some(Some(digits)))
+        }
+        None => Ok(None),
+    }
+}
+
+fn read_file(filename: &str) -> Result<String> {
+    fs::read_to_string(filename).with_context(|| format!("Cannot read file '{}'", filename))
+}
+
+/// Зчитує файл із виразом, відрізняючи порожній файл від помилки парсингу
+fn read_expression(filename: &str) -> Result<String> {
+    let content = read_file(filename)?;
+    if content.trim().is_empty() {
+        bail!("File '{}' contains no expression", filename);
+    }
+    Ok(content)
+}
+
+fn parse_file(filename: &str) -> Result<Expr> {
+    let content = read_expression(filename)?;
+    let config = ParserConfig {
+        token_positions: true,
+        ..ParserConfig::default()
+    };
+    parse_expression_with(&content, &config)
+        .map_err(|err| anyhow!("{}", render_diagnostic(&content, &err).trim_end()))
+        .with_context(|| format!("Invalid expression in file '{}'", filename))
+}
+
+/// Розбирає аргумент виду `x=2` у назву змінної та її значення
+fn parse_assignment(arg: &str) -> Result<(String, f64)> {
+    let (name, value) = arg.split_once('=').context("Expected name=value")?;
+    let name = name.trim();
+    if !name.starts_with(|c: char| c.is_alphabetic()) {
+        bail!("Invalid variable name in '{}'", arg);
+    }
+    let value = value
+        .trim()
+        .parse()
+        .with_context(|| format!("Invalid value in '{}'", arg))?;
+    Ok((name.to_string(), value))
+}
+
+/// Рендерить граф DOT у файл SVG програмою `dot` з Graphviz
+///
+/// Повертає `false`, якщо `dot` не знайдено в `PATH`.
+fn render_svg(dot: &str, output: &str) -> Result<bool> {
+    let mut child = match Command::new("dot")
+        .args(["-Tsvg", "-o", output])
+        .stdin(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(err) => return Err(err).context("Cannot run 'dot'"),
+    };
+    child
+        .stdin
+        .take()
+        .context("Cannot write to 'dot'")?
+        .write_all(dot.as_bytes())
+        .context("Cannot write to 'dot'")?;
+    let status = child.wait().context("Cannot run 'dot'")?;
+    if !status.success() {
+        bail!("'dot' failed with {}", status);
+    }
+    Ok(true)
+}
+
+/// Обчислює кожен рядок `input` і одразу записує результат у `output`
+///
+/// На кожен рядок вводу припадає рівно один рядок виводу: значення або
+/// `ERR: <повідомлення>`. Після кожного рядка вивід скидається, щоб
+/// батьківський процес отримував результати без затримки.
+fn filter_lines<R: BufRead, W: Write>(input: R, output: &mut W) -> io::Result<()> {
+    for line in input.lines() {
+        match parse_and_eval(&line?) {
+            Ok(value) => writeln!(output, "{}", format_number(value))?,
+            Err(err) => writeln!(output, "ERR: {}", err)?,
+        }
+        output.flush()?;
+    }
+    Ok(())
+}
+
+/// Як часто команда `watch` перевіряє час зміни файлу
+const WATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Стежить за файлом виразу, опитуючи час його зміни
+struct Watcher {
+    path: String,
+    polled: bool,
+    modified: Option<SystemTime>,
+    content: Option<String>,
+}
+
+impl Watcher {
+    fn new(path: &str) -> Self {
+        Watcher {
+            path: path.to_string(),
+            polled: false,
+            modified: None,
+            content: None,
+        }
+    }
+
+    /// Перевіряє файл і повертає рядок для виводу, якщо вміст змінився
+    ///
+    /// Новий вміст обчислюється одразу: результат має вигляд `Result: ...`,
+    /// а помилка читання, парсингу чи обчислення — `Error: ...`. Для
+    /// незміненого файлу (зокрема зі зміненим лише часом) повертає `None`.
+    fn poll(&mut self) -> Option<String> {
+        let modified = fs::metadata(&self.path)
+            .and_then(|meta| meta.modified())
+            .ok();
+        if self.polled && modified == self.modified {
+            return None;
+        }
+        self.polled = true;
+        self.modified = modified;
+        let content = read_expression(&self.path);
+        let changed = content.as_ref().ok().cloned();
+        if changed.is_some() && changed == self.content {
+            return None;
+        }
+        self.content = changed;
+        let result = content.and_then(|content| {
+            parse_and_eval(&content)
+                .with_context(|| format!("Invalid expression in file '{}'", self.path))
+        });
+        Some(match result {
+            Ok(value) => format!("Result: {}", format_number(value)),
+            Err(err) => format!("Error: {:#}", err),
+        })
+    }
+}
+
 fn main() -> Result<()> {
     let args: Vec<String> = env::args().collect();
 
@@ -38,23 +247,162 @@ fn main() -> Result<()> {
     match args[1].as_str() {
         "parse" => {
             let filename = args.get(2).context("Error: Missing filename")?;
-            let content = fs::read_to_string(filename)
-                .with_context(|| format!("Cannot read file '{}'", filename))?;
-            let expr = parse_expression(&content)
-                .with_context(|| format!("Invalid expression in file '{}'", filename))?;
-            expr.print_tree();
+            let expr = parse_file(filename)?;
+            // Закритий канал (наприклад, `| head`) — не помилка
+            if let Err(err) = expr.print_tree()
+                && err.kind() != io::ErrorKind::BrokenPipe
+            {
+                return Err(err).context("Cannot write the tree to stdout");
+            }
         }
 
         "eval" => {
             let filename = args.get(2).context("Error: Missing filename")?;
-            let content = fs::read_to_string(filename)
-                .with_context(|| format!("Cannot read file '{}'", filename))?;
-            let expr = parse_expression(&content)
-                .with_context(|| format!("Invalid expression in file '{}'", filename))?;
-            let result = evaluate(&expr);
-            println!("Result: {}", result);
+            let (assignments, flags): (Vec<&String>, Vec<&String>) = args[3..]
+                .iter()
+                .partition(|arg| arg.contains('=') && !arg.starts_with("--"));
+            let vars = assignments
+                .into_iter()
+                .map(|arg| parse_assignment(arg))
+                .collect::<Result<HashMap<_, _>>>()?;
+            let scientific = parse_scientific(&flags)?;
+
+            let result = if vars.is_empty() {
+                // Без змінних дерево не потрібне, тож вираз обчислюється під час парсингу
+                let content = read_expression(filename)?;
+                let config = ParserConfig {
+                    token_positions: true,
+                    ..ParserConfig::default()
+                };
+                match parse_and_eval_with(&content, &config) {
+                    Ok(value) => value,
+                    Err(ParseEvalError::Eval(EvalError::UndefinedVariable(name))) => {
+                        bail!("Undefined variables: {} (pass them as name=value)", name)
+                    }
+                    Err(ParseEvalError::Eval(err)) => {
+                        return Err(err).with_context(|| {
+                            format!("Cannot evaluate expression in file '{}'", filename)
+                        });
+                    }
+                    Err(ParseEvalError::Parse(err)) => {
+                        let diagnostic =
+                            anyhow!("{}", render_diagnostic(&content, &err).trim_end());
+                        return Err(diagnostic
+                            .context(format!("Invalid expression in file '{}'", filename)));
+                    }
+                }
+            } else {
+                let expr = parse_file(filename)?;
+                let missing: Vec<String> = expr
+                    .free_variables()
+                    .into_iter()
+                    .filter(|name| !vars.contains_key(name))
+                    .collect();
+                if !missing.is_empty() {
+                    bail!(
+                        "Undefined variables: {} (pass them as name=value)",
+                        missing.join(", ")
+                    );
+                }
+                evaluate_with(&expr, &vars)
+                    .with_context(|| format!("Cannot evaluate expression in file '{}'", filename))?
+            };
+            if flags.iter().any(|arg| *arg == "--strict-nan") && !result.is_finite() {
+                bail!("Result is not a finite number: {}", format_number(result));
+            }
+            let formatted = match scientific {
+                Some(digits) => format_scientific(result, digits),
+                None => format_number(result),
+            };
+            match to_fraction(result) {
+                Some((numerator, denominator))
+                    if denominator != 1 && flags.iter().any(|arg| *arg == "--fraction") =>
+                {
+                    println!("Result: {} ({}/{})", formatted, numerator, denominator);
+                }
+                _ => println!("Result: {}", formatted),
+            }
+        }
+
+        "check" => {
+            let filename = args.get(2).context("Error: Missing filename")?;
+            parse_file(filename)?;
+            println!("OK");
+        }
+
+        "evalcsv" => {
+            let filename = args.get(2).context("Error: Missing filename")?;
+            let column = args.get(3).context("Error: Missing column name")?;
+            let content = read_file(filename)?;
+            let output = eval_csv(&content, column)
+                .with_context(|| format!("Invalid CSV file '{}'", filename))?;
+            print!("{}", output);
+        }
+
+        "diff" => {
+            let first = args.get(2).context("Error: Missing first filename")?;
+            let second = args.get(3).context("Error: Missing second filename")?;
+            let value = |filename: &str| -> Result<f64> {
+                try_evaluate(&parse_file(filename)?)
+                    .with_context(|| format!("Cannot evaluate expression in file '{}'", filename))
+            };
+            let (a, b) = (value(first)?, value(second)?);
+            if (a - b).abs() <= DIFF_EPSILON {
+                println!("Match: {} == {}", format_number(a), format_number(b));
+            } else {
+                println!("Mismatch: {} != {}", format_number(a), format_number(b));
+                bail!("Values of '{}' and '{}' differ", first, second);
+            }
+        }
+
+        "formats" => {
+            let filename = args.get(2).context("Error: Missing filename")?;
+            let expr = parse_file(filename)?;
+            println!("Infix:   {}", expr.to_infix_min());
+            println!("Prefix:  {}", expr.to_prefix());
+            println!("Postfix: {}", expr.to_postfix());
+            println!("S-expr:  {}", expr.to_sexpr());
+        }
+
+        "dot" => {
+            let filename = args.get(2).context("Error: Missing filename")?;
+            let dot = parse_file(filename)?.to_dot();
+            match args.iter().position(|arg| arg == "--svg") {
+                Some(index) => {
+                    let output = args.get(index + 1).context("Error: Missing SVG filename")?;
+                    if !render_svg(&dot, output)? {
+                        eprintln!("Graphviz 'dot' not found in PATH, printing DOT instead");
+                        print!("{}", dot);
+                    }
+                }
+                None => print!("{}", dot),
+            }
+        }
+
+        "filter" => {
+            // Закритий канал означає, що батьківський процес завершився
+            if let Err(err) = filter_lines(io::stdin().lock(), &mut io::stdout().lock())
+                && err.kind() != io::ErrorKind::BrokenPipe
+            {
+                return Err(err).context("Cannot process stdin");
+            }
         }
 
+        "watch" => {
+            let filename = args.get(2).context("Error: Missing filename")?;
+            let mut watcher = Watcher::new(filename);
+            let mut stdout = io::stdout();
+            loop {
+                if let Some(line) = watcher.poll() {
+                    writeln!(stdout, "{}", line).context("Cannot write to stdout")?;
+                    stdout.flush().context("Cannot write to stdout")?;
+                }
+                thread::sleep(WATCH_INTERVAL);
+            }
+        }
+
+        "grammar" => print!("{}", grammar()),
+
         "help" => print_help(),
 
         "about" => print_about(),
@@ -67,3 +415,56 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_filter_lines_evaluates_each_line() {
+        let mut output = Vec::new();
+        filter_lines(
+            Cursor::new("2 + 3\n2 +\n\nlet x = 4 in x * x\n"),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "5\nERR: Missing operand after '+' at position 3\nERR: Unexpected end of input\n16\n"
+        );
+    }
+
+    #[test]
+    fn test_watcher_poll_reports_changes() {
+        let path = env::temp_dir().join(format!("tree_parser_{}_poll.txt", std::process::id()));
+        let touch = |content: &str, seconds: u64| {
+            fs::write(&path, content).unwrap();
+            let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(seconds);
+            File::options()
+                .write(true)
+                .open(&path)
+                .and_then(|file| file.set_modified(modified))
+                .unwrap();
+        };
+        let mut watcher = Watcher::new(path.to_str().unwrap());
+
+        touch("2 + 3", 1_000);
+        assert_eq!(watcher.poll().as_deref(), Some("Result: 5"));
+        assert_eq!(watcher.poll(), None);
+
+        touch("2 +", 2_000);
+        let error = watcher.poll().unwrap();
+        assert!(error.starts_with("Error: "), "{}", error);
+        assert!(error.contains("Missing operand"), "{}", error);
+
+        touch("let x = 4 in x * x", 3_000);
+        assert_eq!(watcher.poll().as_deref(), Some("Result: 16"));
+        // Лише новий час зміни без нового вмісту нічого не виводить
+        touch("let x = 4 in x * x", 4_000);
+        assert_eq!(watcher.poll(), None);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,149 @@
+//! Модульна арифметика
+
+use crate::evaluator::{Evaluator, eval_tree};
+use crate::{EvalError, Expr, apply_binary_op, apply_function, to_integer};
+
+/// Значення вузла: лишок за модулем і звичайне значення
+///
+/// Обидва обчислюються для кожного вузла, а помилка стає результатом, лише
+/// якщо значення справді потрібне: звичайне значення показника `2 ^ (3 / 2 * 2)`
+/// є, хоча лишку `3 / 2` за модулем 4 немає.
+#[derive(Debug, Clone)]
+struct ModValue {
+    residue: Result<i64, EvalError>,
+    plain: Result<f64, EvalError>,
+}
+
+/// Обчислює вираз у цілих числах за модулем `modulus`
+///
+/// `+ - *` і унарний мінус зводяться за модулем після кожної операції, а
+/// результат завжди лежить у `[0, modulus)`. `/` множить на обернений за
+/// модулем елемент дільника, а якщо його немає, повертає
+/// [`EvalError::NoModularInverse`]. Показник `^` обчислюється звичайно й має
+/// бути цілим; від'ємний показник бере обернений елемент основи. Решта
+/// операторів і функції обчислюються у `f64`, і їхній цілий результат
+/// зводиться за модулем.
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{evaluate_mod, parse_expression};
+///
+/// let expr = parse_expression("2 ^ 10").unwrap();
+/// assert_eq!(evaluate_mod(&expr, 1000), Ok(24));
+/// assert_eq!(evaluate_mod(&parse_expression("3 / 2").unwrap(), 7), Ok(5));
+/// ```
+pub fn evaluate_mod(expr: &Expr, modulus: i64) -> Result<i64, EvalError> {
+    if modulus <= 0 {
+        return Err(EvalError::InvalidModulus(modulus));
+    }
+    eval_tree(expr, &mut ModEval { modulus }, &mut Vec::new())?.residue
+}
+
+struct ModEval {
+    modulus: i64,
+}
+
+impl ModEval {
+    /// Значення, лишок якого — звичайне значення, зведене за модулем
+    fn reduced(&self, plain: Result<f64, EvalError>) -> ModValue {
+        ModValue {
+            residue: plain.clone().and_then(|value| reduce(value, self.modulus)),
+            plain,
+        }
+    }
+}
+
+impl Evaluator for ModEval {
+    type Value = ModValue;
+
+    fn number(&mut self, value: f64) -> Result<ModValue, EvalError> {
+        Ok(self.reduced(Ok(value)))
+    }
+
+    fn neg(&mut self, operand: ModValue) -> Result<ModValue, EvalError> {
+        let modulus = self.modulus;
+        Ok(ModValue {
+            residue: operand.residue.map(|r| (modulus - r) % modulus),
+            plain: operand.plain.map(|value| -value),
+        })
+    }
+
+    fn binary(&mut self, op: char, l: ModValue, r: ModValue) -> Result<ModValue, EvalError> {
+        let modulus = self.modulus;
+        let plain = l
+            .plain
+            .clone()
+            .and_then(|lp| r.plain.clone().and_then(|rp| apply_binary_op(op, lp, rp)));
+        let residue = match op {
+            '+' | '-' | '*' | '/' => (|| {
+                let (l, r, m) = (l.residue? as i128, r.residue? as i128, modulus as i128);
+                let result = match op {
+                    '+' => (l + r) % m,
+                    '-' => (l - r).rem_euclid(m),
+                    '*' => l * r % m,
+                    _ => l * inverse(r as i64, modulus)? as i128 % m,
+                };
+                Ok(result as i64)
+            })(),
+            '^' => (|| {
+                let base = l.residue?;
+                let exponent = to_integer('^', r.plain?)?;
+                let base = if exponent < 0 {
+                    inverse(base, modulus)?
+                } else {
+                    base
+                };
+                Ok(pow_mod(base, exponent.unsigned_abs(), modulus))
+            })(),
+            _ => return Ok(self.reduced(plain)),
+        };
+        Ok(ModValue { residue, plain })
+    }
+
+    fn call(&mut self, name: &str, args: Vec<ModValue>) -> Result<ModValue, EvalError> {
+        let plain = args
+            .into_iter()
+            .map(|arg| arg.plain)
+            .collect::<Result<Vec<_>, _>>()
+            .and_then(|values| apply_function(name, &values));
+        Ok(self.reduced(plain))
+    }
+}
+
+/// Зводить ціле значення до `[0, modulus)`; остача `f64` обчислюється точно
+fn reduce(value: f64, modulus: i64) -> Result<i64, EvalError> {
+    if value.is_finite() && value.fract() == 0.0 {
+        Ok(value.rem_euclid(modulus as f64) as i64)
+    } else {
+        Err(EvalError::NonIntegerValue(value))
+    }
+}
+
+/// Обернений за модулем елемент розширеним алгоритмом Евкліда
+fn inverse(value: i64, modulus: i64) -> Result<i64, EvalError> {
+    let (mut old_r, mut r) = (value as i128, modulus as i128);
+    let (mut old_s, mut s) = (1i128, 0i128);
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_s, s) = (s, old_s - q * s);
+    }
+    if old_r != 1 {
+        return Err(EvalError::NoModularInverse { value, modulus });
+    }
+    Ok(old_s.rem_euclid(modulus as i128) as i64)
+}
+
+/// Піднесення до степеня повторним квадратуванням
+fn pow_mod(base: i64, mut exponent: u64, modulus: i64) -> i64 {
+    let m = modulus as i128;
+    let (mut base, mut result) = (base as i128, 1 % m);
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result * base % m;
+        }
+        base = base * base % m;
+        exponent >>= 1;
+    }
+    result as i64
+}
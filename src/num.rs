@@ -0,0 +1,102 @@
+//! Числові типи, над якими працює [`crate::evaluate`]
+
+/// Тип чисел для узагальненого [`crate::evaluate`]
+///
+/// `+ - * /` та унарний мінус виконуються в самому типі, тож точність
+/// результату залежить від нього. Решта операторів і функцій обчислюється
+/// через `f64` і перетворюється назад через [`Num::from_f64`].
+///
+/// # Приклад
+/// ```
+/// let expr = tree_parser::parse_expression("0.1 + 0.2").unwrap();
+/// assert_eq!(tree_parser::evaluate::<f32>(&expr), 0.3);
+/// assert_ne!(tree_parser::evaluate::<f64>(&expr), 0.3);
+/// ```
+pub trait Num: Copy {
+    /// Перетворює літерал чи результат `f64`-обчислення на цей тип
+    fn from_f64(value: f64) -> Self;
+    /// Перетворює значення на `f64` для операторів без власної реалізації
+    fn to_f64(self) -> f64;
+    /// Сума
+    fn add(self, other: Self) -> Self;
+    /// Різниця
+    fn sub(self, other: Self) -> Self;
+    /// Добуток
+    fn mul(self, other: Self) -> Self;
+    /// Частка
+    fn div(self, other: Self) -> Self;
+    /// Протилежне значення
+    fn neg(self) -> Self {
+        Self::from_f64(0.0).sub(self)
+    }
+}
+
+macro_rules! impl_num_float {
+    ($($t:ty),*) => {$(
+        impl Num for $t {
+            fn from_f64(value: f64) -> Self {
+                value as $t
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn add(self, other: Self) -> Self {
+                self + other
+            }
+
+            fn sub(self, other: Self) -> Self {
+                self - other
+            }
+
+            fn mul(self, other: Self) -> Self {
+                self * other
+            }
+
+            fn div(self, other: Self) -> Self {
+                self / other
+            }
+
+            fn neg(self) -> Self {
+                -self
+            }
+        }
+    )*};
+}
+
+impl_num_float!(f32, f64);
+
+/// Десяткові числа; переповнення й ділення на нуль панікують, тож для
+/// помилок замість паніки є [`crate::evaluate_decimal`]
+#[cfg(feature = "decimal")]
+impl Num for rust_decimal::Decimal {
+    fn from_f64(value: f64) -> Self {
+        <Self as rust_decimal::prelude::FromPrimitive>::from_f64(value)
+            .expect("number is out of Decimal range")
+    }
+
+    fn to_f64(self) -> f64 {
+        rust_decimal::prelude::ToPrimitive::to_f64(&self).unwrap_or(f64::NAN)
+    }
+
+    fn add(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn sub(self, other: Self) -> Self {
+        self - other
+    }
+
+    fn mul(self, other: Self) -> Self {
+        self * other
+    }
+
+    fn div(self, other: Self) -> Self {
+        self / other
+    }
+
+    fn neg(self) -> Self {
+        -self
+    }
+}
@@ -0,0 +1,57 @@
+//! Оператори Rust для побудови [`Expr`] у коді
+//!
+//! ```
+//! use tree_parser::{Expr, parse_expression};
+//!
+//! let expr = Expr::Number(2.0) + Expr::Number(3.0) * Expr::Number(4.0);
+//! assert_eq!(expr, parse_expression("2 + 3 * 4").unwrap());
+//! ```
+
+use crate::Expr;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// Реалізує бінарний оператор для `Expr` і `&Expr`, що будує вузол `BinaryOp`
+macro_rules! impl_binary_op {
+    ($trait:ident, $method:ident, $op:literal) => {
+        impl $trait for Expr {
+            type Output = Expr;
+
+            fn $method(self, rhs: Expr) -> Expr {
+                Expr::BinaryOp {
+                    op: $op,
+                    left: Box::new(self),
+                    right: Box::new(rhs),
+                }
+            }
+        }
+
+        impl $trait for &Expr {
+            type Output = Expr;
+
+            fn $method(self, rhs: &Expr) -> Expr {
+                self.clone().$method(rhs.clone())
+            }
+        }
+    };
+}
+
+impl_binary_op!(Add, add, '+');
+impl_binary_op!(Sub, sub, '-');
+impl_binary_op!(Mul, mul, '*');
+impl_binary_op!(Div, div, '/');
+
+impl Neg for Expr {
+    type Output = Expr;
+
+    fn neg(self) -> Expr {
+        Expr::Neg(Box::new(self))
+    }
+}
+
+impl Neg for &Expr {
+    type Output = Expr;
+
+    fn neg(self) -> Expr {
+        -self.clone()
+    }
+}
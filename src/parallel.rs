@@ -0,0 +1,104 @@
+//! Паралельне обчислення незалежних піддерев (feature `parallel`)
+
+use crate::{EvalError, Expr, Scope, apply_binary_op, apply_function, evaluate_num};
+use std::num::NonZeroUsize;
+use std::thread;
+
+/// Найменша кількість вузлів піддерева, для якої варто запускати потік
+const PARALLEL_THRESHOLD: usize = 1024;
+
+/// Обчислює вираз, рахуючи великі праве й ліве піддерева в різних потоках
+///
+/// Операнди бінарної операції обчислюються паралельно, якщо піддерево має
+/// щонайменше 1024 вузли; менші піддерева й глибші рівні, коли потоків уже
+/// вистачає на всі ядра, обчислюються послідовно. Результат збігається з
+/// [`crate::evaluate`].
+///
+/// # Паніка
+/// Панікує, якщо обчислення неможливе (див. [`crate::try_evaluate`]).
+///
+/// # Приклад
+/// ```
+/// let expr = tree_parser::parse_expression("2 + 3 * 4").unwrap();
+/// assert_eq!(tree_parser::evaluate_parallel(&expr), 14.0);
+/// ```
+pub fn evaluate_parallel(expr: &Expr) -> f64 {
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    // Кожен рівень розгалуження подвоює кількість потоків
+    let splits = threads.next_power_of_two().trailing_zeros();
+    let mut sizes = Vec::new();
+    subtree_sizes(expr, &mut sizes);
+    match parallel_in(expr, &sizes, &mut Vec::new(), splits) {
+        Ok(value) => value,
+        Err(err) => panic!("{}", err),
+    }
+}
+
+/// Записує в `sizes` кількість вузлів кожного піддерева у прямому порядку
+///
+/// Розміри піддерев дітей ідуть одразу за розміром батька, тож дитина
+/// займає рівно стільки елементів, скільки має вузлів.
+fn subtree_sizes(expr: &Expr, sizes: &mut Vec<usize>) -> usize {
+    let at = sizes.len();
+    sizes.push(0);
+    let size = 1 + expr
+        .children()
+        .into_iter()
+        .map(|child| subtree_sizes(child, sizes))
+        .sum::<usize>();
+    sizes[at] = size;
+    size
+}
+
+/// Обчислює `expr`, якому відповідають розміри піддерев `sizes`; кожен
+/// рівень дерева зменшує `splits`, тож потоки запускаються лише на верхніх
+/// рівнях
+fn parallel_in(
+    expr: &Expr,
+    sizes: &[usize],
+    scope: &mut Scope<f64>,
+    splits: u32,
+) -> Result<f64, EvalError> {
+    if splits == 0 || sizes[0] < PARALLEL_THRESHOLD {
+        return evaluate_num(expr, scope);
+    }
+    let mut rest = &sizes[1..];
+    let mut child = || {
+        let (child, tail) = rest.split_at(rest[0]);
+        rest = tail;
+        child
+    };
+    let splits = splits - 1;
+    match expr {
+        Expr::BinaryOp { op, left, right } => {
+            let (left_sizes, right_sizes) = (child(), child());
+            let (l, r) = thread::scope(|s| {
+                let mut right_scope = scope.clone();
+                let right =
+                    s.spawn(move || parallel_in(right, right_sizes, &mut right_scope, splits));
+                let l = parallel_in(left, left_sizes, scope, splits);
+                match right.join() {
+                    Ok(r) => (l, r),
+                    Err(payload) => std::panic::resume_unwind(payload),
+                }
+            });
+            apply_binary_op(*op, l?, r?)
+        }
+        Expr::Let { name, value, body } => {
+            let value = parallel_in(value, child(), scope, splits)?;
+            scope.push((name.clone(), value));
+            let result = parallel_in(body, child(), scope, splits);
+            scope.pop();
+            result
+        }
+        Expr::Neg(operand) => Ok(-parallel_in(operand, child(), scope, splits)?),
+        Expr::Func { name, args } => {
+            let values = args
+                .iter()
+                .map(|arg| parallel_in(arg, child(), scope, splits))
+                .collect::<Result<Vec<_>, _>>()?;
+            apply_function(name, &values)
+        }
+        leaf => evaluate_num(leaf, scope),
+    }
+}
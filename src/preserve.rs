@@ -0,0 +1,54 @@
+//! Токенізація зі збереженням пробілів для форматерів
+
+use crate::{ParserConfig, tokenize};
+
+/// Токен вихідного тексту, див. [`tokenize_preserving`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SourceToken {
+    /// Пробільні символи між токенами саме в тому вигляді, як у тексті
+    Whitespace(String),
+    /// Число, назва, оператор чи дужка з оригінальним текстом (`**` лишається `**`)
+    Text(String),
+}
+
+impl SourceToken {
+    /// Текст токена
+    pub fn as_str(&self) -> &str {
+        match self {
+            SourceToken::Whitespace(text) | SourceToken::Text(text) => text,
+        }
+    }
+}
+
+/// Розбиває вираз на токени, зберігаючи пробіли між ними окремими токенами
+///
+/// Поділ на токени той самий, що й у парсера, тож після з'єднання текстів
+/// усіх токенів виходить рівно `input`, зокрема для хибного виразу.
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{SourceToken, tokenize_preserving};
+///
+/// let tokens = tokenize_preserving("2 *  x");
+/// assert_eq!(tokens[3], SourceToken::Whitespace("  ".to_string()));
+/// assert_eq!(tokens.iter().map(SourceToken::as_str).collect::<String>(), "2 *  x");
+/// ```
+pub fn tokenize_preserving(input: &str) -> Vec<SourceToken> {
+    // Без суворого режиму токенізація не повертає помилок
+    let tokens = tokenize(input, &ParserConfig::default()).unwrap_or_default();
+    let mut out = Vec::new();
+    let mut position = 0;
+    for token in tokens {
+        if token.span.start > position {
+            let gap = &input[position..token.span.start];
+            out.push(SourceToken::Whitespace(gap.to_string()));
+        }
+        let text = &input[token.span.start..token.span.end];
+        out.push(SourceToken::Text(text.to_string()));
+        position = token.span.end;
+    }
+    if position < input.len() {
+        out.push(SourceToken::Whitespace(input[position..].to_string()));
+    }
+    out
+}
@@ -0,0 +1,89 @@
+//! Парсинг із відновленням після помилок для редакторів
+
+use crate::{
+    AstBuilder, Expr, ParseError, ParserConfig, Span, Token, in_chars, parse_tokens, tokenize,
+};
+
+/// Парсить вираз, пропускаючи хибні токени, і повертає всі знайдені помилки
+///
+/// Після кожної помилки хибний токен відкидається (неочікуваний токен,
+/// оператор без операнда, зайвий токен після виразу), а бракуючу `)`
+/// дописує сам парсер, і розбір починається знову. Вираз повертається,
+/// якщо після виправлень його вдалося розібрати; помилки йдуть у порядку
+/// виявлення. Для правильного виразу результат той самий, що й у
+/// [`crate::parse_expression`], без помилок.
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{parse_expression, parse_recover};
+///
+/// let (expr, errors) = parse_recover("2 + + 3");
+/// assert_eq!(expr, Some(parse_expression("2 + 3").unwrap()));
+/// assert_eq!(errors.len(), 1);
+/// ```
+pub fn parse_recover(input: &str) -> (Option<Expr>, Vec<ParseError>) {
+    let config = ParserConfig {
+        strict: true,
+        token_positions: true,
+        ..ParserConfig::default()
+    };
+    // Токени беруться без суворого режиму, щоб `2 3` не зупиняло токенізацію
+    let mut tokens = match tokenize(input, &ParserConfig::default()) {
+        Ok(tokens) => tokens,
+        Err(err) => return (None, vec![err]),
+    };
+    let mut errors = Vec::new();
+
+    // Кожне виправлення прибирає токен або закриває дужку, тож спроб скінченна кількість
+    for _ in 0..=2 * tokens.len() {
+        let mut rest = tokens.clone();
+        let err = match parse_tokens(&mut rest, &config, &mut AstBuilder) {
+            Ok(expr) => return (Some(expr), errors),
+            Err(err) => err,
+        };
+        let fixed = match &err {
+            // Позиції дописаної `)` і токена після неї збігаються, тож
+            // токен шукається ще й за текстом
+            ParseError::UnexpectedTokenAt { token, position }
+            | ParseError::MissingOperator { token, position } => remove_where(&mut tokens, |t| {
+                t.span.start == *position && t.text == *token
+            }),
+            ParseError::MissingOperand { op, position } => {
+                remove_where(&mut tokens, |t| t.span.end == *position && t.text == *op)
+            }
+            ParseError::NumberOutOfRange(text) => remove_where(&mut tokens, |t| t.text == *text),
+            // Дужка закривається там, де парсер на неї чекав
+            ParseError::MissingClosingParenthesis => {
+                let index = tokens.len() - rest.len();
+                let at = rest.first().map_or(input.len(), |t| t.span.start);
+                let span = Span { start: at, end: at };
+                let text = ")".to_string();
+                tokens.insert(index, Token { text, span });
+                true
+            }
+            ParseError::UnexpectedEnd => tokens.pop().is_some(),
+            _ => false,
+        };
+        // Відкидання останніх токенів дає ту саму помилку знову
+        let repeated = matches!(err, ParseError::UnexpectedEnd)
+            && matches!(errors.last(), Some(ParseError::UnexpectedEnd));
+        if !repeated {
+            errors.push(in_chars(err, input));
+        }
+        if !fixed {
+            break;
+        }
+    }
+    (None, errors)
+}
+
+/// Прибирає перший токен, що задовольняє `pred`; `false`, якщо такого немає
+fn remove_where(tokens: &mut Vec<Token>, pred: impl Fn(&Token) -> bool) -> bool {
+    match tokens.iter().position(pred) {
+        Some(index) => {
+            tokens.remove(index);
+            true
+        }
+        None => false,
+    }
+}
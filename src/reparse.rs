@@ -0,0 +1,121 @@
+//! Інкрементальний повторний розбір для редакторів
+
+use crate::{
+    AstBuilder, Expr, ParseError, ParserConfig, Token, in_chars, parse_tokens, tokenize,
+    tokenize_chars,
+};
+use std::ops::Range;
+
+/// Текст виразу з токенами, які оновлюються лише навколо правки
+///
+/// Після [`ReparseContext::edit`] повторно токенізується тільки ділянка між
+/// незачепленими токенами; токени після правки лише зсуваються. Результат
+/// той самий, що й у [`crate::parse_expression_with`] для нового тексту.
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{ReparseContext, parse_expression};
+///
+/// let mut context = ReparseContext::new("1 + 2 * 3");
+/// let expr = context.edit(8..9, "30").unwrap();
+/// assert_eq!(context.input(), "1 + 2 * 30");
+/// assert_eq!(expr, parse_expression("1 + 2 * 30").unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReparseContext {
+    input: String,
+    config: ParserConfig,
+    /// `None`, якщо текст не вдалося токенізувати
+    tokens: Option<Vec<Token>>,
+    reused: usize,
+}
+
+impl ReparseContext {
+    /// Створює контекст для `input` зі стандартними налаштуваннями парсера
+    pub fn new(input: &str) -> ReparseContext {
+        ReparseContext::with_config(input, ParserConfig::default())
+    }
+
+    /// Створює контекст для `input` із заданими налаштуваннями парсера
+    pub fn with_config(input: &str, config: ParserConfig) -> ReparseContext {
+        let tokens = tokenize(input, &config).ok();
+        ReparseContext {
+            input: input.to_string(),
+            config,
+            tokens,
+            reused: 0,
+        }
+    }
+
+    /// Поточний текст виразу
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+
+    /// Скільки токенів остання правка взяла без повторної токенізації
+    pub fn reused_tokens(&self) -> usize {
+        self.reused
+    }
+
+    /// Парсить поточний текст
+    pub fn parse(&self) -> Result<Expr, ParseError> {
+        let mut tokens = match &self.tokens {
+            Some(tokens) => tokens.clone(),
+            None => tokenize(&self.input, &self.config)?,
+        };
+        parse_tokens(&mut tokens, &self.config, &mut AstBuilder)
+            .map_err(|err| in_chars(err, &self.input))
+    }
+
+    /// Замінює байти `range` тексту на `replacement` і парсить результат
+    ///
+    /// # Паніка
+    /// Панікує, якщо `range` виходить за межі тексту або не лежить на межах
+    /// символів UTF-8.
+    pub fn edit(&mut self, range: Range<usize>, replacement: &str) -> Result<Expr, ParseError> {
+        let old_len = self.input.len();
+        self.input.replace_range(range.clone(), replacement);
+        self.reused = 0;
+        self.tokens = match self.tokens.take() {
+            Some(tokens) => self.retokenize(tokens, old_len, range, replacement.len()),
+            // Без попередніх токенів повторно використати нічого
+            None => tokenize(&self.input, &self.config).ok(),
+        };
+        self.parse()
+    }
+
+    /// Токенізує ділянку навколо правки та зшиває її з незачепленими токенами
+    fn retokenize(
+        &mut self,
+        mut tokens: Vec<Token>,
+        old_len: usize,
+        range: Range<usize>,
+        inserted: usize,
+    ) -> Option<Vec<Token>> {
+        // Сусідній з правкою токен теж перечитується: символи впритул до
+        // нього можуть злитися з ним в один токен (`1` + `.5` = `1.5`)
+        let prefix = tokens
+            .partition_point(|t| t.span.end < range.start)
+            .saturating_sub(1);
+        let suffix = (tokens.partition_point(|t| t.span.start <= range.end) + 1).min(tokens.len());
+        let shift = |position: usize| position + inserted - range.len();
+
+        let start = prefix.checked_sub(1).map_or(0, |i| tokens[i].span.end);
+        let end = shift(tokens.get(suffix).map_or(old_len, |t| t.span.start));
+        let region = self.input[start..end]
+            .char_indices()
+            .map(|(i, c)| (i + start, c));
+        let middle = tokenize_chars(region, &self.config).ok()?;
+
+        let rest = tokens.split_off(suffix);
+        tokens.truncate(prefix);
+        self.reused = tokens.len() + rest.len();
+        tokens.extend(middle);
+        tokens.extend(rest.into_iter().map(|mut token| {
+            token.span.start = shift(token.span.start);
+            token.span.end = shift(token.span.end);
+            token
+        }));
+        Some(tokens)
+    }
+}
@@ -0,0 +1,55 @@
+//! Цілочисельне обчислення з насиченням
+
+use crate::Expr;
+use crate::integer::{Overflow, evaluate_i64};
+
+/// Обчислює вираз у цілих `i64` з насиченням замість переповнення
+///
+/// Числа округлюються до найближчого цілого (половини від нуля), а `+ - *`
+/// і степінь з невід'ємним показником насичуються на `i64::MAX`/`i64::MIN`.
+/// `/` — ціле ділення з відкиданням дробової частини; ділення на нуль дає
+/// межу зі знаком діленого, а `0 / 0` — нуль. Решта операторів і
+/// тригонометричні функції обчислюються у `f64` і округлюються з насиченням.
+///
+/// # Паніка
+/// Панікує, якщо обчислення неможливе (див. [`crate::try_evaluate`]).
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{eval_saturating_i64, parse_expression};
+///
+/// let expr = parse_expression("9223372036854775807 + 1").unwrap();
+/// assert_eq!(eval_saturating_i64(&expr), i64::MAX);
+/// assert_eq!(eval_saturating_i64(&parse_expression("7 / 2").unwrap()), 3);
+/// ```
+pub fn eval_saturating_i64(expr: &Expr) -> i64 {
+    evaluate_i64::<Saturating>(expr)
+}
+
+struct Saturating;
+
+impl Overflow for Saturating {
+    fn neg(value: i64) -> i64 {
+        value.saturating_neg()
+    }
+
+    fn abs(value: i64) -> i64 {
+        value.saturating_abs()
+    }
+
+    fn binary(op: char, l: i64, r: i64) -> Option<i64> {
+        match op {
+            '+' => Some(l.saturating_add(r)),
+            '-' => Some(l.saturating_sub(r)),
+            '*' => Some(l.saturating_mul(r)),
+            '/' if r == 0 => Some(match l.signum() {
+                1 => i64::MAX,
+                -1 => i64::MIN,
+                _ => 0,
+            }),
+            '/' => Some(l.saturating_div(r)),
+            '^' if r >= 0 => Some(l.saturating_pow(u32::try_from(r).unwrap_or(u32::MAX))),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Обчислення з розрізненням чисел і логічних значень
+
+use crate::evaluator::{Evaluator, eval_tree};
+use crate::{EvalError, Expr, apply_binary_op, apply_function, format_number};
+use std::fmt;
+
+/// Значення виразу — результат [`evaluate_typed`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Value {
+    /// Число
+    Num(f64),
+    /// Логічне значення — результат порівняння чи `&`
+    Bool(bool),
+}
+
+impl Value {
+    /// Назва типу для повідомлень про помилки
+    fn type_name(self) -> &'static str {
+        match self {
+            Value::Num(_) => "number",
+            Value::Bool(_) => "boolean",
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Num(n) => write!(f, "{}", format_number(*n)),
+            Value::Bool(b) => write!(f, "{}", b),
+        }
+    }
+}
+
+/// Обчислює вираз, розрізняючи числа та логічні значення
+///
+/// Порівняння `<` і `>` приймають числа й дають [`Value::Bool`], `&` —
+/// приймає й дає логічні значення, а арифметика, унарний мінус і функції
+/// працюють лише з числами. Операнд не того типу (`(1 < 2) + 3`) дає
+/// [`EvalError::TypeMismatch`]. Змінні `let` можуть мати будь-який тип.
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{Value, evaluate_typed, parse_expression};
+///
+/// let expr = parse_expression("1 < 2 < 3").unwrap();
+/// assert_eq!(evaluate_typed(&expr), Ok(Value::Bool(true)));
+/// ```
+pub fn evaluate_typed(expr: &Expr) -> Result<Value, EvalError> {
+    eval_tree(expr, &mut TypedEval, &mut Vec::new())
+}
+
+struct TypedEval;
+
+impl Evaluator for TypedEval {
+    type Value = Value;
+
+    fn number(&mut self, value: f64) -> Result<Value, EvalError> {
+        Ok(Value::Num(value))
+    }
+
+    fn neg(&mut self, operand: Value) -> Result<Value, EvalError> {
+        Ok(Value::Num(-number("-", operand)?))
+    }
+
+    fn binary(&mut self, op: char, l: Value, r: Value) -> Result<Value, EvalError> {
+        let name = op.to_string();
+        match op {
+            '&' => Ok(Value::Bool(boolean(&name, l)? && boolean(&name, r)?)),
+            '<' => Ok(Value::Bool(number(&name, l)? < number(&name, r)?)),
+            '>' => Ok(Value::Bool(number(&name, l)? > number(&name, r)?)),
+            _ => apply_binary_op(op, number(&name, l)?, number(&name, r)?).map(Value::Num),
+        }
+    }
+
+    fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Value, EvalError> {
+        let values = args
+            .into_iter()
+            .map(|arg| number(name, arg))
+            .collect::<Result<Vec<_>, _>>()?;
+        apply_function(name, &values).map(Value::Num)
+    }
+}
+
+/// Число з `value` або помилка типу для операції `op`
+fn number(op: &str, value: Value) -> Result<f64, EvalError> {
+    match value {
+        Value::Num(n) => Ok(n),
+        other => Err(mismatch(op, "number", other)),
+    }
+}
+
+/// Логічне значення з `value` або помилка типу для операції `op`
+fn boolean(op: &str, value: Value) -> Result<bool, EvalError> {
+    match value {
+        Value::Bool(b) => Ok(b),
+        other => Err(mismatch(op, "boolean", other)),
+    }
+}
+
+fn mismatch(op: &str, expected: &str, found: Value) -> EvalError {
+    EvalError::TypeMismatch {
+        op: op.to_string(),
+        expected: expected.to_string(),
+        found: found.type_name().to_string(),
+    }
+}
@@ -0,0 +1,212 @@
+//! Обчислення з одиницями вимірювання (feature `units`)
+
+use crate::evaluator::{Evaluator, eval_tree, leaf_error};
+use crate::{EvalError, Expr, format_number};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Одиниця вимірювання як добуток базових одиниць у цілих степенях
+///
+/// Наприклад, `m*s^-1` зберігається як `{m: 1, s: -1}`. Порожня одиниця
+/// означає безрозмірну величину.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Unit(BTreeMap<String, i32>);
+
+impl Unit {
+    /// Безрозмірна одиниця
+    pub fn dimensionless() -> Unit {
+        Unit::default()
+    }
+
+    /// Базова одиниця на кшталт `m` чи `s`
+    pub fn base(name: &str) -> Unit {
+        Unit(BTreeMap::from([(name.to_string(), 1)]))
+    }
+
+    /// Чи є одиниця безрозмірною
+    pub fn is_dimensionless(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn combine(&self, other: &Unit, sign: i32) -> Unit {
+        let mut powers = self.0.clone();
+        for (name, power) in &other.0 {
+            let entry = powers.entry(name.clone()).or_insert(0);
+            *entry += sign * power;
+            if *entry == 0 {
+                powers.remove(name);
+            }
+        }
+        Unit(powers)
+    }
+
+    fn powi(&self, exponent: i32) -> Unit {
+        if exponent == 0 {
+            return Unit::dimensionless();
+        }
+        Unit(
+            self.0
+                .iter()
+                .map(|(name, power)| (name.clone(), power * exponent))
+                .collect(),
+        )
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_dimensionless() {
+            return write!(f, "1");
+        }
+        let parts: Vec<String> = self
+            .0
+            .iter()
+            .map(|(name, power)| match power {
+                1 => name.clone(),
+                _ => format!("{}^{}", name, power),
+            })
+            .collect();
+        write!(f, "{}", parts.join("*"))
+    }
+}
+
+/// Значення з одиницею вимірювання — результат [`evaluate_units`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnitValue {
+    /// Числове значення
+    pub value: f64,
+    /// Одиниця вимірювання
+    pub unit: Unit,
+}
+
+impl fmt::Display for UnitValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.unit.is_dimensionless() {
+            write!(f, "{}", format_number(self.value))
+        } else {
+            write!(f, "{}{}", format_number(self.value), self.unit)
+        }
+    }
+}
+
+/// Обчислює вираз з урахуванням одиниць вимірювання
+///
+/// Додавання, віднімання та порівняння вимагають однакових одиниць, інакше
+/// повертається [`EvalError::UnitMismatch`]. Множення й ділення множать та
+/// ділять одиниці. Показник степеня має бути безрозмірним, а для розмірної
+/// основи — ще й цілим. Звичайні числа безрозмірні.
+///
+/// # Приклад
+/// ```
+/// let expr = tree_parser::parse_expression("3m + 2m").unwrap();
+/// let result = tree_parser::evaluate_units(&expr).unwrap();
+/// assert_eq!(result.to_string(), "5m");
+/// ```
+pub fn evaluate_units(expr: &Expr) -> Result<UnitValue, EvalError> {
+    eval_tree(expr, &mut UnitsEval, &mut Vec::new())
+}
+
+struct UnitsEval;
+
+impl Evaluator for UnitsEval {
+    type Value = UnitValue;
+
+    fn number(&mut self, value: f64) -> Result<UnitValue, EvalError> {
+        Ok(UnitValue {
+            value,
+            unit: Unit::dimensionless(),
+        })
+    }
+
+    fn leaf(&mut self, leaf: &Expr) -> Result<UnitValue, EvalError> {
+        match leaf {
+            Expr::Quantity { value, unit } => Ok(UnitValue {
+                value: *value,
+                unit: Unit::base(unit),
+            }),
+            other => Err(leaf_error(other)),
+        }
+    }
+
+    fn neg(&mut self, operand: UnitValue) -> Result<UnitValue, EvalError> {
+        Ok(UnitValue {
+            value: -operand.value,
+            unit: operand.unit,
+        })
+    }
+
+    fn binary(&mut self, op: char, l: UnitValue, r: UnitValue) -> Result<UnitValue, EvalError> {
+        let value = crate::apply_binary_op(op, l.value, r.value)?;
+        let unit = match op {
+            '+' | '-' => same_unit(&l, &r)?,
+            '<' | '>' => {
+                same_unit(&l, &r)?;
+                Unit::dimensionless()
+            }
+            '*' => l.unit.combine(&r.unit, 1),
+            '/' => l.unit.combine(&r.unit, -1),
+            '^' => {
+                dimensionless(&r)?;
+                if l.unit.is_dimensionless() {
+                    Unit::dimensionless()
+                } else if r.value.fract() == 0.0 {
+                    l.unit.powi(r.value as i32)
+                } else {
+                    return Err(EvalError::NonIntegerOperand {
+                        op: '^',
+                        value: r.value,
+                    });
+                }
+            }
+            _ => {
+                dimensionless(&l)?;
+                dimensionless(&r)?;
+                Unit::dimensionless()
+            }
+        };
+        Ok(UnitValue { value, unit })
+    }
+
+    fn call(&mut self, name: &str, args: Vec<UnitValue>) -> Result<UnitValue, EvalError> {
+        let numbers: Vec<f64> = args.iter().map(|v| v.value).collect();
+        let value = crate::apply_function(name, &numbers)?;
+        // Тригонометричні функції приймають і повертають безрозмірні
+        // значення, решта — мають одиницю аргументів, тож вони мають збігатися
+        let unit = match args.split_first() {
+            _ if matches!(name, "sin" | "cos" | "tan") => {
+                args.iter().try_for_each(dimensionless)?;
+                Unit::dimensionless()
+            }
+            Some((first, rest)) => {
+                for other in rest {
+                    same_unit(first, other)?;
+                }
+                first.unit.clone()
+            }
+            None => Unit::dimensionless(),
+        };
+        Ok(UnitValue { value, unit })
+    }
+}
+
+fn same_unit(l: &UnitValue, r: &UnitValue) -> Result<Unit, EvalError> {
+    if l.unit == r.unit {
+        Ok(l.unit.clone())
+    } else {
+        Err(EvalError::UnitMismatch {
+            left: l.unit.to_string(),
+            right: r.unit.to_string(),
+        })
+    }
+}
+
+fn dimensionless(v: &UnitValue) -> Result<(), EvalError> {
+    if v.unit.is_dimensionless() {
+        Ok(())
+    } else {
+        Err(EvalError::UnitMismatch {
+            left: v.unit.to_string(),
+            right: Unit::dimensionless().to_string(),
+        })
+    }
+}
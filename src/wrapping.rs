@@ -0,0 +1,66 @@
+//! Цілочисельне обчислення із загортанням
+
+use crate::Expr;
+use crate::integer::{Overflow, evaluate_i64};
+
+/// Обчислює вираз у цілих `i64` із загортанням за модулем 2^64
+///
+/// Числа округлюються до найближчого цілого (половини від нуля; завеликі
+/// літерали насичуються), а `+ - *`, унарний мінус, `abs` і степінь з
+/// невід'ємним показником загортаються, як `i64::wrapping_*`. `/` — ціле
+/// ділення з відкиданням дробової частини, тож `i64::MIN / -1` дає
+/// `i64::MIN`; ділення на нуль, як і в [`crate::eval_saturating_i64`], дає
+/// межу зі знаком діленого. Решта операторів і тригонометричні функції
+/// обчислюються у `f64` і округлюються з насиченням.
+///
+/// # Паніка
+/// Панікує, якщо обчислення неможливе (див. [`crate::try_evaluate`]).
+///
+/// # Приклад
+/// ```
+/// use tree_parser::{evaluate_wrapping_i64, parse_expression};
+///
+/// let expr = parse_expression("9223372036854775807 + 1").unwrap();
+/// assert_eq!(evaluate_wrapping_i64(&expr), i64::MIN);
+/// ```
+pub fn evaluate_wrapping_i64(expr: &Expr) -> i64 {
+    evaluate_i64::<Wrapping>(expr)
+}
+
+struct Wrapping;
+
+impl Overflow for Wrapping {
+    fn neg(value: i64) -> i64 {
+        value.wrapping_neg()
+    }
+
+    fn abs(value: i64) -> i64 {
+        value.wrapping_abs()
+    }
+
+    /// Ділення на нуль обчислюється у `f64` і насичується
+    fn binary(op: char, l: i64, r: i64) -> Option<i64> {
+        match op {
+            '+' => Some(l.wrapping_add(r)),
+            '-' => Some(l.wrapping_sub(r)),
+            '*' => Some(l.wrapping_mul(r)),
+            '/' if r != 0 => Some(l.wrapping_div(r)),
+            '^' if r >= 0 => Some(wrapping_pow(l, r.unsigned_abs())),
+            _ => None,
+        }
+    }
+}
+
+/// Степінь піднесенням до квадрата; `i64::wrapping_pow` приймає лише `u32`
+fn wrapping_pow(base: i64, mut exponent: u64) -> i64 {
+    let mut result: i64 = 1;
+    let mut square = base;
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = result.wrapping_mul(square);
+        }
+        exponent >>= 1;
+        square = square.wrapping_mul(square);
+    }
+    result
+}
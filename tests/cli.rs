@@ -0,0 +1,362 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+fn write_temp(name: &str, content: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("tree_parser_{}_{}", std::process::id(), name));
+    fs::write(&path, content).expect("cannot write temp file");
+    path
+}
+
+fn run(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_tree_parser"))
+        .args(args)
+        .output()
+        .expect("cannot run tree_parser")
+}
+
+#[test]
+fn test_check_valid_file() {
+    let file = write_temp("check_valid.txt", "3 + 5 * (2 - 8) / 4");
+    let output = run(&["check", file.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "OK");
+}
+
+#[test]
+fn test_check_invalid_file() {
+    let file = write_temp("check_invalid.txt", "2 + (3 * 4");
+    let output = run(&["check", file.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Missing closing parenthesis"));
+}
+
+#[test]
+fn test_eval_normalizes_negative_zero() {
+    let zero = write_temp("neg_zero.txt", "(0 - 1) * 0");
+    let output = run(&["eval", zero.to_str().unwrap()]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "Result: 0");
+
+    let negative = write_temp("neg_two.txt", "(0 - 1) * 2");
+    let output = run(&["eval", negative.to_str().unwrap()]);
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "Result: -2");
+}
+
+#[test]
+fn test_evalcsv_command() {
+    let file = write_temp("evalcsv.csv", "expr\n1 + 1\n(\n");
+    let output = run(&["evalcsv", file.to_str().unwrap(), "expr"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "expr,result\n1 + 1,2\n(,ERR\n"
+    );
+}
+
+#[test]
+fn test_diff_equal_values() {
+    let first = write_temp("diff_a.txt", "2 * (3 + 4)");
+    let second = write_temp("diff_b.txt", "2 * 3 + 2 * 4");
+    let output = run(&["diff", first.to_str().unwrap(), second.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "Match: 14 == 14"
+    );
+}
+
+#[test]
+fn test_diff_different_values() {
+    let first = write_temp("diff_c.txt", "2 * 3 + 4");
+    let second = write_temp("diff_d.txt", "2 * (3 + 4)");
+    let output = run(&["diff", first.to_str().unwrap(), second.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "Mismatch: 10 != 14"
+    );
+}
+
+#[test]
+fn test_diff_reports_free_variable() {
+    let first = write_temp("diff_e.txt", "x + 1");
+    let second = write_temp("diff_f.txt", "2");
+    let output = run(&["diff", first.to_str().unwrap(), second.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("Cannot evaluate expression in file"),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains("Undefined variable: x"), "{}", stderr);
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+}
+
+#[test]
+fn test_eval_output_matches_tree_evaluation() {
+    let inputs = [
+        "3 + 5 * (2 - 8) / 4",
+        "2 ^ 3 ^ 2",
+        "max(1, -2) < 3",
+        "-(1 + 2) * 4",
+    ];
+    for (i, input) in inputs.iter().enumerate() {
+        let file = write_temp(&format!("eval_same_{}.txt", i), input);
+        let output = run(&["eval", file.to_str().unwrap()]);
+        assert!(output.status.success());
+        let expected = tree_parser::evaluate(&tree_parser::parse_expression(input).unwrap());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout).trim(),
+            format!("Result: {}", tree_parser::format_number(expected))
+        );
+    }
+}
+
+#[test]
+fn test_whitespace_only_file() {
+    let file = write_temp("blank.txt", "  \n\t\n");
+    for command in ["eval", "check"] {
+        let output = run(&[command, file.to_str().unwrap()]);
+        assert!(!output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("contains no expression"), "{}", stderr);
+        assert!(!stderr.contains("Unexpected end"));
+    }
+}
+
+#[test]
+fn test_eval_strict_nan() {
+    let file = write_temp("strict_nan.txt", "10 / (5 - 5)");
+    let output = run(&["eval", file.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "Result: inf"
+    );
+
+    let output = run(&["eval", file.to_str().unwrap(), "--strict-nan"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not a finite number"));
+}
+
+#[test]
+fn test_eval_fraction() {
+    let file = write_temp("fraction.txt", "3 / 4");
+    let output = run(&["eval", file.to_str().unwrap(), "--fraction"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "Result: 0.75 (3/4)"
+    );
+
+    let file = write_temp("fraction_irrational.txt", "2 ^ (1 / 2)");
+    let output = run(&["eval", file.to_str().unwrap(), "--fraction"]);
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stdout).contains('/'));
+}
+
+#[test]
+fn test_formats_command() {
+    let file = write_temp("formats.txt", "2 + 3 * 4");
+    let output = run(&["formats", file.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "Infix:   2 + 3 * 4\n\
+         Prefix:  + 2 * 3 4\n\
+         Postfix: 2 3 4 * +\n\
+         S-expr:  (+ 2 (* 3 4))\n"
+    );
+}
+
+#[test]
+fn test_eval_with_variables() {
+    let file = write_temp("variables.txt", "x * x + y");
+    let output = run(&["eval", file.to_str().unwrap(), "x=2", "y=0.5"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "Result: 4.5"
+    );
+
+    let output = run(&["eval", file.to_str().unwrap(), "x=2", "--fraction"]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Undefined variables: y"), "{}", stderr);
+
+    let output = run(&["eval", file.to_str().unwrap(), "x=two", "y=1"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid value in 'x=two'"));
+
+    let output = run(&["eval", file.to_str().unwrap()]);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Undefined variables: x"), "{}", stderr);
+}
+
+#[test]
+fn test_dot_svg_falls_back_without_graphviz() {
+    let file = write_temp("dot.txt", "2 + 3");
+    let svg = std::env::temp_dir().join(format!("tree_parser_{}_dot.svg", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_tree_parser"))
+        .args([
+            "dot",
+            file.to_str().unwrap(),
+            "--svg",
+            svg.to_str().unwrap(),
+        ])
+        .env("PATH", "")
+        .output()
+        .expect("cannot run tree_parser");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("digraph expr {"), "{}", stdout);
+    assert!(stdout.contains("n0 -> n1;"));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("not found"));
+    assert!(!svg.exists());
+}
+
+#[test]
+fn test_filter_reads_stdin() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    // Обробку окремих рядків перевіряє модульний тест filter_lines у src/main.rs
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tree_parser"))
+        .arg("filter")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("cannot run tree_parser");
+    child.stdin.take().unwrap().write_all(b"2 + 3\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "5\n");
+}
+
+#[test]
+fn test_eval_file_with_bom_and_crlf() {
+    let file = write_temp("bom_crlf.txt", "\u{feff}2 +\r\n3\u{a0}*\r\n4\r\n");
+    let output = run(&["eval", file.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "Result: 14");
+}
+
+/// Зупиняє дочірній процес, навіть якщо тест панікує
+struct KillOnDrop(std::process::Child);
+
+impl Drop for KillOnDrop {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+#[test]
+fn test_watch_prints_initial_result() {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    // Реакцію на зміни перевіряє модульний тест Watcher::poll у src/main.rs
+    let file = write_temp("watch.txt", "2 + 3");
+    let mut child = KillOnDrop(
+        Command::new(env!("CARGO_BIN_EXE_tree_parser"))
+            .args(["watch", file.to_str().unwrap()])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("cannot run tree_parser"),
+    );
+    let stdout = child.0.stdout.take().unwrap();
+    let line = BufReader::new(stdout).lines().next().unwrap().unwrap();
+    assert_eq!(line, "Result: 5");
+}
+
+#[test]
+fn test_grammar_lists_active_operators() {
+    let output = run(&["grammar"]);
+    assert!(output.status.success());
+    let grammar = String::from_utf8_lossy(&output.stdout);
+    for op in ["+", "-", "*", "/", "^", "**", "<", ">", "(", ")", ","] {
+        assert!(
+            grammar.contains(&format!("\"{}\"", op)),
+            "{} in {}",
+            op,
+            grammar
+        );
+    }
+    for function in [
+        "abs", "floor", "ceil", "round", "sin", "cos", "tan", "min", "max",
+    ] {
+        assert!(
+            grammar.contains(&format!("\"{}\"", function)),
+            "{}",
+            function
+        );
+    }
+    assert_eq!(grammar.contains("\"i\""), cfg!(feature = "complex"));
+    assert!(
+        grammar
+            .lines()
+            .all(|line| line.starts_with(' ') || line.contains(" = "))
+    );
+}
+
+#[test]
+fn test_eval_scientific() {
+    let file = write_temp("scientific_large.txt", "6.02214076e23 * 1000 / 1000");
+    let output = run(&["eval", file.to_str().unwrap(), "--scientific"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "Result: 6.02214076e23"
+    );
+    let output = run(&["eval", file.to_str().unwrap(), "--scientific=4"]);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "Result: 6.022e23"
+    );
+
+    let file = write_temp("scientific_small.txt", "-x / 8000000");
+    let output = run(&["eval", file.to_str().unwrap(), "x=12", "--scientific=2"]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "Result: -1.5e-6"
+    );
+
+    let output = run(&["eval", file.to_str().unwrap(), "x=1", "--scientific=0"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid significant digits"));
+}
+
+#[test]
+fn test_check_renders_diagnostic() {
+    let end = write_temp("diagnostic.txt", "1 + 2\n  * 3 /\n");
+    let token = write_temp("diagnostic_token.txt", "2 + )");
+    for (file, line, caret) in [
+        (&end, "2 |   * 3 /\n", "  |        ^\n"),
+        (&token, "1 | 2 + )\n", "  |     ^\n"),
+    ] {
+        for command in [&["check"][..], &["eval"], &["eval", "x=1"]] {
+            let mut args = command.to_vec();
+            args.insert(1, file.to_str().unwrap());
+            let output = run(&args);
+            assert!(!output.status.success(), "{:?}", args);
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            assert!(stderr.contains(line), "{:?}: {}", args, stderr);
+            assert!(stderr.contains(caret), "{:?}: {}", args, stderr);
+        }
+    }
+}
+
+#[test]
+fn test_eval_prints_nan_canonically() {
+    let file = write_temp("nan.txt", "0 / 0");
+    let output = run(&["eval", file.to_str().unwrap()]);
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "Result: nan"
+    );
+}
@@ -1,10 +1,23 @@
 use anyhow::Result;
-use tree_parser::{Expr, evaluate, parse_expression, ParseError};
+use std::collections::HashMap;
+use tree_parser::{
+    AngleMode, CsvError, DisplayOptions, EvalCache, EvalError, Expr, ExprArena, ExprNode,
+    FunctionTable, MulSign, NumberFormat, ParseError, ParseEvalError, ParserConfig, ReparseContext,
+    SourceToken, Span, SpanTree, Value, approx_eq, eval_csv, eval_fixed_point, eval_saturating_i64,
+    eval_to_string, evaluate, evaluate_bounded, evaluate_exact, evaluate_interval,
+    evaluate_interval_with, evaluate_lazy_with_functions, evaluate_mod, evaluate_non_negative,
+    evaluate_over, evaluate_typed, evaluate_with, evaluate_with_angle_mode, evaluate_with_budget,
+    evaluate_with_functions, evaluate_wrapping_i64, find_redundant_parens, format_number,
+    parse_and_eval, parse_expression, parse_expression_bytes, parse_expression_spanned,
+    parse_expression_with, parse_expression_with_limits, parse_recover, render_diagnostic,
+    run_bytecode, to_fraction, tokenize_preserving, try_evaluate, try_evaluate_interval_with,
+    try_run_bytecode,
+};
 
 #[test]
 fn test_simple_addition_ast() -> Result<()> {
     let expr = parse_expression("2 + 3")?;
-    expr.print_tree();
+    expr.print_tree()?;
     assert_eq!(
         expr,
         Expr::BinaryOp {
@@ -20,7 +33,7 @@ fn test_simple_addition_ast() -> Result<()> {
 #[test]
 fn test_operator_precedence_ast() -> Result<()> {
     let expr = parse_expression("2 + 3 * 4")?;
-    expr.print_tree();
+    expr.print_tree()?;
     assert_eq!(
         expr,
         Expr::BinaryOp {
@@ -33,14 +46,14 @@ fn test_operator_precedence_ast() -> Result<()> {
             }),
         }
     );
-    assert_eq!(evaluate(&expr), 14.0);
+    assert_eq!(evaluate::<f64>(&expr), 14.0);
     Ok(())
 }
 
 #[test]
 fn test_with_parentheses_ast() -> Result<()> {
     let expr = parse_expression("(2 + 3) * 4")?;
-    expr.print_tree();
+    expr.print_tree()?;
     assert_eq!(
         expr,
         Expr::BinaryOp {
@@ -53,14 +66,14 @@ fn test_with_parentheses_ast() -> Result<()> {
             right: Box::new(Expr::Number(4.0)),
         }
     );
-    assert_eq!(evaluate(&expr), 20.0);
+    assert_eq!(evaluate::<f64>(&expr), 20.0);
     Ok(())
 }
 
 #[test]
 fn test_complex_expression_ast() -> Result<()> {
     let expr = parse_expression("3 + 5 * (2 - 8) / 4")?;
-    expr.print_tree();
+    expr.print_tree()?;
     assert_eq!(
         expr,
         Expr::BinaryOp {
@@ -81,7 +94,7 @@ fn test_complex_expression_ast() -> Result<()> {
             }),
         }
     );
-    let result = evaluate(&expr);
+    let result: f64 = evaluate(&expr);
 assert!((result - (-4.5)).abs() < 1e-6);
     Ok(())
 }
@@ -89,7 +102,7 @@ assert!((result - (-4.5)).abs() < 1e-6);
 #[test]
 fn test_evaluate_numeric() -> Result<()> {
     let expr = parse_expression("3 + 5 * (2 - 8) / 4")?;
-    let result = evaluate(&expr);
+    let result: f64 = evaluate(&expr);
     assert!((result + 4.5).abs() < 1e-6); 
     Ok(())
 }
@@ -113,8 +126,8 @@ fn test_missing_closing_parenthesis_error() {
 
 #[test]
 fn test_unexpected_token_error() {
-    let err = parse_expression("2 + x").unwrap_err();
-    assert!(matches!(err, ParseError::UnexpectedToken(tok) if tok == "x"));
+    let err = parse_expression("2 + )").unwrap_err();
+    assert!(matches!(err, ParseError::UnexpectedToken(tok) if tok == ")"));
 }
 
 #[test]
@@ -130,7 +143,7 @@ fn test_to_infix() -> Result<()> {
 #[test]
 fn test_nested_parentheses() -> Result<()> {
     let expr = parse_expression("((1 + 2) * (3 + 4)) / 7")?;
-    let result = evaluate(&expr);
+    let result: f64 = evaluate(&expr);
     assert!((result - 3.0).abs() < 1e-6); 
     Ok(())
 }
@@ -150,7 +163,7 @@ fn test_invalid_token_error() {
 #[test]
 fn test_division_by_zero() -> Result<()> {
     let expr = parse_expression("10 / (5 - 5)")?;
-    let result = evaluate(&expr);
+    let result = evaluate::<f64>(&expr);
     assert!(result.is_infinite() || result.is_nan());
     Ok(())
 }
@@ -158,7 +171,7 @@ fn test_division_by_zero() -> Result<()> {
 #[test]
 fn test_long_complex_expression() -> Result<()> {
     let expr = parse_expression("1 + 2 - 3 * 4 / 2 + (5 - 6 + (7 * 8))")?;
-    let result = evaluate(&expr);
+    let result: f64 = evaluate(&expr);
     assert!((result - 52.0).abs() < 1e-6); 
     Ok(())
 }
@@ -167,6 +180,2480 @@ fn test_long_complex_expression() -> Result<()> {
 #[test]
 fn test_only_number() -> Result<()> {
     let expr = parse_expression("42")?;
-    assert_eq!(evaluate(&expr), 42.0);
+    assert_eq!(evaluate::<f64>(&expr), 42.0);
+    Ok(())
+}
+
+#[test]
+fn test_try_evaluate_unknown_operator() {
+    let expr = Expr::BinaryOp {
+        op: '$',
+        left: Box::new(Expr::Number(1.0)),
+        right: Box::new(Expr::Number(2.0)),
+    };
+    assert_eq!(try_evaluate(&expr), Err(EvalError::UnknownOperator('$')));
+}
+
+#[test]
+fn test_to_infix_with_default_matches_to_infix() -> Result<()> {
+    let expr = parse_expression("(1 + 2) * 3 - 4 / 5")?;
+    assert_eq!(
+        expr.to_infix_with(&DisplayOptions::default()),
+        expr.to_infix()
+    );
+    Ok(())
+}
+
+#[test]
+fn test_to_infix_with_options() -> Result<()> {
+    let expr = parse_expression("2 * (3 + 4) - (5 - 6)")?;
+
+    let dot = DisplayOptions {
+        mul_sign: MulSign::Dot,
+        spaced: false,
+        minimal_parens: true,
+    };
+    assert_eq!(expr.to_infix_with(&dot), "2·(3+4)-(5-6)");
+
+    let implicit = DisplayOptions {
+        mul_sign: MulSign::Implicit,
+        spaced: true,
+        minimal_parens: true,
+    };
+    assert_eq!(expr.to_infix_with(&implicit), "2(3 + 4) - (5 - 6)");
+    Ok(())
+}
+
+#[test]
+fn test_parse_bytes_matches_str() -> Result<()> {
+    for input in [
+        "42",
+        "2 + 3 * 4",
+        "3 + 5 * (2 - 8) / 4",
+        "((1 + 2) * (3 + 4)) / 7",
+    ] {
+        assert_eq!(
+            parse_expression_bytes(input.as_bytes())?,
+            parse_expression(input)?
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_parse_bytes_rejects_non_ascii() {
+    let err = parse_expression_bytes("2 + \u{0456}".as_bytes()).unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::NonAsciiByte {
+            byte: 0xd1,
+            position: 4
+        }
+    ));
+}
+
+#[test]
+fn test_interval_addition() -> Result<()> {
+    let expr = parse_expression("2 + 3 - 1")?;
+    assert_eq!(evaluate_interval(&expr), (4.0, 4.0));
+
+    let bounds = evaluate_interval_with(&expr, &|n| (n - 0.5, n + 0.5));
+    assert_eq!(bounds, (2.5, 5.5));
+    Ok(())
+}
+
+#[test]
+fn test_interval_multiplication() -> Result<()> {
+    let expr = parse_expression("(1 - 3) * 4")?;
+    let bounds = evaluate_interval_with(&expr, &|n| (n - 1.0, n + 1.0));
+    // [0, 2] - [2, 4] = [-4, 0]; [-4, 0] * [3, 5] = [-20, 0]
+    assert_eq!(bounds, (-20.0, 0.0));
+    Ok(())
+}
+
+#[test]
+fn test_interval_division_by_zero_interval() -> Result<()> {
+    let expr = parse_expression("1 / (2 - 2)")?;
+    assert_eq!(evaluate_interval(&expr), (f64::NEG_INFINITY, f64::INFINITY));
+    Ok(())
+}
+
+#[test]
+fn test_try_evaluate_interval_unknown_operator() {
+    let expr = Expr::BinaryOp {
+        op: '$',
+        left: Box::new(Expr::Number(1.0)),
+        right: Box::new(Expr::Number(2.0)),
+    };
+    assert_eq!(
+        try_evaluate_interval_with(&expr, &|n| (n, n)),
+        Err(EvalError::UnknownOperator('$'))
+    );
+    let expr = Expr::Variable("x".to_string());
+    assert_eq!(
+        try_evaluate_interval_with(&expr, &|n| (n, n)),
+        Err(EvalError::UndefinedVariable("x".to_string()))
+    );
+}
+
+#[test]
+fn test_negative_zero_formatting() {
+    assert_eq!(format_number(-0.0), "0");
+    assert_eq!(format_number(-2.0), "-2");
+    assert_eq!(Expr::Number(-0.0).to_infix(), "0");
+
+    let expr = Expr::BinaryOp {
+        op: '*',
+        left: Box::new(Expr::Number(-1.0)),
+        right: Box::new(Expr::Number(0.0)),
+    };
+    assert_eq!(format_number(evaluate(&expr)), "0");
+}
+
+#[test]
+fn test_accessors() -> Result<()> {
+    let number = parse_expression("7")?;
+    assert_eq!(number.as_number(), Some(7.0));
+    assert!(number.as_binary_op().is_none());
+
+    let op = parse_expression("2 * 3")?;
+    assert_eq!(op.as_number(), None);
+    let (sign, left, right) = op.as_binary_op().unwrap();
+    assert_eq!(sign, '*');
+    assert_eq!(left, &Expr::Number(2.0));
+    assert_eq!(right, &Expr::Number(3.0));
+    Ok(())
+}
+
+#[test]
+fn test_comparison_chain_true() -> Result<()> {
+    let expr = parse_expression("1 < 2 < 3")?;
+    assert_eq!(
+        expr,
+        Expr::BinaryOp {
+            op: '&',
+            left: Box::new(Expr::BinaryOp {
+                op: '<',
+                left: Box::new(Expr::Number(1.0)),
+                right: Box::new(Expr::Number(2.0)),
+            }),
+            right: Box::new(Expr::BinaryOp {
+                op: '<',
+                left: Box::new(Expr::Number(2.0)),
+                right: Box::new(Expr::Number(3.0)),
+            }),
+        }
+    );
+    assert_eq!(evaluate::<f64>(&expr), 1.0);
+    assert_eq!(evaluate::<f64>(&parse_expression("3 > 2 > 1")?), 1.0);
+    Ok(())
+}
+
+#[test]
+fn test_comparison_chain_false() -> Result<()> {
+    // Без розгортання `(3 < 2) < 5` дало б істину
+    assert_eq!(evaluate::<f64>(&parse_expression("3 < 2 < 5")?), 0.0);
+    assert_eq!(evaluate::<f64>(&parse_expression("1 < 3 < 2")?), 0.0);
+    Ok(())
+}
+
+#[test]
+fn test_single_comparison() -> Result<()> {
+    let expr = parse_expression("1 + 1 > 3")?;
+    assert_eq!(expr.to_infix(), "((1 + 1) > 3)");
+    assert_eq!(evaluate::<f64>(&expr), 0.0);
+    assert_eq!(evaluate::<f64>(&parse_expression("(2 < 3) + 1")?), 2.0);
+    Ok(())
+}
+
+#[test]
+fn test_arena_matches_boxed_evaluation() -> Result<()> {
+    for input in [
+        "42",
+        "2 + 3 * 4",
+        "3 + 5 * (2 - 8) / 4",
+        "1 + 2 - 3 * 4 / 2 + (5 - 6 + (7 * 8))",
+        "1 < 2 < 3",
+    ] {
+        let arena = ExprArena::parse(input)?;
+        assert_eq!(arena.evaluate(), evaluate(&parse_expression(input)?));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_arena_layout() -> Result<()> {
+    let arena = ExprArena::parse("2 + 3")?;
+    assert_eq!(
+        arena.nodes(),
+        &[
+            ExprNode::Number(2.0),
+            ExprNode::Number(3.0),
+            ExprNode::BinaryOp {
+                op: '+',
+                left: 0,
+                right: 1
+            },
+        ]
+    );
+    assert_eq!(arena.root(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_arena_try_evaluate_errors() {
+    let expr = Expr::BinaryOp {
+        op: '$',
+        left: Box::new(Expr::Number(1.0)),
+        right: Box::new(Expr::Number(2.0)),
+    };
+    let arena = ExprArena::from_expr(&expr);
+    assert_eq!(arena.try_evaluate(), Err(EvalError::UnknownOperator('$')));
+    assert_eq!(ExprArena::parse("1 + 2").unwrap().try_evaluate(), Ok(3.0));
+}
+
+#[test]
+fn test_eval_csv() {
+    let csv = "id,expr,note\n1,2 + 3 * 4,ok\n2,\"(1 + 2) * 3\",quoted\n3,2 + x,bad\n";
+    let out = eval_csv(csv, "expr").unwrap();
+    assert_eq!(
+        out,
+        "id,expr,note,result\n\
+         1,2 + 3 * 4,ok,14\n\
+         2,\"(1 + 2) * 3\",quoted,9\n\
+         3,2 + x,bad,ERR\n"
+    );
+
+    assert_eq!(
+        eval_csv(csv, "formula"),
+        Err(CsvError::MissingColumn("formula".to_string()))
+    );
+}
+
+#[test]
+fn test_localized_errors() {
+    let err = parse_expression("").unwrap_err();
+    assert_eq!(err.localized("uk"), "Неочікуваний кінець вводу");
+    assert_eq!(err.localized("en"), err.to_string());
+
+    let err = parse_expression("2 + @").unwrap_err();
+    assert_eq!(err.localized("uk"), "Неочікуваний токен: @");
+    assert_eq!(err.localized("fr"), "Unexpected token: @");
+}
+
+#[test]
+fn test_numbers() -> Result<()> {
+    let expr = parse_expression("(1 + 2) * (3 - 4 / 5)")?;
+    assert_eq!(expr.numbers(), vec![1.0, 2.0, 3.0, 4.0, 5.0]);
+
+    assert_eq!(parse_expression("42")?.numbers(), vec![42.0]);
+    Ok(())
+}
+
+#[test]
+fn test_caret_as_power() -> Result<()> {
+    let expr = parse_expression("6 ^ 3")?;
+    assert_eq!(expr.as_binary_op().map(|(op, _, _)| op), Some('^'));
+    assert_eq!(evaluate::<f64>(&expr), 216.0);
+
+    // Правоасоціативність: 2 ^ (3 ^ 2)
+    assert_eq!(evaluate::<f64>(&parse_expression("2 ^ 3 ^ 2")?), 512.0);
+    assert_eq!(evaluate::<f64>(&parse_expression("2 * 3 ^ 2")?), 18.0);
+    Ok(())
+}
+
+#[test]
+fn test_caret_as_xor() -> Result<()> {
+    let config = ParserConfig {
+        caret_is_xor: true,
+        ..ParserConfig::default()
+    };
+    let expr = parse_expression_with("6 ^ 3", &config)?;
+    assert_eq!(expr.as_binary_op().map(|(op, _, _)| op), Some('⊕'));
+    assert_eq!(evaluate::<f64>(&expr), 5.0);
+
+    let expr = parse_expression_with("(3 / 2) ^ 1", &config)?;
+    assert_eq!(
+        try_evaluate(&expr),
+        Err(EvalError::NonIntegerOperand {
+            op: '⊕',
+            value: 1.5
+        })
+    );
+    Ok(())
+}
+
+#[test]
+fn test_xor_to_infix_round_trip() -> Result<()> {
+    let config = ParserConfig {
+        caret_is_xor: true,
+        ..ParserConfig::default()
+    };
+    let expr = parse_expression_with("6 ^ 3 + 1", &config)?;
+    assert_eq!(expr.to_infix(), "((6 ⊕ 3) + 1)");
+    assert_eq!(parse_expression_with(&expr.to_infix(), &config)?, expr);
+    assert_eq!(parse_expression(&expr.to_infix())?, expr);
+    assert_eq!(parse_expression(&expr.to_infix_min())?, expr);
+    Ok(())
+}
+
+#[test]
+fn test_pretty_short_expression() -> Result<()> {
+    let expr = parse_expression("((1 + 2)) * 3 - 4")?;
+    assert_eq!(expr.pretty(80), expr.to_infix_min());
+    assert_eq!(expr.pretty(80), "(1 + 2) * 3 - 4");
+    Ok(())
+}
+
+#[test]
+fn test_pretty_wrapped_expression() -> Result<()> {
+    let expr = parse_expression("(1 + 2 + 3) * (4 + 5 + 6) - 7 * 8")?;
+    assert_eq!(expr.pretty(20), "(1 + 2 + 3)\n* (4 + 5 + 6)\n- 7 * 8");
+
+    let expr = parse_expression("2 * (10 + 20 + 30 + 40)")?;
+    assert_eq!(
+        expr.pretty(14),
+        "2\n* (\n      10 + 20\n      + 30\n      + 40\n  )"
+    );
+    assert_eq!(parse_expression(&expr.pretty(14))?, expr);
+    Ok(())
+}
+
+#[test]
+fn test_strict_rejects_numbers_without_operator() {
+    let config = ParserConfig {
+        strict: true,
+        ..ParserConfig::default()
+    };
+    let err = parse_expression_with("1 + 2 34", &config).unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::MissingOperator { token, position: 6 } if token == "34"
+    ));
+}
+
+#[test]
+fn test_strict_accepts_multi_digit_numbers() -> Result<()> {
+    let config = ParserConfig {
+        strict: true,
+        ..ParserConfig::default()
+    };
+    assert_eq!(parse_expression_with("23", &config)?, Expr::Number(23.0));
+    assert_eq!(
+        evaluate::<f64>(&parse_expression_with(" 2 + 3 ", &config)?),
+        5.0
+    );
+
+    // Без суворого режиму пробіли між цифрами ігноруються
+    assert_eq!(parse_expression("2 3")?, Expr::Number(23.0));
+    Ok(())
+}
+
+#[test]
+fn test_strict_rejects_trailing_tokens() {
+    let config = ParserConfig {
+        strict: true,
+        ..ParserConfig::default()
+    };
+    for (input, token) in [("2 )", ")"), ("1.5.3", "."), ("2 y", "y")] {
+        match parse_expression_with(input, &config) {
+            Err(ParseError::UnexpectedToken(found)) => assert_eq!(found, token, "{}", input),
+            other => panic!("{}: {:?}", input, other),
+        }
+    }
+
+    // Без суворого режиму зайві токени відкидаються
+    assert_eq!(parse_expression("2 )").unwrap(), Expr::Number(2.0));
+}
+
+#[test]
+fn test_strict_rejects_operands_without_operator() {
+    let config = ParserConfig {
+        strict: true,
+        ..ParserConfig::default()
+    };
+    for (input, token, position) in [
+        ("(1) (2)", "(", 4),
+        ("2 (3)", "(", 2),
+        ("(1) 2", "2", 4),
+        ("π * 2 (3)", "(", 6),
+    ] {
+        match parse_expression_with(input, &config) {
+            Err(ParseError::MissingOperator {
+                token: found,
+                position: at,
+            }) => assert_eq!((found.as_str(), at), (token, position), "{}", input),
+            other => panic!("{}: {:?}", input, other),
+        }
+    }
+    assert_eq!(
+        evaluate::<f64>(&parse_expression_with("max (1, 2) * (3)", &config).unwrap()),
+        6.0
+    );
+}
+
+#[test]
+fn test_count_leaves_and_internal() -> Result<()> {
+    let leaf = parse_expression("7")?;
+    assert_eq!(leaf.count_leaves(), 1);
+    assert_eq!(leaf.count_internal(), 0);
+    assert_eq!(leaf.node_count(), 1);
+
+    let balanced = parse_expression("(1 + 2) * (3 - 4)")?;
+    assert_eq!(balanced.count_leaves(), 4);
+    assert_eq!(balanced.count_internal(), 3);
+    assert_eq!(balanced.node_count(), 7);
+    Ok(())
+}
+
+#[test]
+fn test_limits_reject_large_input() {
+    let huge = "1 + ".repeat(1_000_000) + "1";
+    let err = parse_expression_with_limits(&huge, 1024, 256).unwrap_err();
+    assert!(matches!(err, ParseError::InputTooLarge));
+
+    let many_tokens = "1+1+1+1+1";
+    let err = parse_expression_with_limits(many_tokens, 1024, 8).unwrap_err();
+    assert!(matches!(err, ParseError::InputTooLarge));
+}
+
+#[test]
+fn test_limits_accept_normal_input() -> Result<()> {
+    let expr = parse_expression_with_limits("3 + 5 * (2 - 8) / 4", 1024, 256)?;
+    assert_eq!(expr, parse_expression("3 + 5 * (2 - 8) / 4")?);
+    Ok(())
+}
+
+#[test]
+fn test_eval_cache_hit() -> Result<()> {
+    let mut cache = EvalCache::new(4);
+    let first = cache.get_or_eval("3 + 5 * (2 - 8) / 4")?;
+    let second = cache.get_or_eval("3 + 5 * (2 - 8) / 4")?;
+    assert_eq!(first, second);
+    assert_eq!(cache.len(), 1);
+
+    assert!(cache.get_or_eval("2 +").is_err());
+    assert_eq!(cache.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn test_eval_cache_eviction() -> Result<()> {
+    let mut cache = EvalCache::new(2);
+    cache.get_or_eval("1 + 1")?;
+    cache.get_or_eval("2 + 2")?;
+    // Звернення оновлює "1 + 1", тож витісняється "2 + 2"
+    cache.get_or_eval("1 + 1")?;
+    cache.get_or_eval("3 + 3")?;
+
+    assert_eq!(cache.len(), 2);
+    assert!(cache.contains("1 + 1"));
+    assert!(!cache.contains("2 + 2"));
+    assert!(cache.contains("3 + 3"));
+    Ok(())
+}
+
+#[test]
+fn test_write_tree_with_values() -> Result<()> {
+    let expr = parse_expression("2 + 3 * 4")?;
+    let mut out = Vec::new();
+    expr.write_tree_with_values(&mut out)?;
+    assert_eq!(
+        String::from_utf8(out)?,
+        "\nExpression: (2 + (3 * 4))\n\n\
+         └── + = 14\n    \
+         ├── 2\n    \
+         └── * = 12\n        \
+         ├── 3\n        \
+         └── 4\n\n"
+    );
+    Ok(())
+}
+
+#[cfg(feature = "units")]
+#[test]
+fn test_units_compatible_addition() -> Result<()> {
+    use tree_parser::evaluate_units;
+
+    let expr = parse_expression("3m + 2m")?;
+    assert_eq!(
+        expr.as_binary_op().map(|(_, left, _)| left.clone()),
+        Some(Expr::Quantity {
+            value: 3.0,
+            unit: "m".to_string()
+        })
+    );
+    assert_eq!(evaluate_units(&expr)?.to_string(), "5m");
+
+    let speed = evaluate_units(&parse_expression("(3m + 3m) / 2s")?)?;
+    assert_eq!(speed.to_string(), "3m*s^-1");
+    Ok(())
+}
+
+#[cfg(feature = "units")]
+#[test]
+fn test_units_incompatible_addition() -> Result<()> {
+    use tree_parser::evaluate_units;
+
+    let expr = parse_expression("3m + 2s")?;
+    assert_eq!(
+        evaluate_units(&expr),
+        Err(EvalError::UnitMismatch {
+            left: "m".to_string(),
+            right: "s".to_string()
+        })
+    );
+    Ok(())
+}
+
+#[cfg(feature = "units")]
+#[test]
+fn test_units_rejected_without_units_evaluator() -> Result<()> {
+    let expr = parse_expression("3m + 2m")?;
+    let error = EvalError::UnexpectedUnit("3m".to_string());
+    assert_eq!(try_evaluate(&expr), Err(error.clone()));
+    assert_eq!(evaluate_bounded(&expr, 1e3), Err(error.clone()));
+    assert_eq!(expr.to_bytecode(&[]), Err(error));
+    Ok(())
+}
+
+#[test]
+fn test_max_and_min_number() -> Result<()> {
+    let expr = parse_expression("2 + 30 * 4")?;
+    assert_eq!(expr.max_number(), Some(30.0));
+    assert_eq!(expr.min_number(), Some(2.0));
+
+    let single = parse_expression("7")?;
+    assert_eq!(single.max_number(), Some(7.0));
+    assert_eq!(single.min_number(), Some(7.0));
+
+    let negative = Expr::BinaryOp {
+        op: '*',
+        left: Box::new(Expr::Number(-5.0)),
+        right: Box::new(Expr::Number(3.0)),
+    };
+    assert_eq!(negative.min_number(), Some(-5.0));
+    assert_eq!(negative.max_number(), Some(3.0));
+    Ok(())
+}
+
+#[test]
+fn test_max_function() -> Result<()> {
+    let expr = parse_expression("max(2, 3 * 4)")?;
+    assert_eq!(
+        expr,
+        Expr::Func {
+            name: "max".to_string(),
+            args: vec![
+                Expr::Number(2.0),
+                Expr::BinaryOp {
+                    op: '*',
+                    left: Box::new(Expr::Number(3.0)),
+                    right: Box::new(Expr::Number(4.0)),
+                },
+            ],
+        }
+    );
+    assert_eq!(evaluate::<f64>(&expr), 12.0);
+    assert_eq!(expr.to_infix(), "max(2, (3 * 4))");
+    Ok(())
+}
+
+#[test]
+fn test_min_function() -> Result<()> {
+    assert_eq!(evaluate::<f64>(&parse_expression("min(5, 1)")?), 1.0);
+    assert_eq!(
+        evaluate::<f64>(&parse_expression("1 + min(max(1, 2), 3) * 2")?),
+        5.0
+    );
+    Ok(())
+}
+
+#[test]
+fn test_function_wrong_arity() {
+    let err = parse_expression("max(1, 2, 3)").unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::WrongArity { name, expected: 2, found: 3 } if name == "max"
+    ));
+
+    let unknown = parse_expression("foo(1)").unwrap();
+    assert_eq!(
+        try_evaluate(&unknown),
+        Err(EvalError::UnknownFunction("foo".to_string()))
+    );
+}
+
+#[test]
+fn test_is_balanced() -> Result<()> {
+    let balanced = parse_expression("((1 + 2) * (3 + 4)) - ((5 + 6) / (7 + 8))")?;
+    assert_eq!(balanced.depth(), 4);
+    assert!(balanced.is_balanced(0));
+
+    let right_leaning = parse_expression("1 ^ 2 ^ 3 ^ 4 ^ 5")?;
+    assert_eq!(right_leaning.depth(), 5);
+    assert!(!right_leaning.is_balanced(1));
+    assert!(right_leaning.is_balanced(3));
+    Ok(())
+}
+
+#[test]
+fn test_spans_of_simple_sum() {
+    let (expr, spans) = parse_expression_spanned("2 + 3").unwrap();
+    assert_eq!(expr, parse_expression("2 + 3").unwrap());
+    assert_eq!(spans.span, Span { start: 0, end: 5 });
+    assert_eq!(spans.children.len(), 2);
+    assert_eq!(spans.children[0].span, Span { start: 0, end: 1 });
+    assert_eq!(spans.children[1].span, Span { start: 4, end: 5 });
+    assert!(spans.children[0].children.is_empty());
+}
+
+#[test]
+fn test_spans_include_parentheses_and_calls() {
+    let input = "(12 + 3) * max(1, 2)";
+    let (_, spans) = parse_expression_spanned(input).unwrap();
+    assert_eq!(spans.span, Span { start: 0, end: 20 });
+    let paren = spans.children[0].span;
+    assert_eq!(&input[paren.start..paren.end], "(12 + 3)");
+    let call = &spans.children[1];
+    assert_eq!(&input[call.span.start..call.span.end], "max(1, 2)");
+    assert_eq!(call.children[1].span, Span { start: 18, end: 19 });
+}
+
+/// Перевіряє, що `spans` має ту саму форму, що й `expr`, а діапазон кожного
+/// вузла лежить у діапазоні батька
+fn assert_spans_aligned(expr: &Expr, spans: &SpanTree, input: &str) {
+    let children: Vec<&Expr> = match expr {
+        Expr::Neg(operand) => vec![operand],
+        Expr::BinaryOp { left, right, .. } => vec![left, right],
+        Expr::Func { args, .. } => args.iter().collect(),
+        Expr::Let { value, body, .. } => vec![value, body],
+        _ => Vec::new(),
+    };
+    assert_eq!(
+        children.len(),
+        spans.children.len(),
+        "{}: {:?}",
+        input,
+        expr
+    );
+    assert!(spans.span.end <= input.len(), "{}", input);
+    for (child, child_spans) in children.into_iter().zip(&spans.children) {
+        assert!(spans.span.start <= child_spans.span.start, "{}", input);
+        assert!(child_spans.span.end <= spans.span.end, "{}", input);
+        assert_spans_aligned(child, child_spans, input);
+    }
+}
+
+#[test]
+fn test_spans_stay_aligned_with_desugared_tree() {
+    for input in [
+        "2 + 3 * 4 - 5",
+        "-(1 + 2) ^ 3",
+        "2 ** 3 ** 2",
+        "max(1, 2 - x) * -y",
+        "let x = 2 in x * x",
+        "100 + 10%",
+        "π * 2 / 4",
+    ] {
+        let (expr, spans) = parse_expression_spanned(input).unwrap();
+        assert_eq!(expr, parse_expression(input).unwrap(), "{}", input);
+        assert_spans_aligned(&expr, &spans, input);
+    }
+}
+
+#[test]
+fn test_reduce_steps_folds_innermost_first() {
+    let expr = parse_expression("2 + 3 * 4").unwrap();
+    let steps = expr.reduce_steps();
+    assert_eq!(steps.len(), 2);
+    assert_eq!(steps[0], parse_expression("2 + 12").unwrap());
+    assert_eq!(steps[1], Expr::Number(14.0));
+}
+
+#[test]
+fn test_reduce_steps_left_to_right() {
+    let expr = parse_expression("(1 + 2) * (3 + 4)").unwrap();
+    let steps: Vec<String> = expr.reduce_steps().iter().map(Expr::to_infix).collect();
+    assert_eq!(steps, ["(3 * (3 + 4))", "(3 * 7)", "21"]);
+    assert!(Expr::Number(5.0).reduce_steps().is_empty());
+}
+
+#[test]
+fn test_operator_traits_build_parsed_tree() {
+    let two = Expr::Number(2.0);
+    let three = Expr::Number(3.0);
+    let four = Expr::Number(4.0);
+    let expr = (&two - &three) / (two.clone() + three.clone() * four.clone());
+    assert_eq!(expr, parse_expression("(2 - 3) / (2 + 3 * 4)").unwrap());
+    assert_eq!(-(two * -four), parse_expression("-(2 * -4)").unwrap());
+}
+
+#[test]
+fn test_unary_minus() {
+    let expr = parse_expression("-2 ^ 2").unwrap();
+    assert_eq!(evaluate::<f64>(&expr), -4.0);
+    assert_eq!(expr.to_infix(), "(-(2 ^ 2))");
+    assert_eq!(evaluate::<f64>(&parse_expression("2 - -3").unwrap()), 5.0);
+    assert_eq!(evaluate::<f64>(&parse_expression("2 ^ -1").unwrap()), 0.5);
+
+    let neg_base = parse_expression("(-2) ^ 2").unwrap();
+    assert_eq!(neg_base.to_infix_min(), "(-2) ^ 2");
+    assert_eq!(evaluate::<f64>(&neg_base), 4.0);
+}
+
+#[test]
+fn test_parse_and_eval_matches_evaluate() {
+    for input in [
+        "3 + 5 * (2 - 8) / 4",
+        "1 < 2 < 3",
+        "-2 ^ 2",
+        "min(4, 2 * 3)",
+    ] {
+        let expected = evaluate(&parse_expression(input).unwrap());
+        assert_eq!(parse_and_eval(input).unwrap(), expected);
+    }
+    let err = parse_and_eval("foo(1)").unwrap_err();
+    assert!(matches!(err, ParseEvalError::Eval(EvalError::UnknownFunction(name)) if name == "foo"));
+    assert!(matches!(
+        parse_and_eval("(2"),
+        Err(ParseEvalError::Parse(ParseError::MissingClosingParenthesis))
+    ));
+}
+
+#[cfg(feature = "complex")]
+#[test]
+fn test_complex_imaginary_unit_squared() {
+    use tree_parser::evaluate_complex;
+
+    let expr = parse_expression("i * i").unwrap();
+    assert_eq!(evaluate_complex(&expr), (-1.0, 0.0));
+
+    let (re, im) = evaluate_complex(&parse_expression("(-1) ^ (1 / 2)").unwrap());
+    assert!(re.abs() < 1e-12 && (im - 1.0).abs() < 1e-12);
+}
+
+#[cfg(feature = "complex")]
+#[test]
+fn test_complex_sqrt_of_negative_one_is_i() {
+    use tree_parser::try_evaluate_complex;
+
+    let eval = |input: &str| try_evaluate_complex(&parse_expression(input).unwrap());
+    assert_eq!(eval("sqrt(-1)"), Ok((0.0, 1.0)));
+    assert_eq!(eval("sqrt(-1) - i"), Ok((0.0, 0.0)));
+    assert_eq!(eval("sqrt(2 * i)"), Ok((1.0, 1.0)));
+    assert_eq!(eval("sqrt(9)"), Ok((3.0, 0.0)));
+    assert_eq!(eval("max(i, 1)"), Err(EvalError::NotReal));
+}
+
+#[cfg(feature = "complex")]
+#[test]
+fn test_complex_real_expression_has_zero_imaginary_part() {
+    use tree_parser::evaluate_complex;
+
+    let expr = parse_expression("3 + 5 * (2 - 8) / 4").unwrap();
+    assert_eq!(evaluate_complex(&expr), (evaluate(&expr), 0.0));
+    assert_eq!(
+        try_evaluate(&parse_expression("2 * i").unwrap()),
+        Err(EvalError::NotReal)
+    );
+}
+
+#[test]
+fn test_bytecode_matches_evaluate() {
+    for input in [
+        "3 + 5 * (2 - 8) / 4",
+        "2 ^ 3 ^ 2",
+        "-(1 + 2) * 4",
+        "max(1, 2) < 3 < 4",
+    ] {
+        let expr = parse_expression(input).unwrap();
+        let code = expr.to_bytecode(&[]).unwrap();
+        assert_eq!(run_bytecode(&code, &[]), evaluate(&expr), "{}", input);
+    }
+}
+
+#[test]
+fn test_bytecode_with_variables() {
+    let expr = parse_expression("x * x - y / 2").unwrap();
+    let code = expr.to_bytecode(&["x", "y"]).unwrap();
+    for (x, y) in [(1.0, 2.0), (3.0, -4.0), (0.5, 0.0)] {
+        let vars = HashMap::from([("x".to_string(), x), ("y".to_string(), y)]);
+        assert_eq!(
+            run_bytecode(&code, &[x, y]),
+            evaluate_with(&expr, &vars).unwrap()
+        );
+    }
+    assert_eq!(
+        expr.to_bytecode(&["x"]),
+        Err(EvalError::UndefinedVariable("y".to_string()))
+    );
+}
+
+#[test]
+fn test_try_run_bytecode_malformed() {
+    use tree_parser::Instruction;
+
+    let code = parse_expression("x + 1")
+        .unwrap()
+        .to_bytecode(&["x"])
+        .unwrap();
+    assert_eq!(try_run_bytecode(&code, &[2.0]), Ok(3.0));
+    assert_eq!(
+        try_run_bytecode(&code, &[]),
+        Err(EvalError::InvalidBytecode(0))
+    );
+    assert_eq!(
+        try_run_bytecode(&[Instruction::Push(1.0), Instruction::Add], &[]),
+        Err(EvalError::InvalidBytecode(1))
+    );
+    assert_eq!(
+        try_run_bytecode(&[Instruction::LoadLocal(0)], &[]),
+        Err(EvalError::InvalidBytecode(0))
+    );
+    assert_eq!(
+        try_run_bytecode(&[], &[]),
+        Err(EvalError::InvalidBytecode(0))
+    );
+}
+
+#[test]
+fn test_division_not_allowed() {
+    let config = ParserConfig {
+        allowed_operators: Some(vec!['+', '-', '*']),
+        ..ParserConfig::default()
+    };
+    let err = parse_expression_with("8 / 2", &config).unwrap_err();
+    assert!(matches!(err, ParseError::OperatorNotAllowed('/')));
+    assert!(parse_expression_with("8 * 2 - -1", &config).is_ok());
+
+    let expr = parse_expression_with("8 / 2", &ParserConfig::default()).unwrap();
+    assert_eq!(evaluate::<f64>(&expr), 4.0);
+}
+
+#[test]
+fn test_decimal_literals() {
+    assert_eq!(evaluate::<f64>(&parse_expression("1.5 * 4").unwrap()), 6.0);
+    assert!(
+        matches!(parse_expression("2 + .5"), Err(ParseError::UnexpectedToken(tok)) if tok == ".")
+    );
+}
+
+#[cfg(feature = "decimal")]
+#[test]
+fn test_decimal_evaluation_is_exact() {
+    use tree_parser::{Decimal, evaluate_decimal};
+
+    let expr = parse_expression("0.1 + 0.2").unwrap();
+    assert_ne!(evaluate::<f64>(&expr), 0.3);
+    assert_eq!(evaluate_decimal(&expr), Ok(Decimal::new(3, 1)));
+
+    let comparison = parse_expression("0.1 + 0.2 > 0.3").unwrap();
+    assert_eq!(evaluate::<f64>(&comparison), 1.0);
+    assert_eq!(evaluate_decimal(&comparison), Ok(Decimal::ZERO));
+
+    let by_zero = parse_expression("1 / (0.5 - 0.5)").unwrap();
+    assert_eq!(evaluate_decimal(&by_zero), Err(EvalError::DecimalOverflow));
+}
+
+#[test]
+fn test_root_operator_predicates() {
+    assert_eq!(Expr::Number(7.0).root_operator(), None);
+    assert_eq!(parse_expression("-(2 - 3)").unwrap().root_operator(), None);
+
+    for (input, op) in [
+        ("1 + 2 * 3", '+'),
+        ("1 - 2", '-'),
+        ("(1 + 2) * 3", '*'),
+        ("6 / 3", '/'),
+        ("2 ^ 3", '^'),
+    ] {
+        assert_eq!(
+            parse_expression(input).unwrap().root_operator(),
+            Some(op),
+            "{}",
+            input
+        );
+    }
+    assert!(parse_expression("1 + 2").unwrap().is_addition());
+    assert!(parse_expression("1 - 2").unwrap().is_subtraction());
+    assert!(parse_expression("1 * 2 + 3").unwrap().is_addition());
+    assert!(!parse_expression("1 * 2 + 3").unwrap().is_multiplication());
+    assert!(parse_expression("(1 + 2) / 3").unwrap().is_division());
+}
+
+#[test]
+fn test_fuzz_regressions() {
+    assert!(matches!(parse_expression("."), Err(ParseError::UnexpectedToken(tok)) if tok == "."));
+    #[cfg(not(feature = "units"))]
+    for (input, token, position) in [("1e", "1e", 0), ("2 * 1.5E-", "1.5E-", 4)] {
+        match parse_expression(input) {
+            Err(ParseError::MalformedExponent {
+                token: found,
+                position: at,
+            }) => assert_eq!((found.as_str(), at), (token, position), "{}", input),
+            other => panic!("{}: {:?}", input, other),
+        }
+    }
+    // З одиницями `e` після числа — назва одиниці
+    #[cfg(feature = "units")]
+    assert!(matches!(
+        parse_expression("1e"),
+        Ok(Expr::Quantity { value: 1.0, ref unit }) if unit == "e"
+    ));
+
+    let long = "9".repeat(400);
+    let err = parse_expression(&format!("1 + {}", long)).unwrap_err();
+    assert!(matches!(err, ParseError::NumberOutOfRange(number) if number == long));
+    assert!(parse_and_eval(&long).is_err());
+}
+
+#[test]
+fn test_evaluate_over_matches_evaluate_with() {
+    let expr = parse_expression("x * x - 2 * x + max(x, 0)").unwrap();
+    let values = [-2.0, -0.5, 0.0, 1.0, 3.0];
+    let results = evaluate_over(&expr, "x", &values);
+    assert_eq!(results.len(), values.len());
+    for (value, result) in values.iter().zip(&results) {
+        let vars = HashMap::from([("x".to_string(), *value)]);
+        assert_eq!(*result, evaluate_with(&expr, &vars).unwrap());
+    }
+    assert_eq!(
+        evaluate_over(&parse_expression("x * x").unwrap(), "x", &[1.0, 2.0, 3.0]),
+        [1.0, 4.0, 9.0]
+    );
+}
+
+#[test]
+fn test_let_binding() {
+    let expr = parse_expression("let x = 2 + 3 in x * x").unwrap();
+    assert!(matches!(&expr, Expr::Let { name, .. } if name == "x"));
+    assert_eq!(evaluate::<f64>(&expr), 25.0);
+    assert_eq!(parse_and_eval("let x = 2 + 3 in x * x").unwrap(), 25.0);
+    assert_eq!(run_bytecode(&expr.to_bytecode(&[]).unwrap(), &[]), 25.0);
+    assert_eq!(ExprArena::from_expr(&expr).evaluate(), 25.0);
+    assert_eq!(parse_expression(&expr.to_infix()).unwrap(), expr);
+    assert!(matches!(
+        parse_expression("let in = 2 in 3"),
+        Err(ParseError::UnexpectedToken(tok)) if tok == "in"
+    ));
+}
+
+#[test]
+fn test_let_shadowing_is_lexical() {
+    let input = "let x = 2 in (let x = x * 10 in x + 1) + x";
+    let expr = parse_expression(input).unwrap();
+    assert_eq!(evaluate::<f64>(&expr), 23.0);
+    assert_eq!(parse_and_eval(input).unwrap(), 23.0);
+    assert_eq!(run_bytecode(&expr.to_bytecode(&[]).unwrap(), &[]), 23.0);
+    assert_eq!(ExprArena::from_expr(&expr).evaluate(), 23.0);
+    assert_eq!(expr.reduce_steps().last(), Some(&Expr::Number(23.0)));
+
+    let outer = HashMap::from([("x".to_string(), 100.0)]);
+    let inner = parse_expression("(let x = 1 in x) + x").unwrap();
+    assert_eq!(evaluate_with(&inner, &outer), Ok(101.0));
+}
+
+#[test]
+fn test_to_fraction() {
+    assert_eq!(to_fraction(0.75), Some((3, 4)));
+    assert_eq!(to_fraction(-1.0 / 3.0), Some((-1, 3)));
+    assert_eq!(to_fraction(0.1 + 0.2), Some((3, 10)));
+    assert_eq!(to_fraction(5.0), Some((5, 1)));
+    assert_eq!(to_fraction(std::f64::consts::PI), None);
+    assert_eq!(to_fraction(f64::NAN), None);
+}
+
+#[test]
+fn test_canonicalize_commutative() {
+    let a = parse_expression("2 + 3").unwrap();
+    let b = parse_expression("3 + 2").unwrap();
+    assert_ne!(a, b);
+    assert_eq!(a.canonicalize(), b.canonicalize());
+
+    let a = parse_expression("(x + 1) * (y * 2) + 4").unwrap();
+    let b = parse_expression("4 + 2 * (1 + x) * y").unwrap();
+    assert_eq!(a.canonicalize(), b.canonicalize());
+}
+
+#[test]
+fn test_canonicalize_keeps_non_commutative() {
+    let a = parse_expression("3 - 2").unwrap();
+    let b = parse_expression("2 - 3").unwrap();
+    assert_ne!(a.canonicalize(), b.canonicalize());
+    assert_eq!(a.canonicalize(), a);
+    assert_eq!(
+        parse_expression("6 / (b + a)")
+            .unwrap()
+            .canonicalize()
+            .to_infix(),
+        "(6 / (a + b))"
+    );
+}
+
+#[test]
+fn test_reparse_reuses_unaffected_tokens() {
+    let input = "(1 + 2) * 3 - max(4, 5) / 6";
+    let mut context = ReparseContext::new(input);
+    assert_eq!(context.parse().unwrap(), parse_expression(input).unwrap());
+
+    // Правка в кінці не чіпає дужок на початку
+    let expr = context.edit(26..27, "60").unwrap();
+    assert_eq!(context.input(), "(1 + 2) * 3 - max(4, 5) / 60");
+    assert_eq!(expr, parse_expression(context.input()).unwrap());
+    assert!(context.reused_tokens() >= 13);
+
+    // Цифри впритул до правки зливаються з сусіднім числом
+    let expr = context.edit(2..2, ".5").unwrap();
+    assert_eq!(context.input(), "(1.5 + 2) * 3 - max(4, 5) / 60");
+    assert_eq!(expr, parse_expression(context.input()).unwrap());
+}
+
+#[test]
+fn test_reparse_recovers_after_error() {
+    let config = ParserConfig {
+        strict: true,
+        ..ParserConfig::default()
+    };
+    let mut context = ReparseContext::with_config("2 + 3", config);
+    assert!(matches!(
+        context.edit(2..3, ""),
+        Err(ParseError::MissingOperator { .. })
+    ));
+    let expr = context.edit(2..2, "*").unwrap();
+    assert_eq!(expr, parse_expression("2 * 3").unwrap());
+}
+
+#[test]
+fn test_rounding_functions() {
+    let eval = |input: &str| evaluate::<f64>(&parse_expression(input).unwrap());
+    assert_eq!(eval("abs(0 - 3.5)"), 3.5);
+    assert_eq!(eval("abs(2)"), 2.0);
+    assert_eq!(eval("floor(2.7)"), 2.0);
+    assert_eq!(eval("floor(0 - 2.2)"), -3.0);
+    assert_eq!(eval("ceil(2.2)"), 3.0);
+    assert_eq!(eval("ceil(0 - 2.7)"), -2.0);
+    assert_eq!(eval("round(2.5)"), 3.0);
+    assert_eq!(eval("round(-2.5)"), -3.0);
+    assert_eq!(eval("round(2.4)"), 2.0);
+    assert!(matches!(
+        parse_expression("abs(1, 2)"),
+        Err(ParseError::WrongArity {
+            expected: 1,
+            found: 2,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_interval_abs() {
+    // 1 - 2 з похибкою ±1 лежить у [-3, 1], тож abs — у [0, 3]
+    let expr = parse_expression("abs(1 - 2)").unwrap();
+    assert_eq!(
+        evaluate_interval_with(&expr, &|n| (n - 1.0, n + 1.0)),
+        (0.0, 3.0)
+    );
+    let expr = parse_expression("abs(0 - 5)").unwrap();
+    assert_eq!(
+        evaluate_interval_with(&expr, &|n| (n - 1.0, n + 1.0)),
+        (3.0, 7.0)
+    );
+}
+
+#[cfg(feature = "decimal")]
+#[test]
+fn test_decimal_rounding_functions() {
+    use tree_parser::{Decimal, evaluate_decimal};
+
+    let eval = |input: &str| evaluate_decimal(&parse_expression(input).unwrap());
+    assert_eq!(eval("round(2.5)"), Ok(Decimal::new(3, 0)));
+    assert_eq!(eval("round(-2.5)"), Ok(Decimal::new(-3, 0)));
+    assert_eq!(eval("floor(0.1 + 0.2)"), Ok(Decimal::ZERO));
+    assert_eq!(eval("abs(0.1 - 0.3)"), Ok(Decimal::new(2, 1)));
+}
+
+#[test]
+fn test_eval_saturating_i64() {
+    let eval = |input: &str| eval_saturating_i64(&parse_expression(input).unwrap());
+    assert_eq!(eval("2 + 3 * 4"), 14);
+    assert_eq!(eval("7 / 2"), 3);
+    assert_eq!(eval("-7 / 2"), -3);
+    assert_eq!(eval("2.5 + 1.4"), 4);
+    assert_eq!(eval("max(2, 3) - abs(-10)"), -7);
+}
+
+#[test]
+fn test_eval_saturating_i64_saturates() {
+    let eval = |input: &str| eval_saturating_i64(&parse_expression(input).unwrap());
+    assert_eq!(eval("9223372036854775807 + 1"), i64::MAX);
+    assert_eq!(eval("-9223372036854775807 - 10"), i64::MIN);
+    assert_eq!(eval("3037000500 * 3037000500"), i64::MAX);
+    assert_eq!(eval("2 ^ 100"), i64::MAX);
+    assert_eq!(eval("-5 / 0"), i64::MIN);
+    assert_eq!(eval("100000000000000000000000"), i64::MAX);
+}
+
+#[test]
+fn test_evaluate_precision_f32_f64() {
+    let expr = parse_expression("1 / 3").unwrap();
+    let single: f32 = evaluate(&expr);
+    let double: f64 = evaluate(&expr);
+    assert_eq!(single, 1.0_f32 / 3.0);
+    assert_eq!(double, 1.0 / 3.0);
+    assert_ne!(single as f64, double);
+
+    // 2^24 + 1 не вміщується в мантису f32
+    let expr = parse_expression("16777216 + 1").unwrap();
+    assert_eq!(evaluate::<f32>(&expr), 16777216.0);
+    assert_eq!(evaluate::<f64>(&expr), 16777217.0);
+}
+
+#[test]
+fn test_evaluate_generic_fallback_ops() {
+    let expr = parse_expression("let x = 2 in max(x ^ 3, -x) < 9").unwrap();
+    assert_eq!(evaluate::<f32>(&expr), 1.0);
+    assert_eq!(evaluate::<f64>(&expr), 1.0);
+}
+
+#[cfg(feature = "decimal")]
+#[test]
+fn test_evaluate_generic_decimal() {
+    use tree_parser::Decimal;
+
+    let expr = parse_expression("0.1 + 0.2").unwrap();
+    assert_eq!(evaluate::<Decimal>(&expr), Decimal::new(3, 1));
+}
+
+/// Writer, що завжди повертає помилку, як закритий канал
+struct BrokenPipe;
+
+impl std::io::Write for BrokenPipe {
+    fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_write_tree_propagates_io_errors() {
+    let expr = parse_expression("2 + 3 * 4").unwrap();
+    let err = expr.write_tree(&mut BrokenPipe).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+    assert!(expr.write_tree_with_values(&mut BrokenPipe).is_err());
+}
+
+#[test]
+fn test_simplify_double_negation() {
+    let simplify = |input: &str| parse_expression(input).unwrap().simplify_double_negation();
+    assert_eq!(simplify("- - x"), Expr::Variable("x".to_string()));
+    assert_eq!(simplify("-(-(2))"), Expr::Number(2.0));
+    assert_eq!(
+        simplify("- - - x"),
+        Expr::Neg(Box::new(Expr::Variable("x".to_string())))
+    );
+    assert_eq!(simplify("- - - - x"), Expr::Variable("x".to_string()));
+    assert_eq!(
+        simplify("max(- - 1, 2) ^ - - 3").to_infix(),
+        "(max(1, 2) ^ 3)"
+    );
+}
+
+#[test]
+fn test_prefix_postfix_sexpr() {
+    let expr = parse_expression("let x = -2 in max(x, 1) ^ 2").unwrap();
+    assert_eq!(expr.to_prefix(), "let x neg 2 ^ max x 1 2");
+    assert_eq!(expr.to_postfix(), "2 neg x 1 max 2 ^ let x");
+    assert_eq!(expr.to_sexpr(), "(let x (neg 2) (^ (max x 1) 2))");
+}
+
+#[test]
+fn test_number_out_of_range() {
+    let huge = "1".repeat(400);
+    assert!(matches!(
+        parse_expression(&huge),
+        Err(ParseError::NumberOutOfRange(number)) if number == huge
+    ));
+
+    // 1e300 ще вміщується у f64
+    let large = format!("1{}", "0".repeat(300));
+    let expr = parse_expression(&format!("{} / 10", large)).unwrap();
+    assert_eq!(evaluate::<f64>(&expr), 1e299);
+    assert!(parse_expression(&format!("{}.5", "9".repeat(308))).is_ok());
+}
+
+#[test]
+fn test_parse_error_with_context() {
+    let source = "100 + 200 * (3 - ) + max(4, 5) / 6";
+    let config = ParserConfig {
+        token_positions: true,
+        ..ParserConfig::default()
+    };
+    let err = parse_expression_with(source, &config).unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::UnexpectedTokenAt { position: 17, .. }
+    ));
+    assert_eq!(
+        err.with_context(source),
+        "Unexpected token: )\n  ... 00 * (3 - >)< + max(4, ..."
+    );
+
+    let err = parse_expression("let x =\n").unwrap_err();
+    assert_eq!(
+        err.with_context("let x =\n"),
+        "Unexpected end of input\n  let x =><"
+    );
+
+    let err = parse_expression("(2 + 3").unwrap_err();
+    assert_eq!(err.with_context("(2 + 3"), err.to_string());
+}
+
+#[test]
+fn test_evaluate_exact() {
+    let exact = |input: &str| evaluate_exact(&parse_expression(input).unwrap());
+    assert_eq!(exact("6 / 2"), (3.0, true));
+    assert_eq!(exact("let x = 10 in x * x - max(3, 4) ^ 2"), (84.0, true));
+    assert_eq!(exact("7 / 2"), (3.5, false));
+    // Дробовий проміжний результат псує точність, навіть якщо відповідь ціла
+    assert_eq!(exact("7 / 2 * 2"), (7.0, false));
+    assert_eq!(exact("0.5 + 0.5"), (1.0, false));
+    assert!(!exact("2 ^ 60 + 1").1);
+}
+
+#[test]
+fn test_free_variables() {
+    let expr = parse_expression("let x = y in x * z + max(x, y) + w").unwrap();
+    assert_eq!(expr.free_variables(), ["w", "y", "z"]);
+    assert!(
+        parse_expression("2 + 3")
+            .unwrap()
+            .free_variables()
+            .is_empty()
+    );
+}
+
+#[test]
+fn test_abstract_numbers() {
+    let (template, constants) = parse_expression("2 + 3 * 4").unwrap().abstract_numbers();
+    assert_eq!(template, parse_expression("a0 + a1 * a2").unwrap());
+    assert_eq!(constants, [2.0, 3.0, 4.0]);
+
+    // Однакова структура з іншими константами дає той самий шаблон
+    let (other, constants) = parse_expression("5 + 6 * 7").unwrap().abstract_numbers();
+    assert_eq!(other, template);
+    assert_eq!(constants, [5.0, 6.0, 7.0]);
+
+    // Наявні назви не перекриваються
+    let (template, _) = parse_expression("a0 * 2").unwrap().abstract_numbers();
+    assert_eq!(template.to_infix_min(), "a0 * a1");
+}
+
+/// Збалансоване дерево глибини `depth` з різними операторами й літералами
+#[cfg(feature = "parallel")]
+fn balanced_tree(depth: usize, seed: &mut u32) -> Expr {
+    if depth == 0 {
+        *seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        return Expr::Number(f64::from(*seed % 97 + 1) / 8.0);
+    }
+    let left = balanced_tree(depth - 1, seed);
+    let right = balanced_tree(depth - 1, seed);
+    match depth % 3 {
+        0 => left + right,
+        1 => left - right,
+        _ => Expr::Func {
+            name: "max".to_string(),
+            args: vec![left, right],
+        },
+    }
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn test_evaluate_parallel_matches_sequential() {
+    use tree_parser::evaluate_parallel;
+
+    let expr = balanced_tree(16, &mut 7);
+    assert!(expr.node_count() > 100_000);
+    let sequential: f64 = evaluate(&expr);
+    assert!(sequential.is_finite());
+    assert_eq!(evaluate_parallel(&expr).to_bits(), sequential.to_bits());
+
+    let wrapped = Expr::Let {
+        name: "x".to_string(),
+        value: Box::new(expr.clone()),
+        body: Box::new(Expr::Func {
+            name: "max".to_string(),
+            args: vec![Expr::Neg(Box::new(Expr::Variable("x".to_string()))), expr],
+        }),
+    };
+    let sequential: f64 = evaluate(&wrapped);
+    assert_eq!(evaluate_parallel(&wrapped).to_bits(), sequential.to_bits());
+
+    let small = parse_expression("let x = 3 in x * (x - 1)").unwrap();
+    assert_eq!(evaluate_parallel(&small), 6.0);
+}
+
+#[test]
+fn test_parse_error_suggestion() {
+    let err = parse_expression("2 x 3").unwrap_err();
+    assert!(matches!(&err, ParseError::UnexpectedToken(token) if token == "x"));
+    assert_eq!(err.suggestion().as_deref(), Some("did you mean '*'?"));
+
+    let err = parse_expression("2 plus 3").unwrap_err();
+    assert_eq!(err.suggestion().as_deref(), Some("did you mean '+'?"));
+    let config = ParserConfig {
+        strict: true,
+        ..ParserConfig::default()
+    };
+    let err = parse_expression_with("2 times 3", &config).unwrap_err();
+    assert_eq!(err.suggestion().as_deref(), Some("did you mean '*'?"));
+
+    // Інші зайві токени й далі відкидаються
+    assert_eq!(parse_expression("2 @ 3").unwrap(), Expr::Number(2.0));
+    let err = parse_expression("2 + )").unwrap_err();
+    assert_eq!(err.suggestion(), None);
+    assert_eq!(parse_expression("(2 + 3").unwrap_err().suggestion(), None);
+}
+
+#[test]
+fn test_diff_tree() {
+    let a = parse_expression("2 + 3").unwrap();
+    let b = parse_expression("2 + 4").unwrap();
+    let diff = a.diff_tree_with(&b, false);
+    assert_eq!(diff, "  └── +\n      ├── 2\n~     └── 3 → 4\n");
+    assert!(!diff.contains('\x1b'));
+    assert!(
+        a.diff_tree_with(&b, true)
+            .contains("\x1b[31m3\x1b[0m → \x1b[32m4\x1b[0m")
+    );
+
+    // Однакові дерева не мають позначок
+    assert!(!a.diff_tree_with(&a, false).contains('~'));
+    let c = parse_expression("2 + max(3, 1)").unwrap();
+    assert!(
+        a.diff_tree_with(&c, false)
+            .contains("~     └── 3 → max(3, 1)")
+    );
+}
+
+#[test]
+fn test_scientific_notation_and_e_constant() {
+    let eval = |input: &str| evaluate::<f64>(&parse_expression(input).unwrap());
+    assert_eq!(parse_expression("2e3").unwrap(), Expr::Number(2000.0));
+    assert_eq!(eval("1.5e-3 + 2E+2"), 200.0015);
+    assert_eq!(eval("2 * e"), 2.0 * std::f64::consts::E);
+    assert_eq!(eval("e + 1"), std::f64::consts::E + 1.0);
+    // `e` після нецифри чи без цифр за ним — константа
+    assert_eq!(eval("(2)*e"), 2.0 * std::f64::consts::E);
+    assert_eq!(eval("e - 3"), std::f64::consts::E - 3.0);
+
+    assert!(matches!(
+        parse_expression("1e999"),
+        Err(ParseError::NumberOutOfRange(number)) if number == "1e999"
+    ));
+    assert!(matches!(
+        parse_expression("let e = 2 in e"),
+        Err(ParseError::UnexpectedToken(token)) if token == "e"
+    ));
+}
+
+#[cfg(feature = "units")]
+#[test]
+fn test_scientific_notation_with_units() {
+    assert_eq!(
+        parse_expression("2e3m").unwrap(),
+        Expr::Quantity {
+            value: 2000.0,
+            unit: "m".to_string(),
+        }
+    );
+}
+
+#[test]
+fn test_eval_fixed_point() {
+    let expr = parse_expression("(x + 2 / x) / 2").unwrap();
+    assert_eq!(eval_fixed_point(&expr, "x", 1.0, 0), 1.0);
+    assert_eq!(eval_fixed_point(&expr, "x", 1.0, 1), 1.5);
+    let root = eval_fixed_point(&expr, "x", 1.0, 10);
+    assert!((root - 2f64.sqrt()).abs() < 1e-12);
+
+    // Лінійна рекурентність x = x / 2 + 1 прямує до 2
+    let expr = parse_expression("x / 2 + 1").unwrap();
+    assert!((eval_fixed_point(&expr, "x", 100.0, 60) - 2.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_for_loop_over_expr() {
+    let expr = parse_expression("max(1, -x) + 2 * 3").unwrap();
+    let mut kinds = Vec::new();
+    for node in &expr {
+        kinds.push(match node {
+            Expr::Number(_) => "number",
+            Expr::Variable(_) => "variable",
+            Expr::Neg(_) => "neg",
+            Expr::BinaryOp { .. } => "binary",
+            Expr::Func { .. } => "func",
+            _ => "other",
+        });
+    }
+    assert_eq!(
+        kinds,
+        [
+            "binary", "func", "number", "neg", "variable", "binary", "number", "number"
+        ]
+    );
+    assert_eq!(expr.iter().count(), expr.node_count());
+}
+
+#[test]
+fn test_evaluate_with_functions() {
+    let mut funcs = FunctionTable::new();
+    funcs.insert("double".to_string(), Box::new(|args| args[0] * 2.0));
+    funcs.insert("hypot".to_string(), Box::new(|args| args[0].hypot(args[1])));
+
+    let eval = |input: &str| evaluate_with_functions(&parse_expression(input).unwrap(), &funcs);
+    assert_eq!(eval("double(21)"), Ok(42.0));
+    assert_eq!(
+        eval("let a = 3 in hypot(a, 4) + max(1, double(1))"),
+        Ok(7.0)
+    );
+    assert_eq!(
+        eval("triple(1)"),
+        Err(EvalError::UnknownFunction("triple".to_string()))
+    );
+}
+
+#[test]
+fn test_find_redundant_parens() {
+    assert_eq!(
+        find_redundant_parens("(2 + 3)"),
+        [Span { start: 0, end: 7 }]
+    );
+    assert_eq!(
+        find_redundant_parens("2 + (3) * (4 ^ 5)"),
+        [Span { start: 4, end: 7 }, Span { start: 10, end: 17 }]
+    );
+    assert_eq!(
+        find_redundant_parens("max((1), 2)"),
+        [Span { start: 4, end: 7 }]
+    );
+}
+
+#[test]
+fn test_find_redundant_parens_keeps_necessary() {
+    assert!(find_redundant_parens("(2 + 3) * 4").is_empty());
+    assert!(find_redundant_parens("2 - (3 - 4)").is_empty());
+    assert!(find_redundant_parens("(-2) ^ 2").is_empty());
+    // Дерево розбору лівоасоціативне, тож ці дужки змінюють його форму
+    assert!(find_redundant_parens("2 * (3 * 4)").is_empty());
+    assert!(find_redundant_parens("(2 + ").is_empty());
+}
+
+#[test]
+fn test_evaluate_mod_power() {
+    let expr = parse_expression("(2 ^ 10) - 1030").unwrap();
+    assert_eq!(evaluate_mod(&expr, 1000), Ok(994));
+    let expr = parse_expression("2 ^ 10").unwrap();
+    assert_eq!(evaluate_mod(&expr, 1000), Ok(24));
+}
+
+#[test]
+fn test_evaluate_mod_division() {
+    let expr = parse_expression("3 / 2 + 1").unwrap();
+    assert_eq!(evaluate_mod(&expr, 7), Ok(6));
+    let expr = parse_expression("1 / 4").unwrap();
+    assert_eq!(
+        evaluate_mod(&expr, 6),
+        Err(EvalError::NoModularInverse {
+            value: 4,
+            modulus: 6
+        })
+    );
+}
+
+#[test]
+fn test_rearrange_commutative_keeps_value() {
+    let expr = parse_expression("0.1 + x * 3 * 0.7 + 2 + (4 - 1) * 5").unwrap();
+    let shaken = expr.rearrange_commutative(42);
+    assert_ne!(shaken, expr);
+    assert_eq!(shaken, expr.rearrange_commutative(42));
+    let vars = HashMap::from([("x".to_string(), 1.5)]);
+    let original = evaluate_with(&expr, &vars).unwrap();
+    assert!((evaluate_with(&shaken, &vars).unwrap() - original).abs() < 1e-9);
+}
+
+#[test]
+fn test_evaluate_bounded() {
+    let expr = parse_expression("10 ^ 400 / 10 ^ 399").unwrap();
+    assert_eq!(
+        evaluate_bounded(&expr, 1e300),
+        Err(EvalError::ValueTooLarge {
+            value: f64::INFINITY,
+            max_abs: 1e300
+        })
+    );
+    let expr = parse_expression("let x = 2 ^ 10 in x * x - 1").unwrap();
+    assert_eq!(evaluate_bounded(&expr, 1e300), Ok(1_048_575.0));
+    assert!(evaluate_bounded(&expr, 1e6).is_err());
+}
+
+#[test]
+fn test_spreadsheet_percent() {
+    let config = ParserConfig {
+        spreadsheet_percent: true,
+        ..ParserConfig::default()
+    };
+    let eval = |input: &str| evaluate::<f64>(&parse_expression_with(input, &config).unwrap());
+    assert_eq!(eval("100 + 10%"), 110.0);
+    assert_eq!(eval("100 - 10%"), 90.0);
+    assert_eq!(eval("100 + 50 + 10%"), 165.0);
+    assert_eq!(eval("50%"), 0.5);
+    assert_eq!(eval("200 * 50% + 1"), 101.0);
+
+    let size = |terms: usize| {
+        let input = format!("a{}", " + 1%".repeat(terms));
+        parse_expression_with(&input, &config).unwrap().node_count()
+    };
+    assert_eq!(size(40) - size(20), size(20) - size(0));
+}
+
+#[test]
+fn test_to_rust_expr() {
+    let no_vars = HashMap::new();
+    let expr = parse_expression("2 + 3 * 4").unwrap();
+    assert_eq!(expr.to_rust_expr(&no_vars), "(2.0f64 + (3.0f64 * 4.0f64))");
+
+    let expr = parse_expression("let y = -x ^ 0.5 in max(y, 1e20) < abs(x)").unwrap();
+    let var_map = HashMap::from([("x".to_string(), "args[0]".to_string())]);
+    assert_eq!(
+        expr.to_rust_expr(&var_map),
+        "{ let y = (-args[0].powf(0.5f64)); ((y.max(1e20f64) < args[0].abs()) as i32 as f64) }"
+    );
+}
+
+#[test]
+fn test_most_expensive_op() {
+    let expr = parse_expression("x * 2 * 3 + y * 4 - 1").unwrap();
+    assert_eq!(expr.most_expensive_op(), Some('*'));
+    let tie = parse_expression("(1 + 2) / (3 - 4) / 5 + 6 - 7").unwrap();
+    assert_eq!(tie.most_expensive_op(), Some('/'));
+    let tie = parse_expression("1 + 2 - 3").unwrap();
+    assert_eq!(tie.most_expensive_op(), Some('-'));
+    assert_eq!(
+        parse_expression("-abs(x)").unwrap().most_expensive_op(),
+        None
+    );
+}
+
+#[test]
+fn test_trailing_operator_reports_position() {
+    let err = parse_expression("2 + ").unwrap_err();
+    assert!(matches!(
+        err,
+        ParseError::MissingOperand { ref op, position: 3 } if op == "+"
+    ));
+    assert_eq!(
+        err.with_context("2 + "),
+        "Missing operand after '+' at position 3\n  2 +><"
+    );
+    assert!(matches!(
+        parse_expression("(1 - 2) * -").unwrap_err(),
+        ParseError::MissingOperand { position: 11, .. }
+    ));
+}
+
+#[test]
+fn test_evaluate_with_angle_mode() {
+    let sin = parse_expression("sin(90)").unwrap();
+    assert_eq!(evaluate_with_angle_mode(&sin, AngleMode::Degrees), Ok(1.0));
+    let radians = evaluate_with_angle_mode(&sin, AngleMode::Radians).unwrap();
+    assert!((radians - 0.893_996_663_600_558).abs() < 1e-12);
+
+    let cos = parse_expression("cos(0) + cos(180)").unwrap();
+    assert_eq!(evaluate_with_angle_mode(&cos, AngleMode::Degrees), Ok(0.0));
+    let cos = parse_expression("cos(0)").unwrap();
+    assert_eq!(evaluate_with_angle_mode(&cos, AngleMode::Radians), Ok(1.0));
+}
+
+#[test]
+fn test_trig_interval_includes_extrema() {
+    let (lo, hi) = evaluate_interval_with(&parse_expression("sin(1.5)").unwrap(), &|n| {
+        (n - 0.5, n + 0.5)
+    });
+    assert_eq!(hi, 1.0);
+    assert!((lo - 1.0_f64.sin()).abs() < 1e-12);
+    let tan = parse_expression("tan(1.5)").unwrap();
+    assert_eq!(
+        evaluate_interval_with(&tan, &|n| (n - 0.5, n + 0.5)),
+        (f64::NEG_INFINITY, f64::INFINITY)
+    );
+    assert_eq!(
+        evaluate_interval(&parse_expression("tan(0)").unwrap()),
+        (0.0, 0.0)
+    );
+}
+
+#[test]
+fn test_fold_evaluate_matches_evaluate() {
+    // try_evaluate згортає дерево через Expr::fold
+    for input in [
+        "2 + 3 * 4",
+        "-(2 ^ 3) / 5 - 1",
+        "1 < 2 < 3",
+        "2 ^ -1 ^ 2",
+        "-0",
+        "max(1, 2) + abs(-3)",
+        "let x = 2 in x * sin(x)",
+        "5 ^ 3 & 0",
+    ] {
+        let expr = parse_expression(input).unwrap();
+        assert_eq!(
+            try_evaluate(&expr).map(f64::to_bits),
+            Ok(evaluate::<f64>(&expr).to_bits()),
+            "{}",
+            input
+        );
+    }
+    assert_eq!(
+        try_evaluate(&parse_expression("x + 1").unwrap()),
+        Err(EvalError::UndefinedVariable("x".to_string()))
+    );
+}
+
+#[test]
+fn test_double_star_is_power() {
+    assert_eq!(
+        parse_expression("2**3").unwrap(),
+        parse_expression("2^3").unwrap()
+    );
+    assert_eq!(
+        parse_expression("2 ** -x ** 2").unwrap(),
+        parse_expression("2 ^ -x ^ 2").unwrap()
+    );
+    let (_, spans) = parse_expression_spanned("2 ** 3").unwrap();
+    assert_eq!(spans.span, Span { start: 0, end: 6 });
+    assert!(parse_expression("2 * * 3").is_err());
+}
+
+#[test]
+fn test_double_star_is_power_with_caret_xor() {
+    let config = ParserConfig {
+        caret_is_xor: true,
+        ..ParserConfig::default()
+    };
+    let expr = parse_expression_with("2 ** 3", &config).unwrap();
+    assert_eq!(expr.as_binary_op().map(|(op, _, _)| op), Some('^'));
+    assert_eq!(evaluate::<f64>(&expr), 8.0);
+    let expr = parse_expression_with("6 ^ 3 + 2 ** 3", &config).unwrap();
+    assert_eq!(evaluate::<f64>(&expr), 13.0);
+
+    let config = ParserConfig {
+        caret_is_xor: true,
+        allowed_operators: Some(vec!['+']),
+        ..ParserConfig::default()
+    };
+    let err = parse_expression_with("2 ** 3", &config).unwrap_err();
+    assert!(matches!(err, ParseError::OperatorNotAllowed('^')));
+}
+
+#[test]
+fn test_evaluate_all_subresults() {
+    let expr = parse_expression("2 + 3 * 4").unwrap();
+    let expected = [
+        ("2", 2.0),
+        ("3", 3.0),
+        ("4", 4.0),
+        ("(3 * 4)", 12.0),
+        ("(2 + (3 * 4))", 14.0),
+    ];
+    let expected: Vec<(String, f64)> = expected
+        .iter()
+        .map(|(infix, value)| (infix.to_string(), *value))
+        .collect();
+    assert_eq!(expr.evaluate_all_subresults(), expected);
+
+    let expr = parse_expression("let x = 2 in -x").unwrap();
+    let values: Vec<f64> = expr
+        .evaluate_all_subresults()
+        .into_iter()
+        .map(|(_, value)| value)
+        .collect();
+    assert_eq!(values, [2.0, 2.0, -2.0, -2.0]);
+}
+
+/// Наступне значення лінійного конгруентного генератора в межах `0..bound`
+fn next_below(seed: &mut u32, bound: u32) -> u32 {
+    *seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+    (*seed >> 16) % bound
+}
+
+/// Випадковий вираз з `+ - * / ^ **`, унарним мінусом і дужками; токени
+/// розділено пробілами
+fn random_expression(depth: u32, seed: &mut u32) -> String {
+    let operand = |seed: &mut u32| match next_below(seed, 6) {
+        0 if depth > 0 => format!("( {} )", random_expression(depth - 1, seed)),
+        1 => format!("- {}", next_below(seed, 9) + 1),
+        2 => format!("{}.5", next_below(seed, 4)),
+        _ => (next_below(seed, 9) + 1).to_string(),
+    };
+    let mut out = operand(seed);
+    for _ in 0..next_below(seed, 5) {
+        let op = ["+", "-", "*", "/", "^", "**"][next_below(seed, 6) as usize];
+        let minus = if next_below(seed, 4) == 0 { "- " } else { "" };
+        out = format!("{} {} {}{}", out, op, minus, operand(seed));
+    }
+    out
+}
+
+/// Еталонне обчислення алгоритмом сортувальної станції Дейкстри
+///
+/// Унарний мінус — префіксний оператор між `* /` і `^`, тож `-2 ^ 2` =
+/// `-(2 ^ 2)`, а `2 ^ -1 * 4` = `(2 ^ -1) * 4`; `^` правоасоціативний.
+fn shunting_yard(input: &str) -> f64 {
+    fn precedence(op: &str) -> (u8, bool) {
+        match op {
+            "+" | "-" => (1, false),
+            "*" | "/" => (2, false),
+            "neg" => (3, true),
+            _ => (4, true),
+        }
+    }
+    fn apply(op: &str, values: &mut Vec<f64>) {
+        let r = values.pop().unwrap();
+        if op == "neg" {
+            values.push(-r);
+            return;
+        }
+        let l = values.pop().unwrap();
+        values.push(match op {
+            "+" => l + r,
+            "-" => l - r,
+            "*" => l * r,
+            "/" => l / r,
+            _ => l.powf(r),
+        });
+    }
+
+    let mut values = Vec::new();
+    let mut ops: Vec<&str> = Vec::new();
+    let mut expect_operand = true;
+    for token in input.split_whitespace() {
+        match token {
+            "(" => ops.push("("),
+            ")" => {
+                while let Some(op) = ops.pop().filter(|op| *op != "(") {
+                    apply(op, &mut values);
+                }
+                expect_operand = false;
+                continue;
+            }
+            "-" if expect_operand => ops.push("neg"),
+            "+" | "-" | "*" | "/" | "^" | "**" => {
+                let (prec, right_assoc) = precedence(token);
+                while let Some(&top) = ops.last().filter(|top| **top != "(") {
+                    let (top_prec, _) = precedence(top);
+                    if top_prec > prec || (top_prec == prec && !right_assoc) {
+                        apply(ops.pop().unwrap(), &mut values);
+                    } else {
+                        break;
+                    }
+                }
+                ops.push(token);
+            }
+            number => {
+                values.push(number.parse().unwrap());
+                expect_operand = false;
+                continue;
+            }
+        }
+        expect_operand = true;
+    }
+    while let Some(op) = ops.pop() {
+        apply(op, &mut values);
+    }
+    values.pop().unwrap()
+}
+
+#[test]
+fn test_parser_matches_shunting_yard() {
+    let mut seed = 7;
+    for _ in 0..5000 {
+        let input = random_expression(3, &mut seed);
+        let expected = shunting_yard(&input);
+        let actual = evaluate::<f64>(&parse_expression(&input).unwrap());
+        assert!(
+            actual == expected || (actual.is_nan() && expected.is_nan()),
+            "{}: parser gives {}, shunting-yard gives {}",
+            input,
+            actual,
+            expected
+        );
+    }
+}
+
+#[test]
+fn test_eval_to_string_grouping() {
+    let expr = parse_expression("2 ^ 40").unwrap();
+    assert_eq!(
+        eval_to_string(&expr, &NumberFormat::default()).unwrap(),
+        "1,099,511,627,776"
+    );
+    let expr = parse_expression("-1000000 / 3").unwrap();
+    let opts = NumberFormat {
+        group_separator: Some(' '),
+        decimal_separator: ',',
+        decimals: Some(2),
+    };
+    assert_eq!(eval_to_string(&expr, &opts).unwrap(), "-333 333,33");
+    let tiny = parse_expression("-0.001").unwrap();
+    assert_eq!(eval_to_string(&tiny, &opts).unwrap(), "0,00");
+    let opts = NumberFormat {
+        group_separator: None,
+        ..NumberFormat::default()
+    };
+    assert_eq!(eval_to_string(&expr, &opts).unwrap(), "-333333.3333333333");
+}
+
+#[test]
+fn test_parse_recover_skips_bad_tokens() {
+    let (expr, errors) = parse_recover("2 + + 3");
+    assert_eq!(expr, Some(parse_expression("2 + 3").unwrap()));
+    assert!(matches!(
+        errors.as_slice(),
+        [ParseError::UnexpectedTokenAt { position: 4, .. }]
+    ));
+
+    let (expr, errors) = parse_recover("(1 + 2 $ * 3");
+    assert_eq!(expr, Some(parse_expression("(1 + 2) * 3").unwrap()));
+    assert!(matches!(
+        errors.as_slice(),
+        [
+            ParseError::MissingClosingParenthesis,
+            ParseError::UnexpectedTokenAt { position: 7, .. },
+        ]
+    ));
+
+    let (expr, errors) = parse_recover("4 * 5 -");
+    assert_eq!(expr, Some(parse_expression("4 * 5").unwrap()));
+    assert!(matches!(
+        errors.as_slice(),
+        [ParseError::MissingOperand { position: 7, .. }]
+    ));
+}
+
+#[test]
+fn test_parse_recover_valid_and_hopeless_input() {
+    let (expr, errors) = parse_recover("let x = 2 in x * x");
+    assert_eq!(expr, Some(parse_expression("let x = 2 in x * x").unwrap()));
+    assert!(errors.is_empty());
+
+    let (expr, errors) = parse_recover("let x =");
+    assert_eq!(expr, None);
+    assert!(matches!(errors.as_slice(), [ParseError::UnexpectedEnd]));
+}
+
+#[test]
+fn test_debug_tree_round_trip() {
+    let inputs = [
+        "3 * 4 + 2",
+        "-(x ^ 2) / -0.5",
+        "let y = max(1, 2) in y < 3 < 4",
+        "round(1e300) - abs(-7)",
+    ];
+    for input in inputs {
+        let expr = parse_expression(input).unwrap();
+        let text = expr.to_debug_tree();
+        assert_eq!(Expr::from_debug_tree(&text).unwrap(), expr, "{}", text);
+    }
+    let special = Expr::Number(f64::NEG_INFINITY) + Expr::Variable("inf".to_string());
+    assert_eq!(special.to_debug_tree(), r#"("+" "-inf" "inf")"#);
+    assert_eq!(
+        Expr::from_debug_tree(&special.to_debug_tree()).unwrap(),
+        special
+    );
+
+    assert!(matches!(
+        Expr::from_debug_tree(r#"("+" "1")"#),
+        Err(ParseError::UnexpectedTokenAt { position: 1, .. })
+    ));
+    assert!(matches!(
+        Expr::from_debug_tree(r#"("+" "1" "2""#),
+        Err(ParseError::UnexpectedEnd)
+    ));
+}
+
+#[test]
+fn test_unicode_whitespace_is_skipped() {
+    let expected = parse_expression("2 + 3 * 4").unwrap();
+    for input in [
+        "2\u{a0}+\u{a0}3 *\u{a0}4",
+        "2\t+\t3\t*\t4",
+        "2 +\r\n3 *\r\n4\r\n",
+        "2\n+\n3\r*\r4",
+        "2\u{2003}+\u{3000}3\u{202f}*\u{2028}4\u{85}",
+        "\u{feff}2 +\u{200b}3 * 4",
+    ] {
+        assert_eq!(parse_expression(input).unwrap(), expected, "{:?}", input);
+    }
+    let config = ParserConfig {
+        strict: true,
+        ..ParserConfig::default()
+    };
+    assert!(matches!(
+        parse_expression_with("2\u{a0}3", &config),
+        Err(ParseError::MissingOperator { position: 2, .. })
+    ));
+}
+
+#[test]
+fn test_postorder_schedule() {
+    let expr = parse_expression("max(1, 2 - 3) * -x").unwrap();
+    let order = expr.postorder();
+    assert_eq!(order.len(), expr.node_count());
+    assert!(std::ptr::eq(*order.last().unwrap(), &expr));
+    // Кожен вузол іде після всіх своїх дітей
+    for (i, node) in order.iter().enumerate() {
+        for child in node.iter().skip(1) {
+            let position = order.iter().position(|n| std::ptr::eq(*n, child)).unwrap();
+            assert!(position < i);
+        }
+    }
+    let labels: Vec<String> = order.iter().map(|node| node.to_string()).collect();
+    assert_eq!(labels, ["1", "2", "3", "(-)", "max()", "x", "(neg)", "(*)"]);
+}
+
+#[test]
+fn test_evaluate_non_negative() {
+    let expr = parse_expression("10 - 3 - 4 + let x = 2 in x * (5 - x)").unwrap();
+    assert_eq!(evaluate_non_negative(&expr), Ok(9.0));
+    let expr = parse_expression("5 - 3 - 4 + 100").unwrap();
+    assert_eq!(
+        evaluate_non_negative(&expr),
+        Err(EvalError::NegativeResult(-2.0))
+    );
+    let expr = parse_expression("abs(-1)").unwrap();
+    assert!(evaluate_non_negative(&expr).is_err());
+}
+
+#[test]
+fn test_leftmost_and_rightmost_number() -> Result<()> {
+    let expr = parse_expression("(1 - max(8, 2 ^ 5)) * -(3 + 6) / 9")?;
+    assert_eq!(expr.leftmost_number(), Some(1.0));
+    assert_eq!(expr.rightmost_number(), Some(9.0));
+
+    let single = parse_expression("7")?;
+    assert_eq!(single.leftmost_number(), Some(7.0));
+    assert_eq!(single.rightmost_number(), Some(7.0));
+
+    let none = parse_expression("x * y")?;
+    assert_eq!(none.leftmost_number(), None);
+    assert_eq!(none.rightmost_number(), None);
+    Ok(())
+}
+
+#[test]
+fn test_tokenize_preserving_round_trips() {
+    for input in [
+        "  2 +\t3 **  x\r\n",
+        "\u{feff}max( 1 ,\u{a0}2 )",
+        "2 + + )",
+        "",
+        "42",
+    ] {
+        let tokens = tokenize_preserving(input);
+        assert_eq!(
+            tokens.iter().map(SourceToken::as_str).collect::<String>(),
+            input
+        );
+    }
+
+    assert_eq!(
+        tokenize_preserving(" 1.5e3*(y) "),
+        [
+            SourceToken::Whitespace(" ".to_string()),
+            SourceToken::Text("1.5e3".to_string()),
+            SourceToken::Text("*".to_string()),
+            SourceToken::Text("(".to_string()),
+            SourceToken::Text("y".to_string()),
+            SourceToken::Text(")".to_string()),
+            SourceToken::Whitespace(" ".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_evaluate_wrapping_i64() {
+    let eval = |input: &str| evaluate_wrapping_i64(&parse_expression(input).unwrap());
+    assert_eq!(eval("2 + 3 * 4"), 14);
+    assert_eq!(eval("-7 / 2"), -3);
+    assert_eq!(eval("2.5 + 1.4"), 4);
+    assert_eq!(eval("let x = 3 in x ^ 4 - max(x, 10)"), 71);
+}
+
+#[test]
+fn test_evaluate_wrapping_i64_wraps() {
+    let eval = |input: &str| evaluate_wrapping_i64(&parse_expression(input).unwrap());
+    assert_eq!(eval("9223372036854775807 + 1"), i64::MIN);
+    assert_eq!(eval("-9223372036854775807 - 10"), i64::MAX - 8);
+    assert_eq!(eval("4294967296 * 4294967296"), 0);
+    assert_eq!(eval("3 ^ 41"), 3_i64.wrapping_pow(41));
+    assert_eq!(eval("-(-9223372036854775807 - 1)"), i64::MIN);
+    assert_eq!(eval("(-9223372036854775807 - 1) / -1"), i64::MIN);
+    assert_eq!(eval("2 ^ 64"), 0);
+}
+
+#[test]
+fn test_value_approx_eq() -> Result<()> {
+    assert!(parse_expression("3 + 5 * (2 - 8) / 4")?.value_approx_eq(-4.5, 1e-6));
+    assert!(parse_expression("((1 + 2) * (3 + 4)) / 7")?.value_approx_eq(3.0, 1e-6));
+    assert!(parse_expression("1 / 3")?.value_approx_eq(0.333, 1e-3));
+    assert!(!parse_expression("1 / 3")?.value_approx_eq(0.333, 1e-6));
+    assert!(parse_expression("1 / 0")?.value_approx_eq(f64::INFINITY, 1e-6));
+    assert!(!parse_expression("-1 / 0")?.value_approx_eq(f64::INFINITY, f64::MAX));
+    assert!(!parse_expression("x + 1")?.value_approx_eq(1.0, f64::INFINITY));
+    Ok(())
+}
+
+#[test]
+fn test_approx_eq_never_matches_nan() -> Result<()> {
+    let nan = parse_expression("0 / 0")?;
+    for expected in [f64::NAN, 0.0, f64::INFINITY, f64::NEG_INFINITY] {
+        assert!(!nan.value_approx_eq(expected, f64::INFINITY));
+        assert!(!approx_eq(f64::NAN, expected, f64::INFINITY));
+        assert!(!approx_eq(expected, f64::NAN, f64::INFINITY));
+    }
+    assert!(approx_eq(-0.0, 0.0, 0.0));
+    assert!(!approx_eq(f64::INFINITY, f64::NEG_INFINITY, f64::INFINITY));
+    assert!(!approx_eq(f64::MAX, f64::INFINITY, f64::INFINITY));
+    Ok(())
+}
+
+#[test]
+fn test_count_matching() -> Result<()> {
+    let expr = parse_expression("2 + 5 * 4")?;
+    let big_numbers = expr.count_matching(&|node| node.as_number().is_some_and(|n| n > 3.0));
+    assert_eq!(big_numbers, 2);
+    assert_eq!(expr.count_matching(&|_| true), expr.node_count());
+    assert_eq!(
+        expr.count_matching(&|node| node.as_number().is_some()),
+        expr.count_leaves()
+    );
+
+    let nested = parse_expression("-max(x, let y = 1 in y * x)")?;
+    assert_eq!(
+        nested.count_matching(&|node| *node == Expr::Variable("x".to_string())),
+        2
+    );
+    Ok(())
+}
+
+#[test]
+fn test_comma_grouping() -> Result<()> {
+    let config = ParserConfig {
+        comma_grouping: true,
+        ..ParserConfig::default()
+    };
+    let eval =
+        |input: &str| -> Result<f64> { Ok(evaluate(&parse_expression_with(input, &config)?)) };
+    assert_eq!(eval("1,000 + 2,000")?, 3000.0);
+    assert_eq!(eval("1,234,567.5")?, 1234567.5);
+    assert_eq!(eval("-12,000e-3")?, -12.0);
+    assert_eq!(eval("abs(-1,000)")?, 1000.0);
+    assert_eq!(eval("1000 + 1")?, 1001.0);
+
+    // Коми групують розряди, тож функції з кількома аргументами вимкнено
+    for (input, name, position) in [
+        ("max(1,000)", "max", 0),
+        ("2 + min (1,2)", "min", 4),
+        ("f(1, 2)", "f", 0),
+    ] {
+        match parse_expression_with(input, &config) {
+            Err(ParseError::MultiArgCall {
+                name: found,
+                position: at,
+            }) => assert_eq!((found.as_str(), at), (name, position), "{}", input),
+            other => panic!("{}: {:?}", input, other),
+        }
+    }
+
+    // Без налаштування кома залишається роздільником аргументів
+    let expr = parse_expression("max(1,000)")?;
+    assert!(matches!(expr, Expr::Func { ref args, .. } if args.len() == 2));
+    Ok(())
+}
+
+#[test]
+fn test_comma_grouping_rejects_malformed_groups() {
+    let config = ParserConfig {
+        comma_grouping: true,
+        ..ParserConfig::default()
+    };
+    for (input, token, position) in [
+        ("1,00", "1,00", 0),
+        ("2 * 1000,000", "1000,000", 4),
+        ("1,0000 + 1", "1,0000", 0),
+        ("1,000,00.5", "1,000,00.5", 0),
+    ] {
+        match parse_expression_with(input, &config) {
+            Err(ParseError::InvalidDigitGrouping {
+                token: found,
+                position: at,
+            }) => assert_eq!((found.as_str(), at), (token, position), "{}", input),
+            other => panic!("{}: {:?}", input, other),
+        }
+    }
+    let err = parse_expression_with("2 * 1000,000", &config).unwrap_err();
+    assert_eq!(
+        err.with_context("2 * 1000,000"),
+        "Invalid digit grouping '1000,000' at position 4\n  2 * >1000,000<"
+    );
+}
+
+#[test]
+fn test_evaluate_lazy_skips_zero_products() {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    let calls = Rc::new(Cell::new(0));
+    let counter = Rc::clone(&calls);
+    let mut funcs = FunctionTable::new();
+    funcs.insert(
+        "expensive".to_string(),
+        Box::new(move |args| {
+            counter.set(counter.get() + 1);
+            args[0] + 1.0
+        }),
+    );
+    let eval =
+        |input: &str| evaluate_lazy_with_functions(&parse_expression(input).unwrap(), &funcs);
+
+    assert_eq!(eval("(1 - 1) * expensive(2)"), Ok(0.0));
+    assert_eq!(eval("expensive(expensive(2)) * 0"), Ok(0.0));
+    assert_eq!(eval("0 * undefined_var + 1"), Ok(1.0));
+    assert_eq!(eval("2 > 3 < expensive(5)"), Ok(0.0));
+    assert_eq!(calls.get(), 0);
+
+    assert_eq!(eval("2 * expensive(2)"), Ok(6.0));
+    assert_eq!(eval("1 < 2 < expensive(2)"), Ok(1.0));
+    assert_eq!(calls.get(), 2);
+}
+
+#[test]
+fn test_evaluate_lazy_matches_evaluate() {
+    for input in [
+        "3 + 5 * (2 - 8) / 4",
+        "(2 - 2) * 7 + 1",
+        "let x = 0 in x * 10 - x ^ 2",
+        "1 < 2 < 3",
+        "max(0 * 5, -2) ^ 2",
+    ] {
+        let expr = parse_expression(input).unwrap();
+        assert_eq!(expr.evaluate_lazy(), evaluate::<f64>(&expr), "{}", input);
+    }
+    // Відома відмінність: звичайне обчислення дає NaN
+    let expr = parse_expression("0 * (0 / 0)").unwrap();
+    assert!(evaluate::<f64>(&expr).is_nan());
+    assert_eq!(expr.evaluate_lazy(), 0.0);
+}
+
+#[test]
+fn test_render_diagnostic_points_at_token() {
+    let source = "2 + x";
+    let err = ParseError::UnexpectedTokenAt {
+        token: "x".to_string(),
+        position: 4,
+    };
+    let diagnostic = render_diagnostic(source, &err);
+    let lines: Vec<&str> = diagnostic.lines().collect();
+    assert_eq!(lines[0], "error: Unexpected token: x");
+    assert_eq!(lines[3], "1 | 2 + x");
+    let caret = lines[4].find('^').unwrap();
+    assert_eq!(&lines[3][caret..caret + 1], "x");
+    assert_eq!(lines[4].trim_end().len(), caret + 1);
+}
+
+#[test]
+fn test_render_diagnostic_multiline_and_unpositioned() {
+    let source = "let y = 2 in\n  y * 3 +\n";
+    let err = parse_expression(source).unwrap_err();
+    assert_eq!(
+        render_diagnostic(source, &err),
+        "error: Missing operand after '+' at position 22\n --> 2:10\n  |\n2 |   y * 3 +\n  |          ^\n"
+    );
+
+    let config = ParserConfig {
+        comma_grouping: true,
+        ..ParserConfig::default()
+    };
+    let source = "1 +\n1000,000";
+    let err = parse_expression_with(source, &config).unwrap_err();
+    assert!(render_diagnostic(source, &err).ends_with("2 | 1000,000\n  | ^^^^^^^^\n"));
+
+    let err = ParseError::MissingClosingParenthesis;
+    assert_eq!(
+        render_diagnostic("(1", &err),
+        "error: Missing closing parenthesis\n"
+    );
+}
+
+#[test]
+fn test_evaluate_with_budget() -> Result<()> {
+    // 4 бінарні оператори, унарний мінус і виклик функції
+    let expr = parse_expression("let x = 2 in max(x * 3, -x) + 1 - 4 / 2")?;
+    assert_eq!(expr.count_internal(), 7);
+    assert_eq!(evaluate_with_budget(&expr, 6), Ok(5.0));
+    assert_eq!(evaluate_with_budget(&expr, 100), Ok(5.0));
+    assert_eq!(
+        evaluate_with_budget(&expr, 5),
+        Err(EvalError::BudgetExceeded(5))
+    );
+    assert_eq!(evaluate_with_budget(&parse_expression("42")?, 0), Ok(42.0));
+    Ok(())
+}
+
+#[test]
+fn test_evaluate_with_budget_long_chain() -> Result<()> {
+    let input = vec!["1"; 200].join(" + ");
+    let expr = parse_expression(&input)?;
+    assert_eq!(evaluate_with_budget(&expr, 199), Ok(200.0));
+    assert_eq!(
+        evaluate_with_budget(&expr, 198),
+        Err(EvalError::BudgetExceeded(198))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_at_path() -> Result<()> {
+    let expr = parse_expression("2 + 3 * 4")?;
+    assert_eq!(expr.at_path(&[]), Some(&expr));
+    assert_eq!(expr.at_path(&[0]), Some(&Expr::Number(2.0)));
+    assert_eq!(expr.at_path(&[1]), Some(&parse_expression("3 * 4")?));
+    assert_eq!(expr.at_path(&[1, 0]), Some(&Expr::Number(3.0)));
+    assert_eq!(expr.at_path(&[1, 1]), Some(&Expr::Number(4.0)));
+
+    let nested = parse_expression("-max(1, let x = 5 in x)")?;
+    assert_eq!(
+        nested.at_path(&[0, 1, 1]),
+        Some(&Expr::Variable("x".to_string()))
+    );
+    assert_eq!(nested.at_path(&[0, 1, 0]), Some(&Expr::Number(5.0)));
+    Ok(())
+}
+
+#[test]
+fn test_at_path_out_of_range() -> Result<()> {
+    let expr = parse_expression("2 + 3 * 4")?;
+    assert_eq!(expr.at_path(&[2]), None);
+    assert_eq!(expr.at_path(&[0, 0]), None);
+    assert_eq!(expr.at_path(&[1, 0, 0]), None);
+    assert_eq!(expr.at_path(&[usize::MAX]), None);
+    assert_eq!(parse_expression("-7")?.at_path(&[1]), None);
+    Ok(())
+}
+
+#[test]
+fn test_replace_at_path_leaf() -> Result<()> {
+    let expr = parse_expression("let x = 2 in max(x, 1) - 3 * 4")?;
+    let edited = expr
+        .replace_at_path(&[1, 0, 1], Expr::Number(10.0))
+        .unwrap();
+    assert_eq!(edited, parse_expression("let x = 2 in max(x, 10) - 3 * 4")?);
+    // Решта дерева та сам вираз не змінилися
+    assert_eq!(edited.at_path(&[0]), expr.at_path(&[0]));
+    assert_eq!(edited.at_path(&[1, 1]), expr.at_path(&[1, 1]));
+    assert_eq!(edited.at_path(&[1, 0, 0]), expr.at_path(&[1, 0, 0]));
+    assert_eq!(expr, parse_expression("let x = 2 in max(x, 1) - 3 * 4")?);
+
+    let subtree = parse_expression("y ^ 2")?;
+    let edited = expr.replace_at_path(&[1, 1], subtree.clone()).unwrap();
+    assert_eq!(edited.at_path(&[1, 1]), Some(&subtree));
+    assert_eq!(
+        expr.replace_at_path(&[], Expr::Number(0.0)),
+        Some(Expr::Number(0.0))
+    );
+    Ok(())
+}
+
+#[test]
+fn test_replace_at_path_invalid() -> Result<()> {
+    let expr = parse_expression("2 + 3 * 4")?;
+    assert_eq!(expr.replace_at_path(&[2], Expr::Number(1.0)), None);
+    assert_eq!(expr.replace_at_path(&[0, 0], Expr::Number(1.0)), None);
+    assert_eq!(expr.replace_at_path(&[1, 1, 0], Expr::Number(1.0)), None);
+    Ok(())
+}
+
+#[test]
+fn test_evaluate_typed() -> Result<()> {
+    let eval = |input: &str| evaluate_typed(&parse_expression(input).unwrap());
+    assert_eq!(eval("3 + 5 * (2 - 8) / 4"), Ok(Value::Num(-4.5)));
+    assert_eq!(eval("max(1, 2) ^ 3"), Ok(Value::Num(8.0)));
+    assert_eq!(eval("2 * 3 > 5"), Ok(Value::Bool(true)));
+    assert_eq!(eval("1 < 3 < 2"), Ok(Value::Bool(false)));
+    assert_eq!(eval("let t = 1 < 2 in t"), Ok(Value::Bool(true)));
+    assert_eq!(Value::Bool(true).to_string(), "true");
+    assert_eq!(Value::Num(-0.0).to_string(), "0");
+    Ok(())
+}
+
+#[test]
+fn test_evaluate_typed_mismatch() {
+    let eval = |input: &str| evaluate_typed(&parse_expression(input).unwrap());
+    let mismatch = |op: &str, expected: &str, found: &str| {
+        Err(EvalError::TypeMismatch {
+            op: op.to_string(),
+            expected: expected.to_string(),
+            found: found.to_string(),
+        })
+    };
+    assert_eq!(eval("(1 < 2) + 3"), mismatch("+", "number", "boolean"));
+    assert_eq!(eval("-(2 > 1)"), mismatch("-", "number", "boolean"));
+    assert_eq!(eval("abs(1 < 2)"), mismatch("abs", "number", "boolean"));
+    assert_eq!(eval("(1 < 2) < 3"), mismatch("<", "number", "boolean"));
+    assert_eq!(
+        eval("(1 < 2) + 3").unwrap_err().to_string(),
+        "Type mismatch: '+' expects number, got boolean"
+    );
+
+    let and = Expr::BinaryOp {
+        op: '&',
+        left: Box::new(Expr::Number(1.0)),
+        right: Box::new(parse_expression("1 < 2").unwrap()),
+    };
+    assert_eq!(evaluate_typed(&and), mismatch("&", "boolean", "number"));
+}
+
+#[test]
+fn test_longest_associative_chain() -> Result<()> {
+    let input = vec!["x"; 50].join(" * ");
+    assert_eq!(parse_expression(&input)?.longest_associative_chain('*'), 50);
+    assert_eq!(
+        parse_expression("(1 + 2) + (3 + (4 + 5))")?.longest_associative_chain('+'),
+        5
+    );
+    // Найдовший ланцюжок — не біля кореня
+    let expr = parse_expression("1 + max(2 + 3 + 4 + 5, 6) + 7")?;
+    assert_eq!(expr.longest_associative_chain('+'), 4);
+    Ok(())
+}
+
+#[test]
+fn test_longest_associative_chain_mixed_operators() -> Result<()> {
+    let expr = parse_expression("1 - 2 * 3 / 4 ^ 5")?;
+    assert_eq!(expr.longest_associative_chain('+'), 1);
+    assert_eq!(expr.longest_associative_chain('-'), 2);
+    assert_eq!(
+        parse_expression("1 + 2 * (3 + 4)")?.longest_associative_chain('+'),
+        2
+    );
+    assert_eq!(parse_expression("7")?.longest_associative_chain('+'), 1);
+    Ok(())
+}
+
+#[test]
+fn test_prefix_keywords() -> Result<()> {
+    let config = ParserConfig {
+        prefix_keywords: vec![
+            ("neg".to_string(), "neg".to_string()),
+            ("abs".to_string(), "abs".to_string()),
+        ],
+        ..ParserConfig::default()
+    };
+    let mut funcs = FunctionTable::new();
+    funcs.insert("neg".to_string(), Box::new(|args| -args[0]));
+    let eval = |input: &str| -> Result<f64> {
+        let expr = parse_expression_with(input, &config)?;
+        Ok(evaluate_with_functions(&expr, &funcs)?)
+    };
+
+    assert_eq!(eval("neg 5")?, -5.0);
+    assert_eq!(eval("abs -3")?, 3.0);
+    assert_eq!(eval("neg 2 ^ 2 + 1")?, -3.0);
+    assert_eq!(eval("10 * neg neg 2")?, 20.0);
+    assert_eq!(eval("abs (1 - 4) * 2")?, 6.0);
+    assert_eq!(
+        parse_expression_with("neg 5", &config)?,
+        Expr::Func {
+            name: "neg".to_string(),
+            args: vec![Expr::Number(5.0)],
+        }
+    );
+    assert!(matches!(
+        parse_expression_with("1 + neg", &config),
+        Err(ParseError::MissingOperand { .. })
+    ));
+
+    // Без налаштування `neg` — звичайна змінна
+    assert_eq!(parse_expression("neg")?, Expr::Variable("neg".to_string()));
+    Ok(())
+}
+
+#[test]
+fn test_prefix_keyword_for_binary_builtin() {
+    let config = ParserConfig {
+        prefix_keywords: vec![("biggest".to_string(), "max".to_string())],
+        ..ParserConfig::default()
+    };
+    assert!(matches!(
+        parse_expression_with("biggest 3", &config),
+        Err(ParseError::WrongArity { ref name, expected: 2, found: 1 }) if name == "max"
+    ));
+}
+
+#[test]
+fn test_format_number_special_values() {
+    assert_eq!(format_number(f64::INFINITY), "inf");
+    assert_eq!(format_number(f64::NEG_INFINITY), "-inf");
+    assert_eq!(format_number(f64::NAN), "nan");
+    assert_eq!(format_number(-f64::NAN), "nan");
+    assert_eq!(format_number(0.0), "0");
+    assert_eq!(format_number(-0.0), "0");
+    assert_eq!(Expr::Number(f64::NAN).to_infix(), "nan");
+    assert_eq!(
+        eval_to_string(
+            &parse_expression("0 / 0").unwrap(),
+            &NumberFormat::default()
+        ),
+        Ok("nan".to_string())
+    );
+}
+
+#[test]
+fn test_format_number_shortest_round_trip() {
+    for (value, text) in [
+        (42.0, "42".to_string()),
+        (-2.5, "-2.5".to_string()),
+        (0.1, "0.1".to_string()),
+        (1.0 / 3.0, "0.3333333333333333".to_string()),
+        (1e21, format!("1{}", "0".repeat(21))),
+        (1.5e-7, "0.00000015".to_string()),
+        (f64::MAX, format!("17976931348623157{}", "0".repeat(292))),
+        (
+            f64::MIN_POSITIVE,
+            format!("0.{}22250738585072014", "0".repeat(307)),
+        ),
+    ] {
+        assert_eq!(format_number(value), text);
+        assert_eq!(format_number(value).parse::<f64>(), Ok(value));
+    }
+}
+
+#[test]
+fn test_evaluate_with_history() -> Result<()> {
+    let expr = parse_expression("2 + 3 * 4")?;
+    let (value, history) = expr.evaluate_with_history();
+    assert_eq!(value, 14.0);
+    assert_eq!(history[&vec![]], 14.0);
+    assert_eq!(history[&vec![1]], 12.0);
+    assert_eq!(history[&vec![1, 0]], 3.0);
+    assert_eq!(history.len(), 5);
+    for (path, value) in &history {
+        assert_eq!(expr.at_path(path).map(evaluate), Some(*value));
+    }
     Ok(())
 }